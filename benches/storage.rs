@@ -0,0 +1,139 @@
+//! Listing, lookup and write costs with a campus-sized inventory.
+//!
+//! Run with `cargo bench --bench storage`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use jump_rs::api::devices::DeviceQuery;
+use jump_rs::devices::list_devices;
+use jump_rs::models::{Device, MacAddress, NetworkInterface};
+use jump_rs::storage::{
+    DeviceIndex, JsonStorage, Mutation, SharedStorage, SqliteStorage, StorageBackend,
+};
+
+const DEVICE_COUNT: usize = 10_000;
+const IMPORT_COUNT: usize = 1_000;
+
+fn mac(index: usize) -> String {
+    let [.., a, b, c] = index.to_be_bytes();
+    format!("02:00:00:{a:02X}:{b:02X}:{c:02X}")
+}
+
+fn inventory() -> Vec<Device> {
+    devices(0..DEVICE_COUNT)
+}
+
+fn devices(indices: std::ops::Range<usize>) -> Vec<Device> {
+    indices
+        .map(|index| {
+            let interfaces = vec![NetworkInterface::new(&mac(index)).unwrap()];
+            let tags = if index % 10 == 0 {
+                vec!["lab".to_string()]
+            } else {
+                Vec::new()
+            };
+            let mut device =
+                Device::new(format!("host-{index}"), interfaces, None, 9, None).with_tags(tags);
+            device.sort_order = u32::try_from(index).unwrap();
+            device
+        })
+        .collect()
+}
+
+fn storage_benches(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let _guard = runtime.enter();
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("devices.json");
+
+    let devices = inventory();
+    let mut backend = JsonStorage::new(path.to_str().unwrap());
+    backend
+        .apply(devices.iter().cloned().map(Mutation::Add).collect())
+        .unwrap();
+    let storage = SharedStorage::new(Box::new(backend), Duration::ZERO);
+
+    let middle = &devices[DEVICE_COUNT / 2];
+    let middle_mac: MacAddress = mac(DEVICE_COUNT / 2).parse().unwrap();
+    let lab = DeviceQuery {
+        tag: vec!["lab".to_string()],
+        ..DeviceQuery::default()
+    };
+
+    c.bench_function("list all devices", |b| {
+        b.iter(|| list_devices(black_box(&storage), &DeviceQuery::default()));
+    });
+    c.bench_function("list devices by tag", |b| {
+        b.iter(|| list_devices(black_box(&storage), &lab));
+    });
+    c.bench_function("get device by id", |b| {
+        b.iter(|| storage.get(black_box(&middle.id)));
+    });
+    c.bench_function("find device by mac", |b| {
+        b.iter(|| storage.snapshot().by_mac(black_box(middle_mac)).is_some());
+    });
+    c.bench_function("rebuild index", |b| {
+        b.iter_with_setup(|| devices.clone(), DeviceIndex::new);
+    });
+}
+
+/// Writes through the writer task into each backend, the file or database
+/// write included.
+fn write_benches(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let _guard = runtime.enter();
+    let dir = tempfile::TempDir::new().unwrap();
+    let json = dir.path().join("devices.json");
+    let sqlite = dir.path().join("jumpers.db");
+    let backends: [(&str, Box<dyn StorageBackend>); 2] = [
+        ("json", Box::new(JsonStorage::new(json.to_str().unwrap()))),
+        (
+            "sqlite",
+            Box::new(SqliteStorage::open(sqlite.to_str().unwrap()).unwrap()),
+        ),
+    ];
+
+    for (name, mut backend) in backends {
+        backend
+            .apply(inventory().into_iter().map(Mutation::Add).collect())
+            .unwrap();
+        let storage = SharedStorage::new(backend, Duration::ZERO);
+        let id = storage.get_all()[DEVICE_COUNT / 2].id.clone();
+
+        c.bench_function(&format!("update one device ({name})"), |b| {
+            b.iter(|| {
+                let mut device = storage.get(&id).unwrap();
+                device.favorite = !device.favorite;
+                runtime.block_on(storage.update(&id, device)).unwrap()
+            });
+        });
+
+        let mut group = c.benchmark_group("import");
+        group.sample_size(10);
+        group.bench_function(format!("import 1k into 10k ({name})"), |b| {
+            b.iter_custom(|iterations| {
+                let mut elapsed = Duration::ZERO;
+                for _ in 0..iterations {
+                    let imported = devices(DEVICE_COUNT..DEVICE_COUNT + IMPORT_COUNT);
+                    let removals = imported
+                        .iter()
+                        .map(|device| Mutation::Remove(device.id.clone()))
+                        .collect();
+                    let start = Instant::now();
+                    runtime
+                        .block_on(storage.apply(imported.into_iter().map(Mutation::Add).collect()))
+                        .unwrap();
+                    elapsed += start.elapsed();
+                    runtime.block_on(storage.apply(removals)).unwrap();
+                }
+                elapsed
+            });
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, storage_benches, write_benches);
+criterion_main!(benches);
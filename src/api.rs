@@ -1,6 +1,7 @@
+mod admin;
 mod arp;
 pub mod devices;
-mod wol;
+pub mod wol;
 
 use crate::error::ApiError;
 use axum::Router;
@@ -25,6 +26,7 @@ pub fn openapi() -> utoipa::openapi::OpenApi {
     doc.merge(devices::DeviceApiDoc::openapi());
     doc.merge(wol::WolApiDoc::openapi());
     doc.merge(arp::NetworkApiDoc::openapi());
+    doc.merge(admin::AdminApiDoc::openapi());
     doc
 }
 
@@ -34,6 +36,7 @@ pub fn router() -> Router {
         .merge(devices::router())
         .merge(wol::router())
         .merge(arp::router())
+        .merge(admin::router())
 }
 
 /// Creates and configures the API documentation router.
@@ -0,0 +1,89 @@
+use axum::{Extension, Json, Router, http::StatusCode, routing::post};
+use serde::Deserialize;
+use tracing::{info, instrument};
+use utoipa::{OpenApi, ToSchema};
+
+use crate::api::ApiResult;
+use crate::backup::{BackupInfo, Backups, RestoreReport};
+use crate::error::ErrorResponse;
+use crate::storage::SharedStorage;
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        create_backup,
+        restore_backup,
+    ),
+    components(
+        schemas(
+            crate::backup::BackupInfo,
+            crate::backup::RestoreReport,
+            RestoreRequest,
+            crate::error::ErrorResponse,
+        )
+    ),
+    tags(
+        (name = "admin", description = "Maintenance operations")
+    )
+)]
+pub struct AdminApiDoc;
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/api/admin/backup", post(create_backup))
+        .route("/api/admin/restore", post(restore_backup))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RestoreRequest {
+    /// Name of the backup, as listed by `jumpers backup list`
+    #[schema(example = "devices-20260101T030000123Z.json")]
+    pub name: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/backup",
+    operation_id = "createBackup",
+    tag = "admin",
+    summary = "Back up all devices",
+    description = "Stores a snapshot of every device, including those in the trash, in the configured backup target and deletes the oldest snapshots beyond `backup.keep`. Restore a snapshot with `POST /api/admin/restore`.",
+    responses(
+        (status = 201, description = "Backup created", body = BackupInfo),
+        (status = 500, description = "Backup target misconfigured", body = ErrorResponse),
+        (status = 502, description = "Backup target unreachable", body = ErrorResponse)
+    )
+)]
+#[instrument(skip_all)]
+pub async fn create_backup(
+    Extension(storage): Extension<SharedStorage>,
+) -> ApiResult<(StatusCode, Json<BackupInfo>)> {
+    let backups = Backups::open(&crate::config::get().backup)?;
+    let backup = backups.back_up(&storage).await?;
+    info!(backup = %backup.name, "Backup created on request");
+    Ok((StatusCode::CREATED, Json(backup)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/restore",
+    operation_id = "restoreBackup",
+    tag = "admin",
+    summary = "Replace all devices with a backup",
+    description = "Checks every device in the snapshot, backs up the current devices and then replaces them, trash included, in one change. Use this rather than `jumpers backup restore` while the server runs.",
+    request_body = RestoreRequest,
+    responses(
+        (status = 200, description = "Backup restored", body = RestoreReport),
+        (status = 404, description = "Backup not found", body = ErrorResponse),
+        (status = 500, description = "Backup invalid or target misconfigured", body = ErrorResponse),
+        (status = 502, description = "Backup target unreachable", body = ErrorResponse)
+    )
+)]
+#[instrument(skip_all, fields(backup = %request.name))]
+pub async fn restore_backup(
+    Extension(storage): Extension<SharedStorage>,
+    Json(request): Json<RestoreRequest>,
+) -> ApiResult<Json<RestoreReport>> {
+    let backups = Backups::open(&crate::config::get().backup)?;
+    Ok(Json(backups.restore(&storage, &request.name).await?))
+}
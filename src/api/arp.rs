@@ -7,6 +7,7 @@ use crate::{api::ApiResult, error::ErrorResponse};
 #[derive(OpenApi)]
 #[openapi(
     paths(
+        neighbor_lookup,
         arp_lookup,
     ),
     components(
@@ -23,12 +24,15 @@ use crate::{api::ApiResult, error::ErrorResponse};
 pub struct NetworkApiDoc;
 
 pub fn router() -> Router {
-    Router::new().route("/api/arp-lookup", post(arp_lookup))
+    Router::new()
+        .route("/api/neighbor-lookup", post(neighbor_lookup))
+        .route("/api/arp-lookup", post(arp_lookup))
 }
 
 #[derive(Debug, serde::Deserialize, ToSchema)]
 pub struct ArpLookupRequest {
-    /// IPv4 address to look up in the ARP table
+    /// IPv4 or IPv6 address to look up. Link-local IPv6 addresses may include
+    /// an interface scope such as `fe80::1%eth0`.
     #[schema(example = "192.168.1.100")]
     pub ip: String,
 }
@@ -42,22 +46,43 @@ pub struct ArpLookupResponse {
 
 #[utoipa::path(
     post,
-    path = "/api/arp-lookup",
-    operation_id = "arpLookup",
+    path = "/api/neighbor-lookup",
+    operation_id = "neighborLookup",
     tag = "network",
     summary = "Look up MAC address by IP",
-    description = "Queries the system's ARP table to find the MAC address for a given IPv4 address. The IP must have recently communicated with this host to appear in the ARP table.",
+    description = "Resolves the MAC address for an IPv4 address through ARP or for an IPv6 address through NDP neighbor discovery. The target must be on the same layer-2 network as this host.",
     request_body(content = ArpLookupRequest, description = "IP address to look up"),
     responses(
         (status = 200, description = "MAC address found", body = ArpLookupResponse),
         (status = 400, description = "Invalid IP address format", body = ErrorResponse),
-        (status = 404, description = "IP not found in ARP table", body = ErrorResponse),
-        (status = 500, description = "Error querying ARP table", body = ErrorResponse)
+        (status = 404, description = "IP not found in the neighbor table", body = ErrorResponse),
+        (status = 500, description = "Error querying the neighbor table", body = ErrorResponse)
     )
 )]
 #[instrument(skip_all, fields(target_ip = %req.ip))]
-pub async fn arp_lookup(Json(req): Json<ArpLookupRequest>) -> ApiResult<Json<ArpLookupResponse>> {
+pub async fn neighbor_lookup(
+    Json(req): Json<ArpLookupRequest>,
+) -> ApiResult<Json<ArpLookupResponse>> {
     let mac = crate::devices::arp_lookup(&req.ip)?;
-    info!(mac = %mac, "ARP lookup successful");
+    info!(mac = %mac, "Neighbor lookup successful");
     Ok(Json(ArpLookupResponse { mac }))
 }
+
+#[utoipa::path(
+    post,
+    path = "/api/arp-lookup",
+    operation_id = "arpLookup",
+    tag = "network",
+    summary = "Look up MAC address by IP (alias)",
+    description = "Alias of `/api/neighbor-lookup`, kept for existing clients. Accepts both IPv4 and IPv6 addresses.",
+    request_body(content = ArpLookupRequest, description = "IP address to look up"),
+    responses(
+        (status = 200, description = "MAC address found", body = ArpLookupResponse),
+        (status = 400, description = "Invalid IP address format", body = ErrorResponse),
+        (status = 404, description = "IP not found in the neighbor table", body = ErrorResponse),
+        (status = 500, description = "Error querying the neighbor table", body = ErrorResponse)
+    )
+)]
+pub async fn arp_lookup(req: Json<ArpLookupRequest>) -> ApiResult<Json<ArpLookupResponse>> {
+    neighbor_lookup(req).await
+}
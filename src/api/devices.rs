@@ -1,34 +1,59 @@
 use crate::api::ApiResult;
 use crate::error::ErrorResponse;
-use crate::models::Device;
+use crate::models::{Device, FieldError, NetworkInterface, ValidationError};
 use crate::storage::SharedStorage;
 use axum::{
     Router,
-    extract::{Extension, Json, Path},
-    http::StatusCode,
+    extract::{Extension, FromRequest, Json, Path, RawQuery, Request},
+    http::{
+        HeaderMap, HeaderName, HeaderValue, StatusCode,
+        header::{ACCEPT, CONTENT_TYPE, ETAG, IF_MATCH, LINK, WARNING},
+    },
+    response::{IntoResponse, Response},
     routing::{get, post, put},
 };
-use tracing::{info, instrument};
-use utoipa::{OpenApi, ToSchema};
+use axum_extra::extract::Query;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::{info, instrument, warn};
+use utoipa::{IntoParams, OpenApi, ToSchema};
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
         get_devices,
+        get_device,
         export_devices,
         import_devices,
         create_device,
         update_device,
+        patch_device,
         delete_device,
+        get_trash,
+        restore_device,
+        reorder_devices,
     ),
     components(
         schemas(
             crate::models::Device,
+            crate::models::NetworkInterface,
+            crate::models::FieldError,
             crate::error::ErrorResponse,
+            InterfaceRequest,
             ExportResponse,
+            DeviceSort,
+            SortOrder,
+            ExportFormat,
             ImportRequest,
+            ImportFormat,
+            ImportMode,
+            IdConflict,
+            ImportReport,
+            ImportChange,
             CreateDeviceRequest,
             UpdateDeviceRequest,
+            PatchDeviceRequest,
+            ReorderRequest,
         )
     ),
     tags(
@@ -37,50 +62,255 @@ use utoipa::{OpenApi, ToSchema};
 )]
 pub struct DeviceApiDoc;
 
+/// A device response carrying its revision in the `ETag` header.
+type TaggedDevice = ([(HeaderName, String); 1], Json<Device>);
+
 pub fn router() -> Router {
     Router::new()
         .route("/api/devices", get(get_devices).post(create_device))
         .route("/api/devices/export", get(export_devices))
         .route("/api/devices/import", post(import_devices))
+        .route("/api/devices/order", put(reorder_devices))
+        .route("/api/devices/trash", get(get_trash))
+        .route("/api/devices/{id}/restore", post(restore_device))
         .route(
             "/api/devices/{id}",
-            put(update_device).delete(delete_device),
+            get(get_device)
+                .put(update_device)
+                .patch(patch_device)
+                .delete(delete_device),
         )
 }
 
+#[derive(Debug, Clone, Default, serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeviceQuery {
+    /// Only return devices that carry every given tag. Repeat to combine tags.
+    #[serde(default)]
+    #[param(example = json!(["lab", "gpu"]))]
+    pub tag: Vec<String>,
+    /// Metadata filter as `key:value`, matching devices whose `key` contains
+    /// `value` (case-insensitive). A bare `key` matches devices that have it set.
+    /// Repeat to combine filters.
+    #[serde(default)]
+    #[param(example = json!(["location:rack 4"]))]
+    pub meta: Vec<String>,
+    /// Full-text search: only return devices whose name, description, IP
+    /// address or a MAC address contains every word (case-insensitive).
+    #[param(example = "office aa:bb")]
+    pub q: Option<String>,
+    /// Sort by this field; without it devices keep their dashboard order.
+    pub sort: Option<DeviceSort>,
+    #[serde(default)]
+    pub order: SortOrder,
+    /// Return at most this many devices; the `Link` header points at the next page.
+    #[param(minimum = 1, example = 50)]
+    pub limit: Option<usize>,
+    /// Where the page starts, from the `next` link of the page before.
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceSort {
+    /// Alphabetically, ignoring case.
+    Name,
+    CreatedAt,
+    /// Numerically by IP address, then host names; devices without one come last.
+    Ip,
+    /// By when the neighbor watcher last saw the device; devices never seen come last.
+    LastSeen,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
 #[utoipa::path(
     get,
     path = "/api/devices",
     operation_id = "getDevices",
     tag = "devices",
     summary = "List all devices",
-    description = "Returns a list of all registered devices that can receive Wake-on-LAN packets.",
+    description = "Returns a list of all registered devices that can receive Wake-on-LAN packets, optionally filtered by tag, metadata and a full-text search. \
+        `sort` and `order` set the order, which is otherwise the dashboard's. \
+        With `limit` the list is split into pages: the `Link` header holds the `first` page and, unless this is the last one, the `next` page, each with the request's other parameters kept. \
+        A page continues right after where the page before ended, even when devices were changed or deleted in between. A `cursor` of a list with another `sort` is rejected.",
+    params(DeviceQuery),
     responses(
-        (status = 200, description = "List of all devices", body = Vec<Device>),
+        (status = 200, description = "List of all devices", body = Vec<Device>, headers(
+            ("Link" = String, description = "Only when paging: `</api/devices?limit=50&cursor=…>; rel=\"next\", </api/devices?limit=50>; rel=\"first\"`")
+        )),
+        (status = 400, description = "Invalid `limit` or `cursor`", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse)
     )
 )]
 #[instrument(skip_all)]
 pub async fn get_devices(
     Extension(storage): Extension<SharedStorage>,
-) -> ApiResult<Json<Vec<Device>>> {
-    let devices = crate::devices::list_devices(&storage);
-    info!(count = devices.len(), "Devices retrieved");
-    Ok(Json(devices))
+    Query(query): Query<DeviceQuery>,
+    RawQuery(raw_query): RawQuery,
+) -> ApiResult<Response> {
+    let page = crate::devices::search_devices(&storage, &query)?;
+    info!(count = page.devices.len(), "Devices retrieved");
+    let paged = query.limit.is_some() || query.cursor.is_some();
+    let links = paged.then(|| page_links(raw_query.as_deref(), page.next_cursor.as_deref()));
+    let mut response = Json(page.devices).into_response();
+    if let Some(links) = links.and_then(|links| HeaderValue::from_str(&links).ok()) {
+        response.headers_mut().insert(LINK, links);
+    }
+    Ok(response)
+}
+
+/// The `Link` header of a page: the `next` page, when there is one, and the
+/// `first`, with every other parameter of the request kept as it was sent.
+/// Cursors are URL-safe base64, which needs no escaping.
+fn page_links(raw_query: Option<&str>, next_cursor: Option<&str>) -> String {
+    let params: Vec<&str> = raw_query
+        .unwrap_or_default()
+        .split('&')
+        .filter(|param| !param.is_empty() && !param.starts_with("cursor="))
+        .collect();
+    let link = |cursor: Option<&str>, rel: &str| {
+        let mut params: Vec<String> = params.iter().map(ToString::to_string).collect();
+        params.extend(cursor.map(|cursor| format!("cursor={cursor}")));
+        if params.is_empty() {
+            format!("</api/devices>; rel=\"{rel}\"")
+        } else {
+            format!("</api/devices?{}>; rel=\"{rel}\"", params.join("&"))
+        }
+    };
+    let mut links: Vec<String> = next_cursor
+        .map(|cursor| link(Some(cursor), "next"))
+        .into_iter()
+        .collect();
+    links.push(link(None, "first"));
+    links.join(", ")
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/devices/{id}",
+    operation_id = "getDevice",
+    tag = "devices",
+    summary = "Get a device",
+    description = "Returns a single device. The `ETag` header carries its revision for use with `If-Match` on updates.",
+    params(
+        ("id" = String, Path, description = "Device ID", example = "V1StGXR8_Z5jdHi6B")
+    ),
+    responses(
+        (status = 200, description = "The device", body = Device,
+            headers(("ETag" = String, description = "Current revision of the device"))),
+        (status = 404, description = "Device not found", body = ErrorResponse)
+    )
+)]
+#[instrument(skip_all, fields(device_id = %id))]
+pub async fn get_device(
+    Extension(storage): Extension<SharedStorage>,
+    Path(id): Path<String>,
+) -> ApiResult<TaggedDevice> {
+    let device = crate::devices::get_device(&storage, &id)?;
+    Ok(([(ETAG, device.etag())], Json(device)))
 }
 
 #[derive(Debug, Clone, serde::Serialize, ToSchema)]
 pub struct ExportResponse {
     #[schema(example = "Gaming PC")]
     pub name: String,
-    #[schema(example = "00:11:22:33:44:55")]
-    pub mac_address: String,
+    pub interfaces: Vec<NetworkInterface>,
     #[schema(example = 9)]
     pub port: u16,
     #[schema(example = "192.168.1.100")]
     pub ip_address: Option<String>,
     #[schema(example = "My main gaming rig")]
     pub description: Option<String>,
+    #[schema(example = json!(["gaming"]))]
+    pub tags: Vec<String>,
+    #[schema(example = json!({"location": "Office"}))]
+    pub metadata: BTreeMap<String, String>,
+    pub favorite: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, ToSchema, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// Portable device list, without ids and timestamps.
+    Json,
+    /// Portable device list as a spreadsheet.
+    Csv,
+    /// Every field, ids and timestamps included, in the layout of the device file.
+    Backup,
+    /// `/etc/ethers`, so `etherwake <name>` wakes a device.
+    Ethers,
+    /// Home Assistant `wake_on_lan` switches.
+    HomeAssistant,
+    /// Ansible INI inventory with a group per tag.
+    AnsibleIni,
+    /// Ansible YAML inventory with a group per tag.
+    AnsibleYaml,
+}
+
+impl ExportFormat {
+    pub const fn content_type(self) -> &'static str {
+        match self {
+            Self::Json | Self::Backup => "application/json; charset=utf-8",
+            Self::Csv => crate::devices::csv::CONTENT_TYPE,
+            Self::Ethers | Self::AnsibleIni => "text/plain; charset=utf-8",
+            Self::HomeAssistant | Self::AnsibleYaml => "application/yaml; charset=utf-8",
+        }
+    }
+
+    /// Name and extension of a downloaded export.
+    pub const fn file_name(self) -> (&'static str, &'static str) {
+        match self {
+            Self::Json => ("devices", "json"),
+            Self::Csv => ("devices", "csv"),
+            Self::Backup => ("backup", "json"),
+            Self::Ethers => ("ethers", "txt"),
+            Self::HomeAssistant => ("home-assistant", "yaml"),
+            Self::AnsibleIni => ("inventory", "ini"),
+            Self::AnsibleYaml => ("inventory", "yaml"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    /// File format of the export. Without it, `Accept: text/csv` selects CSV
+    /// and anything else JSON.
+    pub format: Option<ExportFormat>,
+}
+
+impl ExportQuery {
+    pub fn format(&self, headers: &HeaderMap) -> ExportFormat {
+        self.format.unwrap_or_else(|| {
+            if has_media_type(headers, &ACCEPT, "text/csv") {
+                ExportFormat::Csv
+            } else {
+                ExportFormat::Json
+            }
+        })
+    }
+}
+
+/// Whether the `name` header lists `media_type`, ignoring parameters.
+fn has_media_type(headers: &HeaderMap, name: &HeaderName, media_type: &str) -> bool {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value.split(',').any(|item| {
+                item.split(';')
+                    .next()
+                    .is_some_and(|item| item.trim().eq_ignore_ascii_case(media_type))
+            })
+        })
 }
 
 #[utoipa::path(
@@ -89,33 +319,178 @@ pub struct ExportResponse {
     operation_id = "exportDevices",
     tag = "devices",
     summary = "Export all devices",
-    description = "Exports all devices in a portable format suitable for backup or migration. Does not include internal fields like id and created_at.",
+    description = "Exports all devices in a portable format suitable for backup or migration. Does not include internal fields like id and created_at. \
+        `format=backup` exports every field, ids and timestamps included, as a `{\"version\": …, \"devices\": […]}` document that the import endpoint restores. \
+        `format=ethers`, `format=home_assistant`, `format=ansible_ini` and `format=ansible_yaml` write configuration for other tools: `/etc/ethers` lines for `etherwake <name>`, Home Assistant `wake_on_lan` switches, and an Ansible inventory with a group per tag. \
+        The CSV form has a header row with `name`, `mac_address`, `ip_address`, `port`, `description`, `tags` and `favorite`, followed by one column per metadata key; \
+        several MAC addresses or tags in one cell are separated by `;`.",
+    params(ExportQuery),
     responses(
-        (status = 200, description = "Exported device list", body = Vec<ExportResponse>),
+        (status = 200, description = "Exported device list", content(
+            (Vec<ExportResponse> = "application/json"),
+            (String = "text/csv", example = "name,mac_address,ip_address,port,description,tags,favorite,location\nGaming PC,00:11:22:33:44:55,192.168.1.100,9,My main gaming rig,gaming,false,Office\n"),
+            (String = "text/plain", example = "00:11:22:33:44:55 gaming-pc\n"),
+            (String = "application/yaml", example = "switch:\n  - platform: wake_on_lan\n    name: \"Gaming PC\"\n    mac: \"00:11:22:33:44:55\"\n")
+        )),
         (status = 500, description = "Internal server error", body = ErrorResponse)
     )
 )]
 #[instrument(skip_all)]
 pub async fn export_devices(
     Extension(storage): Extension<SharedStorage>,
-) -> ApiResult<Json<Vec<ExportResponse>>> {
-    let result = crate::devices::export_devices(&storage);
-    info!(count = result.len(), "Devices exported");
-    Ok(Json(result))
+    Query(query): Query<ExportQuery>,
+    headers: HeaderMap,
+) -> ApiResult<Response> {
+    let count = storage.get_all().len();
+    let response = match query.format(&headers) {
+        ExportFormat::Json => Json(crate::devices::export_devices(&storage)).into_response(),
+        format => {
+            let body = crate::devices::export_file(&storage, format)?;
+            ([(CONTENT_TYPE, format.content_type())], body).into_response()
+        }
+    };
+    info!(count, "Devices exported");
+    Ok(response)
 }
 
-#[derive(Debug, Clone, serde::Deserialize, ToSchema)]
+#[derive(Debug, Clone, Default, serde::Deserialize, ToSchema)]
 pub struct ImportRequest {
     #[schema(example = "Gaming PC")]
     pub name: String,
+    /// Shorthand for a device with a single interface.
     #[schema(example = "00:11:22:33:44:55")]
-    pub mac_address: String,
+    pub mac_address: Option<String>,
+    pub interfaces: Option<Vec<InterfaceRequest>>,
     #[schema(example = 9)]
     pub port: Option<u16>,
     #[schema(example = "192.168.1.100")]
     pub ip_address: Option<String>,
     #[schema(example = "My main gaming rig")]
     pub description: Option<String>,
+    #[serde(default)]
+    #[schema(example = json!(["gaming"]))]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    #[schema(example = json!({"location": "Office"}))]
+    pub metadata: BTreeMap<String, String>,
+    #[serde(default)]
+    pub favorite: bool,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Deserialize,
+    serde::Serialize,
+    ToSchema,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// Add every entry as a new device; entries whose MAC is taken conflict.
+    #[default]
+    Append,
+    /// Update the device owning an entry's MAC address, or add a new one.
+    Upsert,
+    /// Move every device to the trash, then add the entries.
+    Replace,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    serde::Deserialize,
+    serde::Serialize,
+    ToSchema,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    /// Portable device list, as exported with `format=json`.
+    Json,
+    /// Portable device list as a spreadsheet.
+    Csv,
+    /// Backup export, keeping ids and timestamps.
+    Backup,
+    /// UpSnap's device records.
+    Upsnap,
+    /// wolweb's `devices.json`.
+    Wolweb,
+    /// Backup of the Android "Wake On Lan" app.
+    Android,
+    /// `/etc/ethers`: one MAC address and host name per line.
+    Ethers,
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportQuery {
+    /// File format of the body. Without it, `Content-Type: text/csv` selects
+    /// CSV and anything else is told apart by its content.
+    pub format: Option<ImportFormat>,
+    /// How the entries combine with the stored devices.
+    #[serde(default)]
+    pub mode: ImportMode,
+    /// Report what the import would change without saving anything.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// For backup imports: what to do with a device whose id is already taken.
+    #[serde(default)]
+    pub id_conflict: IdConflict,
+}
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, ToSchema, clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum IdConflict {
+    /// Report the device as a conflict.
+    #[default]
+    Reject,
+    /// Import the device under a new id.
+    Remap,
+}
+
+/// What an import changes, entry by entry.
+#[derive(Debug, Clone, Default, serde::Serialize, ToSchema)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    /// The format the file was read as.
+    pub format: Option<ImportFormat>,
+    /// Entries added as new devices.
+    pub created: Vec<ImportChange>,
+    /// Stored devices updated from an entry with a matching MAC address.
+    pub updated: Vec<ImportChange>,
+    /// Entries matching a stored device that already has the same values.
+    pub skipped: Vec<ImportChange>,
+    /// Entries that cannot be imported; a real import fails while there are any.
+    pub conflicts: Vec<ImportChange>,
+    /// Stored devices moved to the trash by a replace.
+    pub removed: Vec<ImportChange>,
+    /// Fields of another tool's file that have no counterpart here and were
+    /// left out, e.g. `[0].wake_cron`.
+    pub warnings: Vec<FieldError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, ToSchema)]
+pub struct ImportChange {
+    /// The entry, as `[0]` for JSON or `line 2` for CSV; absent for removed devices.
+    #[schema(example = "[0]")]
+    pub entry: Option<String>,
+    #[schema(example = "Gaming PC")]
+    pub name: String,
+    /// The stored device concerned, absent for new devices and conflicts.
+    #[schema(example = "V1StGXR8_Z5jdHi6B")]
+    pub device_id: Option<String>,
+    #[schema(example = "MAC address 00:11:22:33:44:55 is already used by Office PC")]
+    pub reason: Option<String>,
 }
 
 #[utoipa::path(
@@ -124,36 +499,106 @@ pub struct ImportRequest {
     operation_id = "importDevices",
     tag = "devices",
     summary = "Import devices",
-    description = "Imports multiple devices from a portable format. Useful for restoring backups or migrating from another system.",
-    request_body(content = Vec<ImportRequest>, description = "List of devices to import"),
+    description = "Imports multiple devices from a portable format. Useful for restoring backups or migrating from another system. \
+        Send `Content-Type: text/csv` to import the CSV form of the export; errors then name the line of the offending row, e.g. `line 3.port`. \
+        `mode=upsert` updates the devices whose MAC address matches an entry instead of failing, and `mode=replace` moves every device to the trash first. \
+        With `dry_run=true` nothing is saved and the response reports what the import would do. \
+        A JSON object instead of a list is read as a backup export: its devices keep their ids and timestamps, and `id_conflict` decides whether a device whose id is taken is rejected or imported under a new id. \
+        Files of other Wake-on-LAN tools are read too: UpSnap's device records, wolweb's `devices.json`, the Android \"Wake On Lan\" app's backup and `/etc/ethers`. \
+        The format is detected from the content unless `format` names it, and the report's `warnings` list every field that has no counterpart here; \
+        a real import sends them as `Warning` headers instead.",
+    params(ImportQuery),
+    request_body(description = "List of devices to import", content(
+        (Vec<ImportRequest> = "application/json"),
+        (String = "text/csv"),
+        (String = "text/plain")
+    )),
     responses(
-        (status = 201, description = "Devices imported successfully", body = Vec<Device>),
-        (status = 400, description = "Validation error (e.g., invalid MAC address)", body = ErrorResponse),
+        (status = 200, description = "Dry run: what the import would change", body = ImportReport),
+        (status = 201, description = "Devices imported successfully; the created devices followed by the updated ones", body = Vec<Device>,
+            headers(("Warning" = String, description = "One `299` warning per field left out, e.g. `299 - \"[0].wake_cron: UpSnap schedules are not imported\"`"))),
+        (status = 400, description = "Validation error or conflicting entries; `errors` lists every invalid field", body = ErrorResponse),
+        (status = 409, description = "A device id or MAC address was taken by another change while importing", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse)
     )
 )]
-#[instrument(skip_all, fields(count = req.len()))]
+#[instrument(skip_all, fields(mode = ?query.mode, dry_run = query.dry_run))]
 pub async fn import_devices(
     Extension(storage): Extension<SharedStorage>,
-    Json(req): Json<Vec<ImportRequest>>,
-) -> ApiResult<(StatusCode, Json<Vec<Device>>)> {
-    let devices = crate::devices::import_devices(&storage, req)?;
-    info!(count = devices.len(), "Devices imported");
-    Ok((StatusCode::CREATED, Json(devices)))
+    Query(query): Query<ImportQuery>,
+    request: Request,
+) -> ApiResult<Response> {
+    let csv_body = has_media_type(request.headers(), &CONTENT_TYPE, "text/csv");
+    let query = ImportQuery {
+        format: query
+            .format
+            .or_else(|| csv_body.then_some(ImportFormat::Csv)),
+        ..query
+    };
+    let content = match String::from_request(request, &()).await {
+        Ok(content) => content,
+        Err(rejection) => return Ok(rejection.into_response()),
+    };
+    let imported = crate::devices::import_file(&storage, &content, &query).await?;
+    if query.dry_run {
+        return Ok(Json(imported.report).into_response());
+    }
+    let report = &imported.report;
+    info!(
+        created = report.created.len(),
+        updated = report.updated.len(),
+        skipped = report.skipped.len(),
+        removed = report.removed.len(),
+        warnings = report.warnings.len(),
+        "Devices imported"
+    );
+    let mut response = (StatusCode::CREATED, Json(imported.devices)).into_response();
+    for warning in &report.warnings {
+        let (entry, field) = warning
+            .field
+            .split_once('.')
+            .unwrap_or((&warning.field, ""));
+        warn!(entry, field, message = %warning.message, "Import field not imported");
+        let text = format!("{}: {}", warning.field, warning.message).replace('"', "\\\"");
+        if let Ok(value) = HeaderValue::from_bytes(format!("299 - \"{text}\"").as_bytes()) {
+            response.headers_mut().append(WARNING, value);
+        }
+    }
+    Ok(response)
+}
+
+#[derive(Debug, Clone, serde::Deserialize, ToSchema)]
+pub struct InterfaceRequest {
+    #[schema(example = "00:11:22:33:44:55")]
+    pub mac_address: String,
+    #[schema(example = "onboard")]
+    pub label: Option<String>,
+    /// Directed broadcast address for this NIC, e.g. the subnet's `.255`.
+    #[schema(example = "192.168.1.255")]
+    pub broadcast: Option<String>,
 }
 
 #[derive(Debug, Clone, serde::Deserialize, ToSchema)]
 pub struct CreateDeviceRequest {
     #[schema(example = "Gaming PC")]
     pub name: String,
+    /// Shorthand for a device with a single interface.
     #[schema(example = "00:11:22:33:44:55")]
-    pub mac_address: String,
+    pub mac_address: Option<String>,
+    /// Every NIC of the device. Use instead of `mac_address`.
+    pub interfaces: Option<Vec<InterfaceRequest>>,
     #[schema(example = "192.168.1.100")]
     pub ip_address: Option<String>,
     #[schema(example = 9)]
     pub port: Option<u16>,
     #[schema(example = "My main gaming rig")]
     pub description: Option<String>,
+    #[schema(example = json!(["gaming"]))]
+    pub tags: Option<Vec<String>>,
+    #[schema(example = json!({"location": "Office"}))]
+    pub metadata: Option<BTreeMap<String, String>>,
+    #[schema(example = false)]
+    pub favorite: Option<bool>,
 }
 
 #[utoipa::path(
@@ -162,11 +607,12 @@ pub struct CreateDeviceRequest {
     operation_id = "createDevice",
     tag = "devices",
     summary = "Create a new device",
-    description = "Creates a new device that can receive Wake-on-LAN packets. The MAC address must be valid.",
+    description = "Creates a new device that can receive Wake-on-LAN packets. Give either a single `mac_address` or a list of `interfaces`; every MAC address must be valid.",
     request_body(content = CreateDeviceRequest, description = "Device to create"),
     responses(
         (status = 201, description = "Device created successfully", body = Device),
-        (status = 400, description = "Validation error (e.g., invalid MAC address)", body = ErrorResponse),
+        (status = 400, description = "Validation error; `errors` lists every invalid field", body = ErrorResponse),
+        (status = 409, description = "MAC address already registered to another device", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse)
     )
 )]
@@ -175,23 +621,87 @@ pub async fn create_device(
     Extension(storage): Extension<SharedStorage>,
     Json(req): Json<CreateDeviceRequest>,
 ) -> ApiResult<(StatusCode, Json<Device>)> {
-    let device = crate::devices::create_device(&storage, req)?;
+    let device = crate::devices::create_device(&storage, req).await?;
     info!(device_id = %device.id, "Device created");
     Ok((StatusCode::CREATED, Json(device)))
 }
 
+/// Full replacement for `PUT`: omitted optional fields are cleared or reset
+/// to their defaults.
 #[derive(Debug, Clone, serde::Deserialize, ToSchema)]
 pub struct UpdateDeviceRequest {
     #[schema(example = "Gaming PC")]
-    pub name: Option<String>,
+    pub name: String,
+    /// Shorthand for a device with a single interface.
     #[schema(example = "00:11:22:33:44:55")]
     pub mac_address: Option<String>,
+    /// Every NIC of the device. Use instead of `mac_address`.
+    pub interfaces: Option<Vec<InterfaceRequest>>,
     #[schema(example = "192.168.1.100")]
     pub ip_address: Option<String>,
+    /// Defaults to the configured Wake-on-LAN port.
     #[schema(example = 9)]
     pub port: Option<u16>,
     #[schema(example = "Updated description")]
     pub description: Option<String>,
+    #[serde(default)]
+    #[schema(example = json!(["gaming", "lan-party"]))]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    #[schema(example = json!({"location": "Living room"}))]
+    pub metadata: BTreeMap<String, String>,
+    #[serde(default)]
+    #[schema(example = true)]
+    pub favorite: bool,
+}
+
+/// JSON Merge Patch (RFC 7396) for `PATCH`: absent fields keep their value,
+/// `null` clears them.
+#[derive(Debug, Clone, Default, serde::Deserialize, ToSchema)]
+pub struct PatchDeviceRequest {
+    /// Cannot be cleared.
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>, example = "Gaming PC")]
+    pub name: Option<Option<String>>,
+    /// Changes the MAC of the first interface. Cannot be cleared.
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>, example = "00:11:22:33:44:55")]
+    pub mac_address: Option<Option<String>>,
+    /// Replaces every interface. Cannot be cleared.
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<Vec<InterfaceRequest>>)]
+    pub interfaces: Option<Option<Vec<InterfaceRequest>>>,
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>, example = "192.168.1.100")]
+    pub ip_address: Option<Option<String>>,
+    /// `null` resets the port to the configured default.
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<u16>, example = 9)]
+    pub port: Option<Option<u16>>,
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>, example = "Updated description")]
+    pub description: Option<Option<String>>,
+    /// Replaces the device's tags.
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<Vec<String>>, example = json!(["gaming", "lan-party"]))]
+    pub tags: Option<Option<Vec<String>>>,
+    /// Merged into the device's metadata; a `null` value removes that key.
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<BTreeMap<String, Option<String>>>, example = json!({"location": "Living room", "owner": null}))]
+    pub metadata: Option<Option<BTreeMap<String, Option<String>>>>,
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<bool>, example = true)]
+    pub favorite: Option<Option<bool>>,
+}
+
+/// Tells an explicit `null` (`Some(None)`) apart from an absent field, which
+/// `#[serde(default)]` leaves at `None`.
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::Deserialize<'de>,
+{
+    <Option<T> as serde::Deserialize>::deserialize(deserializer).map(Some)
 }
 
 #[utoipa::path(
@@ -199,16 +709,20 @@ pub struct UpdateDeviceRequest {
     path = "/api/devices/{id}",
     operation_id = "updateDevice",
     tag = "devices",
-    summary = "Update a device",
-    description = "Updates an existing device. Only provided fields will be updated (partial update).",
+    summary = "Replace a device",
+    description = "Replaces an existing device. Omitted optional fields are cleared (`ip_address`, `description`, `tags`, `metadata`), `port` falls back to the configured default and `favorite` to false. Use PATCH to change single fields. Send the device's `ETag` as `If-Match` to reject the update when someone else changed the device in the meantime.",
     params(
-        ("id" = String, Path, description = "Device ID", example = "V1StGXR8_Z5jdHi6B")
+        ("id" = String, Path, description = "Device ID", example = "V1StGXR8_Z5jdHi6B"),
+        ("If-Match" = Option<String>, Header, description = "ETag the update is based on", example = "\"3\"")
     ),
-    request_body(content = UpdateDeviceRequest, description = "Fields to update"),
+    request_body(content = UpdateDeviceRequest, description = "The complete device"),
     responses(
-        (status = 200, description = "Device updated successfully", body = Device),
-        (status = 400, description = "Validation error (e.g., invalid MAC address)", body = ErrorResponse),
+        (status = 200, description = "Device replaced successfully", body = Device,
+            headers(("ETag" = String, description = "New revision of the device"))),
+        (status = 400, description = "Validation error; `errors` lists every invalid field", body = ErrorResponse),
         (status = 404, description = "Device not found", body = ErrorResponse),
+        (status = 409, description = "MAC address already registered to another device", body = ErrorResponse),
+        (status = 412, description = "The device changed since the revision given in If-Match", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse)
     )
 )]
@@ -216,11 +730,60 @@ pub struct UpdateDeviceRequest {
 pub async fn update_device(
     Extension(storage): Extension<SharedStorage>,
     Path(id): Path<String>,
+    headers: HeaderMap,
     Json(req): Json<UpdateDeviceRequest>,
-) -> ApiResult<Json<Device>> {
-    let updated = crate::devices::update_device(&storage, &id, req)?;
-    info!("Device updated");
-    Ok(Json(updated))
+) -> ApiResult<TaggedDevice> {
+    let updated = crate::devices::update_device(&storage, &id, req, if_match(&headers)?).await?;
+    info!(revision = updated.revision, "Device updated");
+    Ok(([(ETAG, updated.etag())], Json(updated)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/devices/{id}",
+    operation_id = "patchDevice",
+    tag = "devices",
+    summary = "Update device fields",
+    description = "Applies a JSON Merge Patch (RFC 7396): absent fields keep their value and `null` clears `ip_address`, `description`, `tags` or `metadata`. Metadata is merged key by key, with `null` removing a key. Honors `If-Match` like PUT.",
+    params(
+        ("id" = String, Path, description = "Device ID", example = "V1StGXR8_Z5jdHi6B"),
+        ("If-Match" = Option<String>, Header, description = "ETag the patch is based on", example = "\"3\"")
+    ),
+    request_body(content = PatchDeviceRequest, content_type = "application/merge-patch+json", description = "Fields to change"),
+    responses(
+        (status = 200, description = "Device updated successfully", body = Device,
+            headers(("ETag" = String, description = "New revision of the device"))),
+        (status = 400, description = "Validation error; `errors` lists every invalid field", body = ErrorResponse),
+        (status = 404, description = "Device not found", body = ErrorResponse),
+        (status = 409, description = "MAC address already registered to another device", body = ErrorResponse),
+        (status = 412, description = "The device changed since the revision given in If-Match", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+#[instrument(skip_all, fields(device_id = %id))]
+pub async fn patch_device(
+    Extension(storage): Extension<SharedStorage>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<PatchDeviceRequest>,
+) -> ApiResult<TaggedDevice> {
+    let updated = crate::devices::patch_device(&storage, &id, req, if_match(&headers)?).await?;
+    info!(revision = updated.revision, "Device patched");
+    Ok(([(ETAG, updated.etag())], Json(updated)))
+}
+
+fn if_match(headers: &HeaderMap) -> Result<Option<&str>, ValidationError> {
+    headers
+        .get(IF_MATCH)
+        .map(|value| {
+            value.to_str().map_err(|_| {
+                ValidationError::Fields(vec![FieldError::new(
+                    "If-Match",
+                    "Must be an ETag such as \"3\"",
+                )])
+            })
+        })
+        .transpose()
 }
 
 #[utoipa::path(
@@ -229,12 +792,12 @@ pub async fn update_device(
     operation_id = "deleteDevice",
     tag = "devices",
     summary = "Delete a device",
-    description = "Permanently deletes a device from the system.",
+    description = "Moves a device to the trash. It can be restored until it is purged after `storage.trash_retention_days`.",
     params(
         ("id" = String, Path, description = "Device ID", example = "V1StGXR8_Z5jdHi6B")
     ),
     responses(
-        (status = 204, description = "Device deleted successfully"),
+        (status = 204, description = "Device moved to the trash"),
         (status = 404, description = "Device not found", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse)
     )
@@ -244,7 +807,85 @@ pub async fn delete_device(
     Extension(storage): Extension<SharedStorage>,
     Path(id): Path<String>,
 ) -> ApiResult<StatusCode> {
-    crate::devices::delete_device(&storage, &id)?;
+    crate::devices::delete_device(&storage, &id).await?;
     info!("Device deleted");
     Ok(StatusCode::NO_CONTENT)
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/devices/trash",
+    operation_id = "getTrash",
+    tag = "devices",
+    summary = "List deleted devices",
+    description = "Returns the devices in the trash. Each carries the time it was deleted in `deleted_at`.",
+    responses(
+        (status = 200, description = "Devices in the trash", body = Vec<Device>)
+    )
+)]
+#[instrument(skip_all)]
+pub async fn get_trash(Extension(storage): Extension<SharedStorage>) -> Json<Arc<[Device]>> {
+    let devices = crate::devices::list_trash(&storage);
+    info!(count = devices.len(), "Trash retrieved");
+    Json(devices)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/devices/{id}/restore",
+    operation_id = "restoreDevice",
+    tag = "devices",
+    summary = "Restore a deleted device",
+    description = "Takes a device out of the trash and returns it. Fails if one of its MAC addresses has been registered to another device since it was deleted.",
+    params(
+        ("id" = String, Path, description = "Device ID", example = "V1StGXR8_Z5jdHi6B")
+    ),
+    responses(
+        (status = 200, description = "Device restored", body = Device,
+            headers(("ETag" = String, description = "New revision of the device"))),
+        (status = 404, description = "Device not in the trash", body = ErrorResponse),
+        (status = 409, description = "A MAC address now belongs to another device", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+#[instrument(skip_all, fields(device_id = %id))]
+pub async fn restore_device(
+    Extension(storage): Extension<SharedStorage>,
+    Path(id): Path<String>,
+) -> ApiResult<TaggedDevice> {
+    let device = crate::devices::restore_device(&storage, &id).await?;
+    info!("Device restored");
+    Ok(([(ETAG, device.etag())], Json(device)))
+}
+
+#[derive(Debug, Clone, serde::Deserialize, ToSchema)]
+pub struct ReorderRequest {
+    /// Device IDs in their new order. Listed devices are rearranged within the
+    /// positions they already occupy; unlisted devices stay where they are.
+    #[schema(example = json!(["V1StGXR8_Z5jdHi6B", "Uakgb_J5m9g-0JDMbcJqL"]))]
+    pub ids: Vec<String>,
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/devices/order",
+    operation_id = "reorderDevices",
+    tag = "devices",
+    summary = "Reorder devices",
+    description = "Sets the display order of several devices in one request and returns the full, reordered device list. Every device whose position changes gets a new revision, and with it a new `ETag`.",
+    request_body(content = ReorderRequest, description = "Device IDs in their new order"),
+    responses(
+        (status = 200, description = "Devices reordered successfully", body = Vec<Device>),
+        (status = 404, description = "One of the IDs does not exist", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+#[instrument(skip_all, fields(count = req.ids.len()))]
+pub async fn reorder_devices(
+    Extension(storage): Extension<SharedStorage>,
+    Json(req): Json<ReorderRequest>,
+) -> ApiResult<Json<Arc<[Device]>>> {
+    let devices = crate::devices::reorder_devices(&storage, &req.ids).await?;
+    info!("Devices reordered");
+    Ok(Json(devices))
+}
@@ -1,6 +1,11 @@
-use axum::{Extension, Router, extract::Path, http::StatusCode, routing::post};
+use axum::{
+    Extension, Router,
+    extract::{Path, Query},
+    http::StatusCode,
+    routing::post,
+};
 use tracing::{info, instrument};
-use utoipa::OpenApi;
+use utoipa::{IntoParams, OpenApi};
 
 use crate::{api::ApiResult, error::ErrorResponse, storage::SharedStorage};
 
@@ -24,19 +29,29 @@ pub fn router() -> Router {
     Router::new().route("/api/devices/{id}/wake", post(wake_device))
 }
 
+#[derive(Debug, Default, serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct WakeQuery {
+    /// MAC address of the interface to wake. All interfaces are woken when omitted.
+    #[param(example = "00:11:22:33:44:55")]
+    pub interface: Option<String>,
+}
+
 #[utoipa::path(
     post,
     path = "/api/devices/{id}/wake",
     operation_id = "wakeDevice",
     tag = "wol",
     summary = "Wake a device",
-    description = "Sends a Wake-on-LAN magic packet to the device. The target device must have WoL enabled in BIOS and be connected via ethernet.",
+    description = "Sends a Wake-on-LAN magic packet to every interface of the device, or only to the one selected with `interface`. The target device must have WoL enabled in BIOS and be connected via ethernet.",
     params(
-        ("id" = String, Path, description = "Device ID", example = "V1StGXR8_Z5jdHi6B")
+        ("id" = String, Path, description = "Device ID", example = "V1StGXR8_Z5jdHi6B"),
+        WakeQuery,
     ),
     responses(
         (status = 204, description = "WoL packet sent successfully"),
-        (status = 404, description = "Device not found", body = ErrorResponse),
+        (status = 400, description = "Invalid interface MAC address", body = ErrorResponse),
+        (status = 404, description = "Device or interface not found", body = ErrorResponse),
         (status = 500, description = "Network error while sending packet", body = ErrorResponse)
     )
 )]
@@ -44,8 +59,9 @@ pub fn router() -> Router {
 pub async fn wake_device(
     Extension(storage): Extension<SharedStorage>,
     Path(id): Path<String>,
+    Query(query): Query<WakeQuery>,
 ) -> ApiResult<StatusCode> {
-    crate::devices::wake_device(&storage, &id)?;
+    crate::devices::wake_device(&storage, &id, query.interface.as_deref())?;
     info!(device_id = %id, "WoL packet sent");
     Ok(StatusCode::NO_CONTENT)
 }
@@ -0,0 +1,362 @@
+//! Snapshots of the device store, kept in a local directory or an
+//! S3-compatible bucket.
+//!
+//! A snapshot is the device file layout of the JSON backend, so it can be
+//! read by any later release and restored into either backend.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use object_store::aws::AmazonS3Builder;
+use object_store::local::LocalFileSystem;
+use object_store::path::Path;
+use object_store::prefix::PrefixStore;
+use object_store::{ObjectStore, PutPayload};
+use serde::Serialize;
+use thiserror::Error;
+use time::format_description::BorrowedFormatItem;
+use time::{OffsetDateTime, PrimitiveDateTime};
+use tracing::{info, instrument, warn};
+use utoipa::ToSchema;
+
+use crate::config::{BackupConfig, BackupTarget};
+use crate::models::Device;
+use crate::storage::{Mutation, SharedStorage, StorageError, decode_devices, encode_devices};
+
+const NAME_PREFIX: &str = "devices-";
+const NAME_SUFFIX: &str = ".json";
+/// Layout of the timestamp in a snapshot's name, e.g. `20260101T030000123Z`.
+const TIMESTAMP_FORMAT: &str = "[year][month][day]T[hour][minute][second][subsecond digits:3]Z";
+
+#[derive(Debug, Error)]
+pub enum BackupError {
+    #[error("Backup store error: {0}")]
+    Store(#[from] object_store::Error),
+
+    #[error("Backup {name} is not a valid snapshot: {source}")]
+    Invalid { name: String, source: StorageError },
+
+    #[error("Backup not found: {0}")]
+    NotFound(String),
+
+    #[error("Invalid backup configuration: {0}")]
+    Config(String),
+
+    #[error("Backup could not be restored: {0}")]
+    Restore(#[source] StorageError),
+}
+
+/// A stored snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct BackupInfo {
+    #[schema(example = "devices-20260101T030000123Z.json")]
+    pub name: String,
+    /// Size of the snapshot in bytes.
+    #[schema(example = 2048)]
+    pub size: u64,
+    #[schema(value_type = String)]
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// The outcome of [`Backups::restore`].
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RestoreReport {
+    /// Number of devices restored, including those in the trash.
+    pub restored: usize,
+    /// Backup of the devices the restore replaced.
+    pub previous: BackupInfo,
+}
+
+/// The configured backup target. Snapshots are named after the time they
+/// were taken, so sorting by name sorts them by age.
+#[derive(Debug, Clone)]
+pub struct Backups {
+    store: Arc<dyn ObjectStore>,
+    keep: usize,
+}
+
+impl Backups {
+    pub fn new(store: Arc<dyn ObjectStore>, keep: usize) -> Self {
+        Self { store, keep }
+    }
+
+    /// Connects to the target selected by `[backup] target`, creating the
+    /// local directory if needed.
+    pub fn open(config: &BackupConfig) -> Result<Self, BackupError> {
+        let store: Arc<dyn ObjectStore> = match config.target {
+            BackupTarget::Local => {
+                std::fs::create_dir_all(&config.directory).map_err(|err| {
+                    BackupError::Config(format!("cannot create {}: {err}", config.directory))
+                })?;
+                Arc::new(LocalFileSystem::new_with_prefix(&config.directory)?)
+            }
+            BackupTarget::S3 => {
+                let s3 = &config.s3;
+                if s3.bucket.is_empty() {
+                    return Err(BackupError::Config(
+                        "backup.s3.bucket is required for the s3 target".to_string(),
+                    ));
+                }
+                let mut builder = AmazonS3Builder::from_env()
+                    .with_bucket_name(&s3.bucket)
+                    .with_allow_http(s3.allow_http);
+                if !s3.region.is_empty() {
+                    builder = builder.with_region(&s3.region);
+                }
+                if !s3.endpoint.is_empty() {
+                    builder = builder.with_endpoint(&s3.endpoint);
+                }
+                if !s3.access_key_id.is_empty() {
+                    builder = builder.with_access_key_id(&s3.access_key_id);
+                }
+                if !s3.secret_access_key.is_empty() {
+                    builder = builder.with_secret_access_key(&s3.secret_access_key);
+                }
+                let bucket = builder.build()?;
+                if s3.prefix.is_empty() {
+                    Arc::new(bucket)
+                } else {
+                    Arc::new(PrefixStore::new(bucket, s3.prefix.as_str()))
+                }
+            }
+        };
+        Ok(Self::new(store, config.keep))
+    }
+
+    /// Stores a snapshot of `devices` and deletes the ones beyond `keep`.
+    #[instrument(skip_all, fields(count = devices.len()))]
+    pub async fn create(&self, devices: &[Device]) -> Result<BackupInfo, BackupError> {
+        let now = OffsetDateTime::now_utc();
+        let name = format!(
+            "{NAME_PREFIX}{:04}{:02}{:02}T{:02}{:02}{:02}{:03}Z{NAME_SUFFIX}",
+            now.year(),
+            u8::from(now.month()),
+            now.day(),
+            now.hour(),
+            now.minute(),
+            now.second(),
+            now.millisecond()
+        );
+        let created_at = created_at(&name).unwrap_or(now);
+        let content = encode_devices(devices).map_err(|source| BackupError::Invalid {
+            name: name.clone(),
+            source,
+        })?;
+        let size = content.len() as u64;
+        self.store
+            .put(&Path::from(name.as_str()), PutPayload::from(content))
+            .await?;
+        info!(backup = %name, "Backup created");
+
+        self.prune().await?;
+        Ok(BackupInfo {
+            name,
+            size,
+            created_at,
+        })
+    }
+
+    /// Backs up every device of `storage`, including those in the trash.
+    pub async fn back_up(&self, storage: &SharedStorage) -> Result<BackupInfo, BackupError> {
+        let devices: Vec<Device> = storage.snapshot().stored().cloned().collect();
+        self.create(&devices).await
+    }
+
+    /// Replaces every device of `storage` with the snapshot called `name`,
+    /// in one change. The snapshot is checked and the current devices are
+    /// backed up first, so a restore can itself be undone.
+    pub async fn restore(
+        &self,
+        storage: &SharedStorage,
+        name: &str,
+    ) -> Result<RestoreReport, BackupError> {
+        let devices = self.read(name).await?;
+        let previous = self.back_up(storage).await?;
+        let restored = devices.len();
+
+        let mut mutations = vec![Mutation::Clear];
+        mutations.extend(devices.into_iter().map(Mutation::Add));
+        storage
+            .apply(mutations)
+            .await
+            .map_err(BackupError::Restore)?;
+        info!(backup = %name, restored, "Backup restored");
+        Ok(RestoreReport { restored, previous })
+    }
+
+    /// Every snapshot, newest first.
+    pub async fn list(&self) -> Result<Vec<BackupInfo>, BackupError> {
+        let listing = self.store.list_with_delimiter(None).await?;
+        let mut backups: Vec<BackupInfo> = listing
+            .objects
+            .into_iter()
+            .filter_map(|object| {
+                let name = object.location.filename()?.to_string();
+                let created_at = created_at(&name)?;
+                Some(BackupInfo {
+                    name,
+                    size: object.size,
+                    created_at,
+                })
+            })
+            .collect();
+        backups.sort_by(|a, b| b.name.cmp(&a.name));
+        Ok(backups)
+    }
+
+    /// Reads a snapshot and checks every device in it, so a damaged or
+    /// hand-edited snapshot is rejected before it can replace live data.
+    pub async fn read(&self, name: &str) -> Result<Vec<Device>, BackupError> {
+        if created_at(name).is_none() {
+            return Err(BackupError::NotFound(name.to_string()));
+        }
+        let bytes = match self.store.get(&Path::from(name)).await {
+            Ok(result) => result.bytes().await?,
+            Err(object_store::Error::NotFound { .. }) => {
+                return Err(BackupError::NotFound(name.to_string()));
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let invalid = |source| BackupError::Invalid {
+            name: name.to_string(),
+            source,
+        };
+        let content = std::str::from_utf8(&bytes).map_err(|err| {
+            invalid(std::io::Error::new(std::io::ErrorKind::InvalidData, err).into())
+        })?;
+        decode_devices(content).map_err(invalid)
+    }
+
+    async fn prune(&self) -> Result<(), BackupError> {
+        if self.keep == 0 {
+            return Ok(());
+        }
+        for expired in self.list().await?.into_iter().skip(self.keep) {
+            self.store
+                .delete(&Path::from(expired.name.as_str()))
+                .await?;
+            info!(backup = %expired.name, "Old backup deleted");
+        }
+        Ok(())
+    }
+}
+
+fn timestamp_format() -> Vec<BorrowedFormatItem<'static>> {
+    time::format_description::parse(TIMESTAMP_FORMAT).expect("valid backup timestamp format")
+}
+
+/// When the snapshot called `name` was taken, or `None` for any other file.
+fn created_at(name: &str) -> Option<OffsetDateTime> {
+    let timestamp = name.strip_prefix(NAME_PREFIX)?.strip_suffix(NAME_SUFFIX)?;
+    PrimitiveDateTime::parse(timestamp, &timestamp_format())
+        .ok()
+        .map(PrimitiveDateTime::assume_utc)
+}
+
+/// Backs up the devices every `interval`, starting one interval from now.
+pub fn spawn_scheduler(storage: SharedStorage, backups: Backups, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+        loop {
+            ticks.tick().await;
+            if let Err(err) = backups.back_up(&storage).await {
+                warn!(error = %err, "Scheduled backup failed");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::JsonStorage;
+    use crate::storage::tests::create_test_device;
+    use tempfile::TempDir;
+
+    fn local_backups(dir: &TempDir, keep: usize) -> Backups {
+        let store = LocalFileSystem::new_with_prefix(dir.path()).unwrap();
+        Backups::new(Arc::new(store), keep)
+    }
+
+    #[tokio::test]
+    async fn created_backup_is_listed_and_reads_back() {
+        let dir = TempDir::new().unwrap();
+        let backups = local_backups(&dir, 0);
+        let devices = vec![create_test_device("Server")];
+
+        let created = backups.create(&devices).await.unwrap();
+
+        assert_eq!(backups.list().await.unwrap(), vec![created.clone()]);
+        assert_eq!(backups.read(&created.name).await.unwrap(), devices);
+    }
+
+    #[tokio::test]
+    async fn only_newest_backups_are_kept() {
+        let dir = TempDir::new().unwrap();
+        let backups = local_backups(&dir, 2);
+        for name in [
+            "devices-20250101T000000000Z.json",
+            "devices-20250102T000000000Z.json",
+        ] {
+            std::fs::write(dir.path().join(name), encode_devices(&[]).unwrap()).unwrap();
+        }
+        std::fs::write(dir.path().join("notes.txt"), "unrelated").unwrap();
+
+        let created = backups.create(&[]).await.unwrap();
+
+        let names: Vec<String> = backups
+            .list()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|b| b.name)
+            .collect();
+        assert_eq!(
+            names,
+            vec![created.name, "devices-20250102T000000000Z.json".to_string()]
+        );
+        assert!(dir.path().join("notes.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn invalid_snapshot_is_rejected() {
+        let dir = TempDir::new().unwrap();
+        let backups = local_backups(&dir, 0);
+        let mut device = create_test_device("");
+        device.port = 0;
+        let content = encode_devices(&[device]).unwrap();
+        std::fs::write(dir.path().join("devices-20250101T000000000Z.json"), content).unwrap();
+
+        let result = backups.read("devices-20250101T000000000Z.json").await;
+        assert!(matches!(result, Err(BackupError::Invalid { .. })));
+        let result = backups.read("../devices.json").await;
+        assert!(matches!(result, Err(BackupError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn restore_replaces_devices_and_keeps_the_previous_ones() {
+        let dir = TempDir::new().unwrap();
+        let backups = local_backups(&dir, 0);
+        let snapshot = vec![create_test_device("Restored")];
+        let name = "devices-20250101T000000000Z.json";
+        std::fs::write(dir.path().join(name), encode_devices(&snapshot).unwrap()).unwrap();
+        let store_dir = TempDir::new().unwrap();
+        let path = store_dir.path().join("devices.json");
+        let storage = SharedStorage::new(
+            Box::new(JsonStorage::open(path.to_str().unwrap(), 0).unwrap()),
+            Duration::ZERO,
+        );
+        let current = create_test_device("Current");
+        storage.add(current.clone()).await.unwrap();
+
+        let report = backups.restore(&storage, name).await.unwrap();
+
+        assert_eq!(report.restored, 1);
+        assert_eq!(*storage.get_all(), snapshot[..]);
+        assert_eq!(
+            backups.read(&report.previous.name).await.unwrap(),
+            [current]
+        );
+    }
+}
@@ -1,7 +1,13 @@
+mod backup;
+mod devices;
 mod openapi;
+mod storage;
 mod user;
 
-use crate::cli::{openapi::OpenApiCommands, user::UserCommands};
+use crate::cli::{
+    backup::BackupCommands, devices::DeviceCommands, openapi::OpenApiCommands,
+    storage::StorageCommands, user::UserCommands,
+};
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 
@@ -27,13 +33,34 @@ pub enum Commands {
         #[command(subcommand)]
         command: OpenApiCommands,
     },
+
+    /// Manage device storage
+    Storage {
+        #[command(subcommand)]
+        command: StorageCommands,
+    },
+
+    /// Export devices to a file or import them from one
+    Devices {
+        #[command(subcommand)]
+        command: DeviceCommands,
+    },
+
+    /// Create, list and restore backups
+    Backup {
+        #[command(subcommand)]
+        command: BackupCommands,
+    },
 }
 
 impl Commands {
-    pub fn execute(self) -> Result<()> {
+    pub async fn execute(self) -> Result<()> {
         match self {
             Self::User { command } => command.run(),
             Self::OpenApi { command } => command.run(),
+            Self::Storage { command } => command.run(),
+            Self::Devices { command } => command.run().await,
+            Self::Backup { command } => command.run().await,
         }
     }
 }
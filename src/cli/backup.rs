@@ -0,0 +1,110 @@
+use anyhow::{Context, Result, bail};
+use clap::{Args, Subcommand};
+use dialoguer::Confirm;
+use time::format_description::well_known::Rfc3339;
+
+use crate::backup::Backups;
+use crate::config;
+use crate::storage::{Mutation, StorageLock, open_backend};
+
+#[derive(Subcommand)]
+pub enum BackupCommands {
+    /// Back up every device to the configured target
+    Create,
+
+    /// List the stored backups, newest first
+    List,
+
+    /// Replace all devices with the contents of a backup
+    #[command(arg_required_else_help = true)]
+    Restore(RestoreArgs),
+}
+
+#[derive(Args)]
+pub struct RestoreArgs {
+    /// Name of the backup, as shown by `jumpers backup list`
+    name: String,
+
+    /// Restore without asking for confirmation
+    #[arg(short = 'y', long)]
+    yes: bool,
+}
+
+impl BackupCommands {
+    pub async fn run(self) -> Result<()> {
+        let config = config::init().context("failed to load configuration")?;
+        let backups = Backups::open(&config.backup).context("failed to open backup target")?;
+        match self {
+            Self::Create => {
+                let storage = &config.storage;
+                let devices = open_backend(storage.backend, storage)
+                    .with_context(|| format!("failed to open {}", storage.path(storage.backend)))?
+                    .get_all();
+                let backup = backups.create(&devices).await?;
+                println!("Backed up {} devices to {}", devices.len(), backup.name);
+            }
+            Self::List => {
+                for backup in backups.list().await? {
+                    let created_at = backup.created_at.format(&Rfc3339)?;
+                    println!("{}\t{created_at}\t{} bytes", backup.name, backup.size);
+                }
+            }
+            Self::Restore(args) => restore(&backups, &args).await?,
+        }
+        Ok(())
+    }
+}
+
+/// Checks the backup before touching the live data, and backs up the live
+/// data before replacing it so a restore can itself be undone. Refuses to
+/// run while a server holds the store; it would not see the change and
+/// could overwrite it.
+async fn restore(backups: &Backups, args: &RestoreArgs) -> Result<()> {
+    let storage = &config::get().storage;
+    let path = storage.path(storage.backend);
+    let Some(_lock) =
+        StorageLock::acquire(storage).with_context(|| format!("failed to lock {path}"))?
+    else {
+        bail!(
+            "a server is using {path}; restore through it with `POST /api/admin/restore` \
+             or stop it first"
+        );
+    };
+    let devices = backups.read(&args.name).await?;
+    let mut backend =
+        open_backend(storage.backend, storage).with_context(|| format!("failed to open {path}"))?;
+    let current = backend.get_all();
+
+    if !args.yes
+        && !Confirm::new()
+            .with_prompt(format!(
+                "Replace the {} devices in {path} with the {} devices in {}?",
+                current.len(),
+                devices.len(),
+                args.name
+            ))
+            .default(false)
+            .interact()
+            .context("failed to read confirmation")?
+    {
+        println!("Restore cancelled");
+        return Ok(());
+    }
+
+    let safety = backups
+        .create(&current)
+        .await
+        .context("failed to back up the current devices")?;
+    let count = devices.len();
+    let mut mutations = vec![Mutation::Clear];
+    mutations.extend(devices.into_iter().map(Mutation::Add));
+    backend
+        .apply(mutations)
+        .with_context(|| format!("failed to write {path}"))?;
+
+    println!(
+        "Restored {count} devices from {}; the previous devices were saved as {}",
+        args.name, safety.name
+    );
+    Ok(())
+}
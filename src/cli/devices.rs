@@ -0,0 +1,194 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use clap::{Args, Subcommand};
+
+use crate::api::devices::{
+    ExportFormat, IdConflict, ImportChange, ImportFormat, ImportMode, ImportQuery, ImportReport,
+};
+use crate::config;
+use crate::error::ApiError;
+use crate::models::ValidationError;
+use crate::storage::{SharedStorage, StorageLock};
+
+#[derive(Subcommand)]
+pub enum DeviceCommands {
+    /// Write every device as JSON, CSV, a backup or configuration for another tool
+    Export(ExportArgs),
+
+    /// Add the devices from an export, a backup or another tool's device file
+    #[command(arg_required_else_help = true)]
+    Import(ImportArgs),
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// File format; defaults to the extension of --output, else JSON
+    #[arg(long, value_enum)]
+    format: Option<ExportFormat>,
+
+    /// File to write instead of standard output
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ImportArgs {
+    /// File to read, as written by `jumpers devices export`, the web UI,
+    /// UpSnap, wolweb, the Android "Wake On Lan" app or found in /etc/ethers
+    file: PathBuf,
+
+    /// File format; detected from the content by default
+    #[arg(long, value_enum)]
+    format: Option<ImportFormat>,
+
+    /// How the file's devices combine with the stored ones
+    #[arg(long, value_enum, default_value_t)]
+    mode: ImportMode,
+
+    /// What to do when a backup's device id is already in use
+    #[arg(long, value_enum, default_value_t)]
+    id_conflict: IdConflict,
+
+    /// List what the import would change without saving anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+impl DeviceCommands {
+    pub async fn run(self) -> Result<()> {
+        let config = config::init().context("failed to load configuration")?;
+        let storage_config = &config.storage;
+        let path = storage_config.path(storage_config.backend);
+        // A running server would not see an import and could overwrite it.
+        let _lock = match &self {
+            Self::Export(_) => None,
+            Self::Import(_) => match StorageLock::acquire(storage_config)
+                .with_context(|| format!("failed to lock {path}"))?
+            {
+                Some(lock) => Some(lock),
+                None => bail!(
+                    "a server is using {path}; import through `POST /api/devices/import` \
+                     or stop it first"
+                ),
+            },
+        };
+        let storage = SharedStorage::open(storage_config)
+            .with_context(|| format!("failed to open {path}"))?;
+        match self {
+            Self::Export(args) => export(&storage, &args),
+            Self::Import(args) => import(&storage, &args).await,
+        }
+    }
+}
+
+fn format_of(format: Option<ExportFormat>, path: Option<&Path>) -> ExportFormat {
+    format.unwrap_or_else(|| {
+        let is_csv = path
+            .and_then(Path::extension)
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+        if is_csv {
+            ExportFormat::Csv
+        } else {
+            ExportFormat::Json
+        }
+    })
+}
+
+fn export(storage: &SharedStorage, args: &ExportArgs) -> Result<()> {
+    let content =
+        crate::devices::export_file(storage, format_of(args.format, args.output.as_deref()))?;
+
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, content)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            println!(
+                "Exported {} devices to {}",
+                storage.get_all().len(),
+                path.display()
+            );
+        }
+        None => print!("{content}"),
+    }
+    Ok(())
+}
+
+/// Imports all devices or none; every invalid entry is listed before failing.
+async fn import(storage: &SharedStorage, args: &ImportArgs) -> Result<()> {
+    let content = std::fs::read_to_string(&args.file)
+        .with_context(|| format!("failed to read {}", args.file.display()))?;
+    let query = ImportQuery {
+        format: args.format,
+        mode: args.mode,
+        dry_run: args.dry_run,
+        id_conflict: args.id_conflict,
+    };
+    let result = crate::devices::import_file(storage, &content, &query).await;
+    if let Ok(imported) = &result {
+        for warning in &imported.report.warnings {
+            eprintln!("warning: {}: {}", warning.field, warning.message);
+        }
+    }
+
+    match result {
+        Ok(imported) if args.dry_run => {
+            print_report(&imported.report);
+            Ok(())
+        }
+        Ok(imported) => {
+            let report = &imported.report;
+            println!(
+                "Imported {}: {} created, {} updated, {} unchanged, {} moved to the trash",
+                args.file.display(),
+                report.created.len(),
+                report.updated.len(),
+                report.skipped.len(),
+                report.removed.len()
+            );
+            Ok(())
+        }
+        Err(ApiError::Validation(ValidationError::Fields(errors))) => {
+            for error in &errors {
+                eprintln!("{}: {}", error.field, error.message);
+            }
+            bail!(
+                "{} has {} problem(s); nothing was imported",
+                args.file.display(),
+                errors.len()
+            )
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn print_report(report: &ImportReport) {
+    let sections = [
+        ("create", &report.created),
+        ("update", &report.updated),
+        ("skip", &report.skipped),
+        ("conflict", &report.conflicts),
+        ("trash", &report.removed),
+    ];
+    for (action, changes) in sections {
+        for change in changes {
+            print_change(action, change);
+        }
+    }
+    println!(
+        "Dry run: {} to create, {} to update, {} unchanged, {} conflicts, {} to trash; nothing was saved",
+        report.created.len(),
+        report.updated.len(),
+        report.skipped.len(),
+        report.conflicts.len(),
+        report.removed.len()
+    );
+}
+
+fn print_change(action: &str, change: &ImportChange) {
+    let entry = change.entry.as_deref().unwrap_or("-");
+    match &change.reason {
+        Some(reason) => println!("{action:<8} {entry:<10} {}: {reason}", change.name),
+        None => println!("{action:<8} {entry:<10} {}", change.name),
+    }
+}
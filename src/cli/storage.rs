@@ -0,0 +1,97 @@
+use anyhow::{Context, Result, bail};
+use clap::{Args, Subcommand};
+
+use crate::config::{self, StorageKind};
+use crate::storage::{JsonStorage, Mutation, StorageLock, open_backend};
+
+#[derive(Subcommand)]
+pub enum StorageCommands {
+    /// Copy every device from one storage backend into another
+    Migrate(MigrateArgs),
+    /// Replace an unreadable JSON device file with its newest readable backup
+    Recover,
+}
+
+#[derive(Args)]
+pub struct MigrateArgs {
+    /// Backend to read devices from
+    #[arg(long, value_enum)]
+    from: StorageKind,
+
+    /// Backend to write devices to; it must not contain devices yet
+    #[arg(long, value_enum)]
+    to: StorageKind,
+}
+
+impl StorageCommands {
+    pub fn run(self) -> Result<()> {
+        match self {
+            Self::Migrate(args) => migrate(args.from, args.to),
+            Self::Recover => recover(),
+        }
+    }
+}
+
+/// Uses the paths from `[storage]`, so the migrated data is where the server
+/// looks once `backend` is switched.
+fn migrate(from: StorageKind, to: StorageKind) -> Result<()> {
+    if from == to {
+        bail!("--from and --to must name different backends");
+    }
+
+    let config = &config::init()
+        .context("failed to load configuration")?
+        .storage;
+    let source = open_backend(from, config)
+        .with_context(|| format!("failed to open {}", config.path(from)))?;
+    let mut target =
+        open_backend(to, config).with_context(|| format!("failed to open {}", config.path(to)))?;
+
+    if !target.get_all().is_empty() {
+        bail!(
+            "{} already contains devices; remove it or point the configuration elsewhere",
+            config.path(to)
+        );
+    }
+
+    let devices = source.get_all();
+    let count = devices.len();
+    target
+        .apply(devices.into_iter().map(Mutation::Add).collect())
+        .with_context(|| format!("failed to write {}", config.path(to)))?;
+
+    println!(
+        "Migrated {count} devices from {} to {}",
+        config.path(from),
+        config.path(to)
+    );
+    Ok(())
+}
+
+/// Startup refuses an unreadable file instead of replacing it, so putting a
+/// backup in its place is left to this command.
+fn recover() -> Result<()> {
+    let config = &config::init()
+        .context("failed to load configuration")?
+        .storage;
+    if config.backend != StorageKind::Json {
+        bail!("only the json backend keeps backups to recover from");
+    }
+    let path = &config.file_path;
+    let Some(_lock) =
+        StorageLock::acquire(config).with_context(|| format!("failed to lock {path}"))?
+    else {
+        bail!("a server is using {path}; stop it first");
+    };
+
+    match JsonStorage::recover(path, config.backups)
+        .with_context(|| format!("failed to recover {path}"))?
+    {
+        Some(backup) => println!(
+            "Restored {path} from {}; the damaged file is kept as {path}.corrupt.*",
+            backup.display()
+        ),
+        None => println!("{path} is readable; nothing to recover"),
+    }
+    Ok(())
+}
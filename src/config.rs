@@ -16,6 +16,9 @@ pub struct AppConfig {
     pub auth: AuthConfig,
     pub storage: StorageConfig,
     pub wol: WolConfig,
+    pub network: NetworkConfig,
+    pub metadata: MetadataConfig,
+    pub backup: BackupConfig,
 }
 
 #[derive(Debug, Deserialize)]
@@ -93,17 +96,53 @@ pub enum LogFormat {
 #[derive(Debug, Deserialize)]
 #[serde(default)]
 pub struct StorageConfig {
+    pub backend: StorageKind,
+    /// Device file of the JSON backend.
     pub file_path: String,
+    /// Database file of the SQLite backend.
+    pub sqlite_path: String,
+    /// Previous versions of the JSON file kept as `<file_path>.1` … `.N`.
+    pub backups: usize,
+    /// Reload the JSON file when another program changes it.
+    pub watch: bool,
+    /// How long a change waits for further changes to save in the same write.
+    pub write_delay_ms: u64,
+    /// Days a removed device stays in the trash before it is deleted for good.
+    pub trash_retention_days: u32,
 }
 
 impl Default for StorageConfig {
     fn default() -> Self {
         Self {
+            backend: StorageKind::default(),
             file_path: "devices.json".to_string(),
+            sqlite_path: "jumpers.db".to_string(),
+            backups: 3,
+            watch: true,
+            write_delay_ms: 20,
+            trash_retention_days: 30,
         }
     }
 }
 
+impl StorageConfig {
+    /// File the given backend reads and writes.
+    pub fn path(&self, kind: StorageKind) -> &str {
+        match kind {
+            StorageKind::Json => &self.file_path,
+            StorageKind::Sqlite => &self.sqlite_path,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageKind {
+    #[default]
+    Json,
+    Sqlite,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(default)]
 pub struct WolConfig {
@@ -116,6 +155,85 @@ impl Default for WolConfig {
     }
 }
 
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// Follow kernel neighbor table events to track MAC/IP mappings passively.
+    pub neighbor_watcher: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct MetadataConfig {
+    /// Metadata keys the device modal offers as dedicated inputs.
+    pub fields: Vec<MetadataField>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MetadataField {
+    pub key: String,
+    pub label: Option<String>,
+    pub placeholder: Option<String>,
+}
+
+impl MetadataField {
+    pub fn label(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.key)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// Take a backup every `interval_hours` while the server runs.
+    pub enabled: bool,
+    pub interval_hours: u64,
+    /// Number of backups to keep; older ones are deleted. 0 keeps all of them.
+    pub keep: usize,
+    pub target: BackupTarget,
+    /// Directory of the local target.
+    pub directory: String,
+    pub s3: S3Config,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_hours: 24,
+            keep: 7,
+            target: BackupTarget::default(),
+            directory: "backups".to_string(),
+            s3: S3Config::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupTarget {
+    #[default]
+    Local,
+    S3,
+}
+
+/// Bucket of the S3 target. Empty credentials and region fall back to the
+/// standard `AWS_*` environment variables.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct S3Config {
+    pub bucket: String,
+    pub region: String,
+    /// Endpoint of an S3-compatible service such as MinIO; empty for AWS.
+    pub endpoint: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Key prefix the backups are stored under.
+    pub prefix: String,
+    /// Allow a plain `http://` endpoint, e.g. a local MinIO.
+    pub allow_http: bool,
+}
+
 /// Initialize the global configuration. Must be called once at startup.
 pub fn init() -> Result<&'static AppConfig, ConfigError> {
     let config = load()?;
@@ -1,111 +1,697 @@
 mod arp;
+pub mod csv;
+mod formats;
+mod import;
+mod neighbors;
 mod wol;
 
 pub use arp::ArpError;
+pub use import::{Imported, import_file};
+pub use neighbors::spawn_watcher as spawn_neighbor_watcher;
 pub use wol::WolError;
 
 use crate::api::ApiResult;
 use crate::api::devices::{
-    CreateDeviceRequest, ExportResponse, ImportRequest, UpdateDeviceRequest,
+    CreateDeviceRequest, DeviceQuery, DeviceSort, ExportFormat, ExportResponse, InterfaceRequest,
+    PatchDeviceRequest, SortOrder, UpdateDeviceRequest,
 };
 use crate::config;
-use crate::models::{Device, validate_mac_address};
-use crate::storage::{SharedStorage, StorageError};
+use crate::models::{
+    Device, FieldError, FieldErrors, MacAddress, NetworkInterface, ValidationError,
+    check_resolvable, normalize_metadata, normalize_tags,
+};
+use crate::storage::{DeviceIndex, Mutation, SharedStorage, StorageError, encode_devices};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tracing::{info, warn};
+
+const TRASH_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Devices matching `query`. Without filters this is the shared snapshot
+/// itself; only filtered lists copy the devices they contain.
+pub fn list_devices(storage: &SharedStorage, query: &DeviceQuery) -> Arc<[Device]> {
+    let devices = storage.get_all();
+    if query.tag.is_empty() && query.meta.is_empty() && search_terms(query).is_empty() {
+        return devices;
+    }
+    devices
+        .iter()
+        .filter(|device| matches_query(device, query))
+        .cloned()
+        .collect()
+}
+
+/// A page of the devices matching `query`, in the order it asks for.
+#[derive(Debug)]
+pub struct DevicePage {
+    pub devices: Arc<[Device]>,
+    /// Cursor of the following page, absent on the last one.
+    pub next_cursor: Option<String>,
+}
+
+/// Filters, sorts and pages the devices as `query` says. A page continues
+/// right after the position its cursor holds, so paging goes on when the
+/// device it came from has since been changed or deleted.
+pub fn search_devices(storage: &SharedStorage, query: &DeviceQuery) -> ApiResult<DevicePage> {
+    if query.limit == Some(0) {
+        let error = FieldError::new("limit", "Must be at least 1");
+        return Err(ValidationError::Fields(vec![error]).into());
+    }
+    let devices = list_devices(storage, query);
+    if query.sort.is_none() && query.limit.is_none() && query.cursor.is_none() {
+        return Ok(DevicePage {
+            devices,
+            next_cursor: None,
+        });
+    }
+
+    let mut sorted: Vec<(Position, &Device)> = devices
+        .iter()
+        .map(|device| (Position::of(device, query.sort), device))
+        .collect();
+    sorted.sort_by(|(a, _), (b, _)| a.cmp(b, query.order));
+    let start = match &query.cursor {
+        Some(cursor) => {
+            let after = Position::decode(cursor, query.sort).ok_or_else(|| {
+                ValidationError::Fields(vec![FieldError::new(
+                    "cursor",
+                    "Not a cursor of this list; list again from the first page",
+                )])
+            })?;
+            sorted.partition_point(|(position, _)| {
+                position.cmp(&after, query.order) != Ordering::Greater
+            })
+        }
+        None => 0,
+    };
+    let rest = &sorted[start..];
+    let limit = query.limit.unwrap_or(rest.len());
+    let page: Arc<[Device]> = rest
+        .iter()
+        .take(limit)
+        .map(|(_, device)| (*device).clone())
+        .collect();
+    let next_cursor = (rest.len() > limit).then(|| rest[limit - 1].0.encode());
+    Ok(DevicePage {
+        devices: page,
+        next_cursor,
+    })
+}
+
+/// Where a device stands in a sorted list: the value it is sorted by, then
+/// its dashboard position and id to order equal values. Cursors carry the
+/// position of a page's last device.
+#[derive(Debug, Serialize, Deserialize)]
+struct Position {
+    key: SortKey,
+    sort_order: u32,
+    id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SortKey {
+    Dashboard,
+    Name(String),
+    CreatedAt(OffsetDateTime),
+    Ip(Option<(bool, Option<IpAddr>, String)>),
+    LastSeen(Option<OffsetDateTime>),
+}
+
+impl Position {
+    fn of(device: &Device, sort: Option<DeviceSort>) -> Self {
+        let key = match sort {
+            None => SortKey::Dashboard,
+            Some(DeviceSort::Name) => SortKey::Name(device.name.to_lowercase()),
+            Some(DeviceSort::CreatedAt) => SortKey::CreatedAt(device.created_at),
+            Some(DeviceSort::Ip) => SortKey::Ip(ip_sort_key(device)),
+            Some(DeviceSort::LastSeen) => SortKey::LastSeen(last_seen(device)),
+        };
+        Self {
+            key,
+            sort_order: device.sort_order,
+            id: device.id.clone(),
+        }
+    }
+
+    /// Compares the sort values in `order`, with devices without an IP
+    /// address or never seen last either way. Equal values keep their
+    /// dashboard order.
+    fn cmp(&self, other: &Self, order: SortOrder) -> Ordering {
+        let directed = |ordering: Ordering| match order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        };
+        let by_key = match (&self.key, &other.key) {
+            (SortKey::Name(a), SortKey::Name(b)) => directed(a.cmp(b)),
+            (SortKey::CreatedAt(a), SortKey::CreatedAt(b)) => directed(a.cmp(b)),
+            (SortKey::Ip(a), SortKey::Ip(b)) => missing_last(a.as_ref(), b.as_ref(), directed),
+            (SortKey::LastSeen(a), SortKey::LastSeen(b)) => missing_last(*a, *b, directed),
+            _ => Ordering::Equal,
+        };
+        by_key
+            .then(self.sort_order.cmp(&other.sort_order))
+            .then_with(|| self.id.cmp(&other.id))
+    }
+
+    /// The position as an opaque, URL-safe cursor.
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    /// Reads a cursor, provided it was made for a list sorted by `sort`.
+    fn decode(cursor: &str, sort: Option<DeviceSort>) -> Option<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        let position: Self = serde_json::from_slice(&bytes).ok()?;
+        let expected = match sort {
+            None => matches!(position.key, SortKey::Dashboard),
+            Some(DeviceSort::Name) => matches!(position.key, SortKey::Name(_)),
+            Some(DeviceSort::CreatedAt) => matches!(position.key, SortKey::CreatedAt(_)),
+            Some(DeviceSort::Ip) => matches!(position.key, SortKey::Ip(_)),
+            Some(DeviceSort::LastSeen) => matches!(position.key, SortKey::LastSeen(_)),
+        };
+        expected.then_some(position)
+    }
+}
+
+/// IP addresses in numeric order, followed by host names.
+fn ip_sort_key(device: &Device) -> Option<(bool, Option<IpAddr>, String)> {
+    let address = device.ip_address.as_deref()?;
+    let ip = address.parse::<IpAddr>().ok();
+    Some((ip.is_none(), ip, address.to_lowercase()))
+}
+
+fn missing_last<T: Ord>(
+    a: Option<T>,
+    b: Option<T>,
+    directed: impl Fn(Ordering) -> Ordering,
+) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => directed(a.cmp(&b)),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+pub fn matches_query(device: &Device, query: &DeviceQuery) -> bool {
+    query.tag.iter().all(|tag| device.has_tag(tag))
+        && query
+            .meta
+            .iter()
+            .all(|filter| matches_metadata(device, filter))
+        && search_terms(query)
+            .iter()
+            .all(|term| matches_search(device, term))
+}
+
+/// The lowercased words of the `q` search.
+fn search_terms(query: &DeviceQuery) -> Vec<String> {
+    query
+        .q
+        .as_deref()
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Whether `term` is part of the device's name, description, IP address or
+/// one of its MAC addresses. MACs also match without separators, so
+/// `aabbcc` finds `AA:BB:CC:…`.
+fn matches_search(device: &Device, term: &str) -> bool {
+    let contains = |value: &str| value.to_lowercase().contains(term);
+    contains(&device.name)
+        || device.description.as_deref().is_some_and(contains)
+        || device.ip_address.as_deref().is_some_and(contains)
+        || device.macs().any(|mac| {
+            let mac = mac.to_string();
+            contains(&mac) || contains(&mac.replace(':', ""))
+        })
+}
 
-pub fn list_devices(storage: &SharedStorage) -> Vec<Device> {
-    storage.get_all()
+/// Matches a `key:value` filter against the device's metadata. Keys compare
+/// case-insensitively, values by case-insensitive substring.
+fn matches_metadata(device: &Device, filter: &str) -> bool {
+    let (key, value) = filter.split_once(':').unwrap_or((filter, ""));
+    let (key, value) = (key.trim(), value.trim().to_lowercase());
+    device.metadata.iter().any(|(own_key, own_value)| {
+        own_key.eq_ignore_ascii_case(key) && own_value.to_lowercase().contains(&value)
+    })
+}
+
+/// Every tag in use, sorted alphabetically.
+pub fn all_tags(devices: &[Device]) -> Vec<String> {
+    let tags: std::collections::BTreeSet<&String> =
+        devices.iter().flat_map(|device| &device.tags).collect();
+    tags.into_iter().cloned().collect()
+}
+
+pub fn get_device(storage: &SharedStorage, id: &str) -> ApiResult<Device> {
+    Ok(storage
+        .get(id)
+        .ok_or_else(|| StorageError::NotFound(id.to_string()))?)
 }
 
 pub fn export_devices(storage: &SharedStorage) -> Vec<ExportResponse> {
     storage
         .get_all()
-        .into_iter()
+        .iter()
         .map(|device| ExportResponse {
-            name: device.name,
-            mac_address: device.mac_address,
+            name: device.name.clone(),
+            interfaces: device.interfaces.clone(),
             port: device.port,
-            ip_address: device.ip_address,
-            description: device.description,
+            ip_address: device.ip_address.clone(),
+            description: device.description.clone(),
+            tags: device.tags.clone(),
+            metadata: device.metadata.clone(),
+            favorite: device.favorite,
         })
         .collect()
 }
 
-pub fn import_devices(storage: &SharedStorage, req: Vec<ImportRequest>) -> ApiResult<Vec<Device>> {
-    let mut devices = Vec::new();
-    for device in req {
-        let device = Device::new(
-            device.name,
-            device.mac_address,
-            device.ip_address,
-            device
-                .port
-                .unwrap_or_else(|| config::get().wol.default_port),
-            device.description,
-        )?;
-        devices.push(device);
-    }
-
-    storage.add_all(devices.clone())?;
-    Ok(devices)
+/// The devices as a file in `format`: an export, a backup or configuration
+/// for another tool.
+pub fn export_file(storage: &SharedStorage, format: ExportFormat) -> ApiResult<String> {
+    let devices = storage.get_all();
+    Ok(match format {
+        ExportFormat::Json => {
+            let mut content = serde_json::to_string_pretty(&export_devices(storage))
+                .map_err(StorageError::Parse)?;
+            content.push('\n');
+            content
+        }
+        ExportFormat::Csv => {
+            csv::write_devices(&export_devices(storage)).map_err(StorageError::Io)?
+        }
+        ExportFormat::Backup => encode_devices(&devices)?,
+        ExportFormat::Ethers => formats::ethers::write_devices(&devices),
+        ExportFormat::HomeAssistant => formats::home_assistant::write_devices(&devices),
+        ExportFormat::AnsibleIni => formats::ansible::write_ini(&devices),
+        ExportFormat::AnsibleYaml => formats::ansible::write_yaml(&devices),
+    })
 }
 
-pub fn create_device(storage: &SharedStorage, req: CreateDeviceRequest) -> ApiResult<Device> {
-    let device = Device::new(
+pub async fn create_device(storage: &SharedStorage, req: CreateDeviceRequest) -> ApiResult<Device> {
+    let existing = storage.snapshot();
+    let mut errors = FieldErrors::default();
+    let interfaces = errors.merge("", parse_interfaces(req.mac_address, req.interfaces));
+    let mut device = Device::new(
         req.name,
-        req.mac_address,
+        interfaces.unwrap_or_default(),
         req.ip_address,
         req.port.unwrap_or_else(|| config::get().wol.default_port),
         req.description,
-    )?;
+    )
+    .with_tags(req.tags.unwrap_or_default())
+    .with_metadata(req.metadata.unwrap_or_default());
+    errors.merge("", check_device(&device, None).await);
+    errors.into_result()?;
+    ensure_unique_macs(&device, |mac| {
+        existing.by_mac(mac).map(|other| other.name.as_str())
+    })?;
+    device.favorite = req.favorite.unwrap_or_default();
+    device.sort_order = next_sort_order(&existing);
 
-    storage.add(device.clone())?;
+    storage.add(device.clone()).await?;
     Ok(device)
 }
 
-pub fn update_device(
+/// Replaces the device with `req`, keeping only its id, position and
+/// timestamps. With `if_match` (an `If-Match` header value) the update only
+/// goes through while the device is still at that revision.
+pub async fn update_device(
     storage: &SharedStorage,
     id: &str,
     req: UpdateDeviceRequest,
+    if_match: Option<&str>,
+) -> ApiResult<Device> {
+    let existing = get_device_for_update(storage, id, if_match)?;
+
+    let mut errors = FieldErrors::default();
+    let interfaces = errors
+        .merge("", parse_interfaces(req.mac_address, req.interfaces))
+        .unwrap_or_else(|| existing.interfaces.clone());
+
+    let updated = Device {
+        name: req.name,
+        interfaces,
+        ip_address: req.ip_address,
+        port: req.port.unwrap_or_else(|| config::get().wol.default_port),
+        description: req.description,
+        tags: normalize_tags(req.tags),
+        metadata: normalize_metadata(req.metadata),
+        favorite: req.favorite,
+        ..existing.clone()
+    };
+    save_update(storage, &existing, updated, errors).await
+}
+
+/// Applies a JSON Merge Patch: absent fields keep their value, `null` clears
+/// them. `if_match` works as for [`update_device`].
+pub async fn patch_device(
+    storage: &SharedStorage,
+    id: &str,
+    req: PatchDeviceRequest,
+    if_match: Option<&str>,
+) -> ApiResult<Device> {
+    let existing = get_device_for_update(storage, id, if_match)?;
+
+    let mut errors = FieldErrors::default();
+    let interfaces = match (req.mac_address, req.interfaces) {
+        (None, None) => existing.interfaces.clone(),
+        (Some(None), _) | (_, Some(None)) => {
+            errors.add("interfaces", "Interfaces cannot be cleared");
+            existing.interfaces.clone()
+        }
+        (Some(Some(mac)), None) => {
+            let mut interfaces = existing.interfaces.clone();
+            if let Some(mac) = errors.merge("", mac.parse()) {
+                interfaces[0].mac_address = mac;
+            }
+            interfaces
+        }
+        (mac, interfaces) => errors
+            .merge("", parse_interfaces(mac.flatten(), interfaces.flatten()))
+            .unwrap_or_else(|| existing.interfaces.clone()),
+    };
+
+    let mut metadata = existing.metadata.clone();
+    match req.metadata {
+        None => {}
+        Some(None) => metadata.clear(),
+        Some(Some(patch)) => {
+            for (key, value) in patch {
+                match value {
+                    Some(value) => metadata.insert(key, value),
+                    None => metadata.remove(&key),
+                };
+            }
+        }
+    }
+
+    let updated = Device {
+        // A cleared name is reported by validation as missing.
+        name: req
+            .name
+            .map_or_else(|| existing.name.clone(), Option::unwrap_or_default),
+        interfaces,
+        ip_address: req
+            .ip_address
+            .unwrap_or_else(|| existing.ip_address.clone()),
+        port: req.port.map_or(existing.port, |port| {
+            port.unwrap_or_else(|| config::get().wol.default_port)
+        }),
+        description: req
+            .description
+            .unwrap_or_else(|| existing.description.clone()),
+        tags: req.tags.map_or_else(
+            || existing.tags.clone(),
+            |tags| normalize_tags(tags.unwrap_or_default()),
+        ),
+        metadata: normalize_metadata(metadata),
+        favorite: req
+            .favorite
+            .map_or(existing.favorite, Option::unwrap_or_default),
+        ..existing.clone()
+    };
+    save_update(storage, &existing, updated, errors).await
+}
+
+/// Loads a device that is about to change, failing with
+/// [`StorageError::Stale`] when `if_match` names another revision.
+fn get_device_for_update(
+    storage: &SharedStorage,
+    id: &str,
+    if_match: Option<&str>,
+) -> ApiResult<Device> {
+    let existing = get_device(storage, id)?;
+    if let Some(if_match) = if_match
+        && !existing.matches_etag(if_match)
+    {
+        return Err(StorageError::Stale {
+            id: id.to_string(),
+            current: existing.revision,
+        }
+        .into());
+    }
+    Ok(existing)
+}
+
+/// Validates `updated` together with the errors collected while building it
+/// and stores it in place of `existing`.
+async fn save_update(
+    storage: &SharedStorage,
+    existing: &Device,
+    updated: Device,
+    mut errors: FieldErrors,
 ) -> ApiResult<Device> {
+    errors.merge(
+        "",
+        check_device(&updated, existing.ip_address.as_deref()).await,
+    );
+    errors.into_result()?;
+    let existing_macs = storage.snapshot();
+    ensure_unique_macs(&updated, |mac| {
+        existing_macs
+            .by_mac(mac)
+            .filter(|other| other.id != updated.id)
+            .map(|other| other.name.as_str())
+    })?;
+
+    Ok(storage.update(&existing.id, updated).await?)
+}
+
+/// Pins or unpins a device, returning its new state.
+pub async fn toggle_favorite(storage: &SharedStorage, id: &str) -> ApiResult<Device> {
     let existing = storage
         .get(id)
         .ok_or_else(|| StorageError::NotFound(id.to_string()))?;
 
-    let mac_address = match req.mac_address {
-        Some(mac) => {
-            validate_mac_address(&mac)?;
-            mac
+    let updated = Device {
+        favorite: !existing.favorite,
+        ..existing
+    };
+    Ok(storage.update(id, updated).await?)
+}
+
+/// Applies a new order to the listed devices and returns the full, reordered list.
+pub async fn reorder_devices(storage: &SharedStorage, ids: &[String]) -> ApiResult<Arc<[Device]>> {
+    storage.reorder(ids).await?;
+    Ok(storage.get_all())
+}
+
+/// Sort order that places a new device after every existing one, including
+/// those in the trash so a restored device keeps a position of its own.
+fn next_sort_order(index: &DeviceIndex) -> u32 {
+    index
+        .stored()
+        .map(|device| device.sort_order.saturating_add(1))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Validates every field of `device` and makes sure a hostname that differs
+/// from `previous_ip` resolves. Unchanged addresses skip the DNS lookup.
+async fn check_device(device: &Device, previous_ip: Option<&str>) -> Result<(), ValidationError> {
+    let mut errors = FieldErrors::default();
+    errors.merge("", device.validate());
+
+    if !errors.contains("ip_address")
+        && let Some(host) = new_host(device, previous_ip)
+        && let Err(error) = check_resolvable(host).await
+    {
+        errors.push(error);
+    }
+    errors.into_result()
+}
+
+/// The hostname `device` has that `previous_ip` did not, which has to resolve
+/// before the device is stored. IP literals need no lookup.
+fn new_host<'a>(device: &'a Device, previous_ip: Option<&str>) -> Option<&'a str> {
+    device
+        .ip_address
+        .as_deref()
+        .filter(|host| previous_ip != Some(*host) && host.parse::<IpAddr>().is_err())
+}
+
+/// Builds a device's interfaces from either the single-MAC shorthand or the
+/// full list; giving both or neither is rejected.
+fn parse_interfaces(
+    mac_address: Option<String>,
+    interfaces: Option<Vec<InterfaceRequest>>,
+) -> Result<Vec<NetworkInterface>, ValidationError> {
+    match (mac_address, interfaces) {
+        (Some(_), Some(_)) => Err(ValidationError::AmbiguousInterfaces),
+        (Some(mac), None) => Ok(vec![NetworkInterface::new(&mac)?]),
+        (None, Some(interfaces)) if !interfaces.is_empty() => {
+            let mut errors = FieldErrors::default();
+            let parsed = interfaces
+                .into_iter()
+                .enumerate()
+                .filter_map(|(index, interface)| {
+                    errors.merge(
+                        &format!("interfaces[{index}]."),
+                        NetworkInterface::parse(
+                            &interface.mac_address,
+                            interface.label,
+                            interface.broadcast.as_deref(),
+                        ),
+                    )
+                })
+                .collect();
+            errors.into_result()?;
+            Ok(parsed)
+        }
+        (None, _) => Err(ValidationError::NoInterfaces),
+    }
+}
+
+/// Rejects `device` when one of its MACs is listed twice or already belongs
+/// to another device, so the same NIC cannot be registered twice under
+/// different spellings. `owner` names the device a MAC is already registered to, if any.
+fn ensure_unique_macs<'a>(
+    device: &Device,
+    owner: impl Fn(MacAddress) -> Option<&'a str>,
+) -> Result<(), ValidationError> {
+    for (index, mac) in device.macs().enumerate() {
+        if device.macs().take(index).any(|earlier| earlier == mac) {
+            return Err(ValidationError::DuplicateMac {
+                mac,
+                device: device.name.clone(),
+            });
         }
-        None => existing.mac_address,
+    }
+
+    for mac in device.macs() {
+        if let Some(other) = owner(mac) {
+            return Err(ValidationError::DuplicateMac {
+                mac,
+                device: other.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Moves a device to the trash. It disappears from every list and lookup
+/// until it is restored or purged by [`purge_trash`].
+pub async fn delete_device(storage: &SharedStorage, id: &str) -> ApiResult<()> {
+    let existing = get_device(storage, id)?;
+    let trashed = Device {
+        deleted_at: Some(OffsetDateTime::now_utc()),
+        ..existing
     };
+    storage.update(id, trashed).await?;
+    Ok(())
+}
 
-    let updated = Device {
-        id: existing.id,
-        name: req.name.unwrap_or(existing.name),
-        mac_address,
-        ip_address: req.ip_address.or(existing.ip_address),
-        port: req.port.unwrap_or(existing.port),
-        description: req.description,
-        created_at: existing.created_at,
+/// Devices in the trash, in storage order.
+pub fn list_trash(storage: &SharedStorage) -> Arc<[Device]> {
+    storage.snapshot().trash()
+}
+
+/// Takes a device out of the trash, unless one of its MACs has been
+/// registered to another device in the meantime.
+pub async fn restore_device(storage: &SharedStorage, id: &str) -> ApiResult<Device> {
+    let snapshot = storage.snapshot();
+    let existing = snapshot
+        .trashed(id)
+        .cloned()
+        .ok_or_else(|| StorageError::NotFound(id.to_string()))?;
+    ensure_unique_macs(&existing, |mac| {
+        snapshot.by_mac(mac).map(|other| other.name.as_str())
+    })?;
+
+    let restored = Device {
+        deleted_at: None,
+        ..existing
     };
+    Ok(storage.update(id, restored).await?)
+}
 
-    Ok(storage.update(id, updated)?)
+/// Deletes every device that has been in the trash for longer than
+/// `retention`, returning how many were removed.
+pub async fn purge_trash(storage: &SharedStorage, retention: time::Duration) -> ApiResult<usize> {
+    let cutoff = OffsetDateTime::now_utc() - retention;
+    let expired: Vec<Mutation> = list_trash(storage)
+        .iter()
+        .filter(|device| {
+            device
+                .deleted_at
+                .is_some_and(|deleted_at| deleted_at <= cutoff)
+        })
+        .map(|device| Mutation::Remove(device.id.clone()))
+        .collect();
+    let count = expired.len();
+    if count > 0 {
+        storage.apply(expired).await?;
+    }
+    Ok(count)
 }
 
-pub fn delete_device(storage: &SharedStorage, id: &str) -> ApiResult<()> {
-    storage.remove(id)?;
-    Ok(())
+/// Purges the trash on startup and then every hour, see [`purge_trash`].
+pub fn spawn_trash_purger(storage: SharedStorage, retention_days: u32) {
+    let retention = time::Duration::days(i64::from(retention_days));
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(TRASH_PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match purge_trash(&storage, retention).await {
+                Ok(0) => {}
+                Ok(count) => info!(count, "Purged devices from the trash"),
+                Err(err) => warn!(error = %err, "Failed to purge the trash"),
+            }
+        }
+    });
 }
 
-pub fn wake_device(storage: &SharedStorage, id: &str) -> ApiResult<()> {
+/// Sends a magic packet to every interface of the device, or only to the one
+/// with the MAC given in `interface`.
+pub fn wake_device(storage: &SharedStorage, id: &str, interface: Option<&str>) -> ApiResult<()> {
     let device = storage
         .get(id)
         .ok_or_else(|| StorageError::NotFound(id.to_string()))?;
 
-    wol::send_wol_packet(&device)?;
+    match interface {
+        Some(interface) => {
+            let mac: MacAddress = interface.parse()?;
+            let interface = device
+                .interfaces
+                .iter()
+                .find(|own| own.mac_address == mac)
+                .ok_or_else(|| WolError::UnknownInterface(mac.to_string()))?;
+            wol::send_wol_packet(interface, device.port)?;
+        }
+        None => {
+            for interface in &device.interfaces {
+                wol::send_wol_packet(interface, device.port)?;
+            }
+        }
+    }
     Ok(())
 }
 
 pub fn arp_lookup(ip: &str) -> ApiResult<String> {
     Ok(arp::lookup_mac(ip)?)
 }
+
+/// When any of the device's NICs was last confirmed reachable by the neighbor watcher.
+pub fn last_seen(device: &Device) -> Option<OffsetDateTime> {
+    device
+        .macs()
+        .filter_map(|mac| neighbors::cache().get(mac)?.last_seen)
+        .max()
+}
+
+/// The address the neighbor watcher currently maps to one of the device's MACs.
+pub fn observed_ip(device: &Device) -> Option<IpAddr> {
+    device
+        .macs()
+        .find_map(|mac| neighbors::cache().get(mac)?.ip)
+}
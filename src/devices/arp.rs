@@ -1,4 +1,5 @@
-use std::net::Ipv4Addr;
+use crate::models::MacAddress;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::process::{Command, Output};
 use thiserror::Error;
 use tracing::{debug, instrument, warn};
@@ -11,6 +12,9 @@ pub enum ArpError {
     #[error("Failed to query ARP table: {0}")]
     Query(#[source] std::io::Error),
 
+    #[error("Invalid IPv6 scope: {0}")]
+    InvalidScope(String),
+
     #[error("MAC lookup needs direct LAN access from this runtime.")]
     NotDirectlyConnected { ip: String, route: String },
 
@@ -22,50 +26,121 @@ impl ArpError {
     pub const fn hint(&self) -> Option<&'static str> {
         match self {
             Self::NotDirectlyConnected { .. } => Some(
-                "ARP-based MAC lookup only works when jump_rs can access the target device on the same layer-2 network. Docker Desktop, OrbStack, and other VM-backed Docker runtimes may hide LAN devices even with host networking. Running jump_rs directly on the host or in a Linux host-network container usually fixes this.",
+                "ARP and NDP-based MAC lookup only work when jump_rs can access the target device on the same layer-2 network. Docker Desktop, OrbStack, and other VM-backed Docker runtimes may hide LAN devices even with host networking. Running jump_rs directly on the host or in a Linux host-network container usually fixes this.",
             ),
-            Self::InvalidIp(_) | Self::Query(_) | Self::NotFound(_) => None,
+            Self::InvalidIp(_) | Self::InvalidScope(_) | Self::Query(_) | Self::NotFound(_) => None,
         }
     }
 }
 
-/// Looks up the MAC address for a given IP by pinging it and checking the ARP table.
+/// Looks up the MAC address for a given IP address.
+///
+/// IPv4 targets are resolved through ARP, IPv6 targets through NDP neighbor
+/// solicitation. Link-local IPv6 addresses may carry a scope (`fe80::1%eth0`).
 #[instrument(skip_all)]
 pub fn lookup_mac(ip: &str) -> Result<String, ArpError> {
-    let ip_addr: Ipv4Addr = ip.parse()?;
+    let target = parse_target(ip)?;
+    if let Some(mac) = super::neighbors::cache().mac_for(target.0) {
+        debug!(mac = %mac, "MAC address found in neighbor cache");
+        return Ok(mac.to_string());
+    }
+
+    match target {
+        (IpAddr::V4(ip_addr), _) => lookup_ipv4(ip_addr),
+        (IpAddr::V6(ip_addr), scope) => lookup_ipv6(ip_addr, scope),
+    }
+}
 
-    ensure_direct_route(ip)?;
+fn lookup_ipv4(ip_addr: Ipv4Addr) -> Result<String, ArpError> {
+    let ip = ip_addr.to_string();
+    ensure_direct_route(&ip)?;
 
     if let Some(mac) = arping_ip(ip_addr)? {
         return Ok(mac);
     }
 
     debug!("Pinging IP to populate ARP cache");
-    ping_ip(ip_addr).ok();
-    get_mac_from_arp(ip)
+    ping_ip(&ip).ok();
+    get_mac_from_arp(&ip)
+}
+
+fn lookup_ipv6(ip_addr: Ipv6Addr, scope: Option<&str>) -> Result<String, ArpError> {
+    let ip = ip_addr.to_string();
+    let interface = match scope {
+        Some(scope) => Some(scope.to_string()),
+        None => ensure_direct_route(&ip)?,
+    };
+
+    if let Some(mac) = query_ip_neighbor(&ip)? {
+        return Ok(mac);
+    }
+
+    if let Some(interface) = interface.as_deref()
+        && let Some(mac) = solicit_neighbor(&ip, interface)?
+    {
+        return Ok(mac);
+    }
+
+    debug!("Pinging IP to populate neighbor cache");
+    let target = scope.map_or_else(|| ip.clone(), |scope| format!("{ip}%{scope}"));
+    ping_ip(&target).ok();
+
+    if let Some(mac) = query_ip_neighbor(&ip)? {
+        return Ok(mac);
+    }
+
+    warn!("MAC address not found in neighbor table");
+    Err(ArpError::NotFound(ip))
+}
+
+/// Splits an optional `%scope` suffix off an address. Scopes are only
+/// meaningful for IPv6 and are restricted to interface-name characters so they
+/// can be passed to helper commands safely.
+fn parse_target(ip: &str) -> Result<(IpAddr, Option<&str>), ArpError> {
+    let ip = ip.trim();
+    let (address, scope) = match ip.split_once('%') {
+        Some((address, scope)) => (address, Some(scope)),
+        None => (ip, None),
+    };
+
+    let address: IpAddr = address.parse()?;
+    if let Some(scope) = scope {
+        let valid = address.is_ipv6()
+            && !scope.is_empty()
+            && scope
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.' | '@'));
+        if !valid {
+            return Err(ArpError::InvalidScope(scope.to_string()));
+        }
+    }
+
+    Ok((address, scope))
 }
 
-fn ensure_direct_route(ip: &str) -> Result<(), ArpError> {
+/// Fails when the route to `ip` goes through a gateway and returns the
+/// outgoing interface of a direct route when `ip route` reports one.
+fn ensure_direct_route(ip: &str) -> Result<Option<String>, ArpError> {
     let Some(output) = run_command("ip", &["route", "get", ip])? else {
-        return Ok(());
+        return Ok(None);
     };
 
     if !output.status.success() {
         log_command_failure("ip route get", &output);
-        return Ok(());
+        return Ok(None);
     }
 
     let route = String::from_utf8_lossy(&output.stdout);
     let route = route.lines().next().unwrap_or_default().trim();
     if is_indirect_route(route) {
-        warn!(route = %route, "Target IP is not directly reachable for ARP lookup");
+        warn!(route = %route, "Target IP is not directly reachable for neighbor lookup");
         return Err(ArpError::NotDirectlyConnected {
             ip: ip.to_string(),
             route: route.to_string(),
         });
     }
 
-    Ok(())
+    Ok(route_device(route))
 }
 
 #[instrument(skip_all)]
@@ -92,9 +167,30 @@ fn arping_ip(ip: Ipv4Addr) -> Result<Option<String>, ArpError> {
 }
 
 #[instrument(skip_all)]
-fn ping_ip(ip: Ipv4Addr) -> Result<(), ArpError> {
-    let ip = ip.to_string();
-    let Some(output) = run_command("ping", &["-c", "1", "-W", "1", ip.as_str()])? else {
+fn solicit_neighbor(ip: &str, interface: &str) -> Result<Option<String>, ArpError> {
+    debug!("Sending NDP neighbor solicitation");
+    let args = ["-1", "-r", "1", "-w", "1000", ip, interface];
+    let Some(output) = run_command("ndisc6", &args)? else {
+        debug!("ndisc6 command not available");
+        return Ok(None);
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if let Some(mac) = parse_ndisc6_output(&stdout) {
+        debug!(mac = %mac, "MAC address found via neighbor solicitation");
+        return Ok(Some(mac));
+    }
+
+    if !output.status.success() {
+        log_command_failure("ndisc6", &output);
+    }
+
+    Ok(None)
+}
+
+#[instrument(skip_all)]
+fn ping_ip(ip: &str) -> Result<(), ArpError> {
+    let Some(output) = run_command("ping", &["-c", "1", "-W", "1", ip])? else {
         debug!("ping command not available");
         return Ok(());
     };
@@ -179,7 +275,7 @@ fn log_command_failure(command: &str, output: &Output) {
         status = %output.status,
         stderr = %stderr.trim(),
         stdout = %stdout.trim(),
-        "ARP helper command did not return a usable result"
+        "Neighbor lookup helper command did not return a usable result"
     );
 }
 
@@ -187,6 +283,22 @@ fn is_indirect_route(route: &str) -> bool {
     route.split_whitespace().any(|field| field == "via")
 }
 
+fn route_device(route: &str) -> Option<String> {
+    let mut fields = route.split_whitespace();
+    fields.find(|field| *field == "dev")?;
+    fields.next().map(ToString::to_string)
+}
+
+fn parse_ndisc6_output(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (label, mac) = line.split_once(':')?;
+        if label.trim() != "Target link-layer address" {
+            return None;
+        }
+        normalize_mac(mac.trim())
+    })
+}
+
 fn parse_arping_output(output: &str, ip: &str) -> Option<String> {
     output
         .lines()
@@ -256,20 +368,7 @@ fn parse_ip_neighbor_line(line: &str, ip: &str) -> Option<String> {
 }
 
 fn normalize_mac(mac: &str) -> Option<String> {
-    let octets: Vec<&str> = mac.split(':').collect();
-    if octets.len() != 6 {
-        return None;
-    }
-
-    let mut normalized = Vec::with_capacity(6);
-    for octet in octets {
-        if octet.is_empty() || octet.len() > 2 || !octet.chars().all(|ch| ch.is_ascii_hexdigit()) {
-            return None;
-        }
-        normalized.push(format!("{octet:0>2}").to_uppercase());
-    }
-
-    Some(normalized.join(":"))
+    mac.parse::<MacAddress>().ok().map(|mac| mac.to_string())
 }
 
 #[cfg(test)]
@@ -358,6 +457,75 @@ Address                  HWtype  HWaddress           Flags Mask            Iface
         assert_eq!(parse_arping_output(output, "192.168.0.20"), None);
     }
 
+    #[test]
+    fn parse_ipv6_neighbor_entry() {
+        let output = "fe80::1 dev eth0 lladdr 10:ff:e0:6b:65:3b router REACHABLE";
+        assert_eq!(
+            parse_ip_neighbor_output(output, "fe80::1").as_deref(),
+            Some("10:FF:E0:6B:65:3B")
+        );
+    }
+
+    #[test]
+    fn parse_ndisc6_reply() {
+        let output = "\
+Soliciting fe80::1 (fe80::1) on eth0...
+Target link-layer address: 10:FF:E0:6B:65:3B
+ from fe80::1";
+        assert_eq!(
+            parse_ndisc6_output(output).as_deref(),
+            Some("10:FF:E0:6B:65:3B")
+        );
+    }
+
+    #[test]
+    fn ignores_ndisc6_timeout() {
+        let output = "Soliciting fe80::1 (fe80::1) on eth0...\nTimed out.";
+        assert_eq!(parse_ndisc6_output(output), None);
+    }
+
+    #[test]
+    fn parse_target_accepts_ipv4_and_ipv6() {
+        assert!(matches!(
+            parse_target("192.168.0.20"),
+            Ok((IpAddr::V4(_), None))
+        ));
+        assert!(matches!(
+            parse_target("2001:db8::20"),
+            Ok((IpAddr::V6(_), None))
+        ));
+    }
+
+    #[test]
+    fn parse_target_keeps_ipv6_scope() {
+        let (ip, scope) = parse_target("fe80::1%eth0").unwrap();
+        assert_eq!(ip, "fe80::1".parse::<IpAddr>().unwrap());
+        assert_eq!(scope, Some("eth0"));
+    }
+
+    #[test]
+    fn parse_target_rejects_invalid_scopes() {
+        assert!(matches!(
+            parse_target("192.168.0.20%eth0"),
+            Err(ArpError::InvalidScope(_))
+        ));
+        assert!(matches!(
+            parse_target("fe80::1%eth0;reboot"),
+            Err(ArpError::InvalidScope(_))
+        ));
+        assert!(matches!(
+            parse_target("banana"),
+            Err(ArpError::InvalidIp(_))
+        ));
+    }
+
+    #[test]
+    fn extracts_route_device() {
+        let route =
+            "2001:db8::20 from :: dev eth0 proto kernel src 2001:db8::10 metric 256 pref medium";
+        assert_eq!(route_device(route).as_deref(), Some("eth0"));
+    }
+
     #[test]
     fn detects_indirect_route() {
         let route = "192.168.0.20 via 192.168.139.1 dev eth0 src 192.168.139.2 uid 1000";
@@ -0,0 +1,435 @@
+//! CSV form of the portable device format, for editing device lists in a
+//! spreadsheet.
+//!
+//! The header row names the columns, in any order. Besides the known columns
+//! every column becomes a metadata key. A device with several NICs lists its
+//! MAC addresses in one cell separated by `;`, and so do tags. Interface
+//! labels and broadcast addresses are not part of the CSV format.
+//!
+//! Spreadsheets run cells starting with `=`, `+`, `-`, `@`, a tab or a
+//! carriage return as formulas, so such cells are written with a `'` in
+//! front, which spreadsheets hide, and the `'` is dropped again on import.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+
+use csv::{ReaderBuilder, Trim, WriterBuilder};
+
+use crate::api::devices::{ExportResponse, ImportRequest, InterfaceRequest};
+use crate::models::FieldErrors;
+
+pub const CONTENT_TYPE: &str = "text/csv; charset=utf-8";
+
+const NAME: &str = "name";
+const MAC_ADDRESS: &str = "mac_address";
+const IP_ADDRESS: &str = "ip_address";
+const PORT: &str = "port";
+const DESCRIPTION: &str = "description";
+const TAGS: &str = "tags";
+const FAVORITE: &str = "favorite";
+const COLUMNS: [&str; 7] = [
+    NAME,
+    MAC_ADDRESS,
+    IP_ADDRESS,
+    PORT,
+    DESCRIPTION,
+    TAGS,
+    FAVORITE,
+];
+/// Separates several MAC addresses or tags within one cell.
+const LIST_SEPARATOR: char = ';';
+/// Marks a cell as text rather than a formula.
+const TEXT_PREFIX: char = '\'';
+/// First characters that make spreadsheets read a cell as a formula.
+const FORMULA_STARTS: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+/// Field name for an error about the whole row starting at `line`.
+pub fn line_field(line: u64) -> String {
+    format!("line {line}")
+}
+
+/// Prefix for the errors of the row starting at `line`, e.g. `line 3.port`.
+fn line_prefix(line: u64) -> String {
+    format!("{}.", line_field(line))
+}
+
+/// Writes `devices` with the known columns first, followed by one column per
+/// metadata key in use.
+pub fn write_devices(devices: &[ExportResponse]) -> io::Result<String> {
+    let metadata_keys: BTreeSet<&str> = devices
+        .iter()
+        .flat_map(|device| device.metadata.keys().map(String::as_str))
+        .filter(|key| {
+            !COLUMNS
+                .iter()
+                .any(|column| column.eq_ignore_ascii_case(key))
+        })
+        .collect();
+
+    let mut writer = WriterBuilder::new().from_writer(Vec::new());
+    writer.write_record(
+        COLUMNS
+            .iter()
+            .chain(&metadata_keys)
+            .map(|header| escape_formula(header)),
+    )?;
+    for device in devices {
+        let macs: Vec<String> = device
+            .interfaces
+            .iter()
+            .map(|interface| interface.mac_address.to_string())
+            .collect();
+        let mut record = vec![
+            device.name.clone(),
+            macs.join(&LIST_SEPARATOR.to_string()),
+            device.ip_address.clone().unwrap_or_default(),
+            device.port.to_string(),
+            device.description.clone().unwrap_or_default(),
+            device.tags.join(&LIST_SEPARATOR.to_string()),
+            device.favorite.to_string(),
+        ];
+        record.extend(
+            metadata_keys
+                .iter()
+                .map(|key| device.metadata.get(*key).cloned().unwrap_or_default()),
+        );
+        writer.write_record(record.iter().map(|cell| escape_formula(cell)))?;
+    }
+
+    let content = writer.into_inner().map_err(|err| err.into_error())?;
+    String::from_utf8(content).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// A parsed row and the line it starts on.
+#[derive(Debug)]
+pub struct CsvRow {
+    pub line: u64,
+    pub request: ImportRequest,
+}
+
+/// Parses every row of `content`, reporting problems in `errors` under the
+/// row's line number so one bad row does not hide the problems of the others.
+/// Cells that cannot be read are left empty and rows that cannot be read at
+/// all are left out.
+pub fn read_devices(content: &str, errors: &mut FieldErrors) -> Vec<CsvRow> {
+    // Spreadsheets on Windows like to start their exports with a BOM.
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let mut reader = ReaderBuilder::new()
+        .flexible(true)
+        .trim(Trim::All)
+        .from_reader(content.as_bytes());
+
+    let header_prefix = line_prefix(1);
+    let headers = match reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(err) => {
+            errors.add(line_field(1), err.to_string());
+            return Vec::new();
+        }
+    };
+    let columns: Vec<Column> = headers
+        .iter()
+        .map(|header| Column::from_header(unescape_formula(header)))
+        .collect();
+    for required in [NAME, MAC_ADDRESS] {
+        if !columns.contains(&Column::Known(required)) {
+            errors.add(
+                format!("{header_prefix}{required}"),
+                format!("Missing column {required}"),
+            );
+        }
+    }
+    if columns.contains(&Column::Unnamed) {
+        errors.add(line_field(1), "Every column needs a name");
+    }
+    if errors.contains(&format!("{header_prefix}{NAME}"))
+        || errors.contains(&format!("{header_prefix}{MAC_ADDRESS}"))
+    {
+        return Vec::new();
+    }
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                let line = err.position().map_or(0, csv::Position::line);
+                errors.add(line_field(line), err.to_string());
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, csv::Position::line);
+        if record.len() > columns.len() {
+            errors.add(
+                line_field(line),
+                format!(
+                    "Row has {} fields but the header has {}",
+                    record.len(),
+                    columns.len()
+                ),
+            );
+            continue;
+        }
+        let request = parse_row(&columns, &record, &line_prefix(line), errors);
+        rows.push(CsvRow { line, request });
+    }
+    rows
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Column {
+    Known(&'static str),
+    Metadata(String),
+    Unnamed,
+}
+
+impl Column {
+    fn from_header(header: &str) -> Self {
+        if header.is_empty() {
+            return Self::Unnamed;
+        }
+        COLUMNS
+            .iter()
+            .find(|column| column.eq_ignore_ascii_case(header))
+            .map_or_else(
+                || Self::Metadata(header.to_string()),
+                |column| Self::Known(column),
+            )
+    }
+}
+
+fn parse_row(
+    columns: &[Column],
+    record: &csv::StringRecord,
+    prefix: &str,
+    errors: &mut FieldErrors,
+) -> ImportRequest {
+    let mut request = ImportRequest {
+        name: String::new(),
+        mac_address: None,
+        interfaces: None,
+        port: None,
+        ip_address: None,
+        description: None,
+        tags: Vec::new(),
+        metadata: BTreeMap::new(),
+        favorite: false,
+    };
+    // Cells missing from a short row count as empty.
+    for (column, value) in columns.iter().zip(record.iter()) {
+        let value = unescape_formula(value);
+        if value.is_empty() {
+            continue;
+        }
+        match column {
+            Column::Known(NAME) => request.name = value.to_string(),
+            Column::Known(MAC_ADDRESS) => {
+                let macs = split_list(value);
+                if macs.len() == 1 {
+                    request.mac_address = macs.into_iter().next();
+                } else {
+                    request.interfaces = Some(
+                        macs.into_iter()
+                            .map(|mac_address| InterfaceRequest {
+                                mac_address,
+                                label: None,
+                                broadcast: None,
+                            })
+                            .collect(),
+                    );
+                }
+            }
+            Column::Known(IP_ADDRESS) => request.ip_address = Some(value.to_string()),
+            Column::Known(PORT) => match value.parse() {
+                Ok(port) => request.port = Some(port),
+                Err(_) => {
+                    errors.add(
+                        format!("{prefix}{PORT}"),
+                        "Port must be a number between 1 and 65535",
+                    );
+                }
+            },
+            Column::Known(DESCRIPTION) => request.description = Some(value.to_string()),
+            Column::Known(TAGS) => request.tags = split_list(value),
+            Column::Known(FAVORITE) => match parse_bool(value) {
+                Some(favorite) => request.favorite = favorite,
+                None => {
+                    errors.add(
+                        format!("{prefix}{FAVORITE}"),
+                        "Favorite must be true or false",
+                    );
+                }
+            },
+            Column::Metadata(key) => {
+                request.metadata.insert(key.clone(), value.to_string());
+            }
+            Column::Known(_) | Column::Unnamed => {}
+        }
+    }
+    request
+}
+
+/// `cell` with a [`TEXT_PREFIX`] if a spreadsheet would run it as a formula.
+fn escape_formula(cell: &str) -> String {
+    if cell.starts_with(FORMULA_STARTS) {
+        format!("{TEXT_PREFIX}{cell}")
+    } else {
+        cell.to_string()
+    }
+}
+
+/// Undoes [`escape_formula`]; a `'` in front of anything else is kept.
+fn unescape_formula(cell: &str) -> &str {
+    match cell.strip_prefix(TEXT_PREFIX) {
+        Some(text) if text.starts_with(FORMULA_STARTS) => text,
+        _ => cell,
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(LIST_SEPARATOR)
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "1" => Some(true),
+        "false" | "no" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{MacAddress, NetworkInterface, ValidationError};
+
+    fn exported(name: &str, macs: &[&str]) -> ExportResponse {
+        ExportResponse {
+            name: name.to_string(),
+            interfaces: macs
+                .iter()
+                .map(|mac| NetworkInterface {
+                    mac_address: mac.parse::<MacAddress>().unwrap(),
+                    label: None,
+                    broadcast: None,
+                })
+                .collect(),
+            port: 9,
+            ip_address: Some("192.168.1.10".to_string()),
+            description: Some("Rack, top shelf".to_string()),
+            tags: vec!["lab".to_string(), "gpu".to_string()],
+            metadata: BTreeMap::from([("location".to_string(), "Office".to_string())]),
+            favorite: true,
+        }
+    }
+
+    fn field_errors(errors: FieldErrors) -> Vec<(String, String)> {
+        match errors.into_result() {
+            Ok(()) => Vec::new(),
+            Err(ValidationError::Fields(errors)) => errors
+                .into_iter()
+                .map(|error| (error.field, error.message))
+                .collect(),
+            Err(err) => panic!("unexpected error: {err}"),
+        }
+    }
+
+    #[test]
+    fn written_devices_read_back() {
+        let devices = [
+            exported("Server", &["AA:BB:CC:DD:EE:01"]),
+            exported("Router", &["AA:BB:CC:DD:EE:02", "AA:BB:CC:DD:EE:03"]),
+        ];
+
+        let content = write_devices(&devices).unwrap();
+        assert!(
+            content.starts_with(
+                "name,mac_address,ip_address,port,description,tags,favorite,location\n"
+            )
+        );
+        assert!(content.contains("\"Rack, top shelf\""));
+
+        let mut errors = FieldErrors::default();
+        let rows = read_devices(&content, &mut errors);
+        assert!(field_errors(errors).is_empty());
+        assert_eq!(
+            rows.iter().map(|row| row.line).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+        let server = &rows[0].request;
+        assert_eq!(server.mac_address.as_deref(), Some("AA:BB:CC:DD:EE:01"));
+        assert_eq!(server.port, Some(9));
+        assert_eq!(server.description.as_deref(), Some("Rack, top shelf"));
+        assert_eq!(server.tags, vec!["lab", "gpu"]);
+        assert_eq!(server.metadata["location"], "Office");
+        assert!(server.favorite);
+        let router = rows[1].request.interfaces.as_ref().unwrap();
+        assert_eq!(router.len(), 2);
+    }
+
+    #[test]
+    fn bad_rows_are_reported_by_line() {
+        let content = "\u{feff}Name,MAC_Address,Port,Favorite\n\
+                       Good,AA:BB:CC:DD:EE:01,9,yes\n\
+                       Bad port,AA:BB:CC:DD:EE:02,wake,no\n\
+                       Too long,AA:BB:CC:DD:EE:03,9,no,extra\n\
+                       Bad flag,AA:BB:CC:DD:EE:04,,maybe\n";
+
+        let mut errors = FieldErrors::default();
+        let rows = read_devices(content, &mut errors);
+
+        let names: Vec<&str> = rows.iter().map(|row| row.request.name.as_str()).collect();
+        assert_eq!(names, ["Good", "Bad port", "Bad flag"]);
+        let fields: Vec<String> = field_errors(errors)
+            .into_iter()
+            .map(|(field, _)| field)
+            .collect();
+        assert_eq!(fields, ["line 3.port", "line 4", "line 5.favorite"]);
+    }
+
+    #[test]
+    fn missing_required_columns_are_reported() {
+        let mut errors = FieldErrors::default();
+        let rows = read_devices("name,ip_address\nServer,10.0.0.1\n", &mut errors);
+
+        assert!(rows.is_empty());
+        assert_eq!(
+            field_errors(errors),
+            vec![(
+                "line 1.mac_address".to_string(),
+                "Missing column mac_address".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn formulas_are_written_as_text_and_read_back() {
+        let mut device = exported("=HYPERLINK(\"http://evil\")", &["AA:BB:CC:DD:EE:01"]);
+        device.description = Some("-1 floor".to_string());
+        device.metadata = BTreeMap::from([
+            ("@owner".to_string(), "+1 555".to_string()),
+            ("quote".to_string(), "'as is".to_string()),
+        ]);
+
+        let content = write_devices(&[device]).unwrap();
+        assert!(content.starts_with(
+            "name,mac_address,ip_address,port,description,tags,favorite,'@owner,quote\n"
+        ));
+        assert!(content.contains("\"'=HYPERLINK(\"\"http://evil\"\")\""));
+        assert!(content.contains(",'-1 floor,"));
+
+        let mut errors = FieldErrors::default();
+        let rows = read_devices(&content, &mut errors);
+        assert!(field_errors(errors).is_empty());
+        let request = &rows[0].request;
+        assert_eq!(request.name, "=HYPERLINK(\"http://evil\")");
+        assert_eq!(request.description.as_deref(), Some("-1 floor"));
+        assert_eq!(request.metadata["@owner"], "+1 555");
+        assert_eq!(request.metadata["quote"], "'as is");
+    }
+}
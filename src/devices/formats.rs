@@ -0,0 +1,216 @@
+//! Device files of other tools: read into import entries so devices can be
+//! moved over without typing them in again, and written as configuration
+//! for the tools that wake or manage them.
+//!
+//! Each reader maps what it can onto [`ImportRequest`] and reports every
+//! other field that is set as a warning, so nothing is dropped silently.
+
+pub mod android;
+pub mod ansible;
+pub mod ethers;
+pub mod home_assistant;
+pub mod upsnap;
+pub mod wolweb;
+
+use std::collections::HashSet;
+
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::api::devices::{ImportFormat, ImportRequest};
+use crate::models::{FieldError, ValidationError};
+
+/// The devices read from a file, each with the label its errors and
+/// warnings use, e.g. `[0]` or `line 2`.
+#[derive(Debug, Default)]
+pub struct Converted {
+    pub entries: Vec<(String, ImportRequest)>,
+    pub warnings: Vec<FieldError>,
+}
+
+impl Converted {
+    /// Warns that `field` of `entry` has no counterpart and was left out.
+    fn dropped(&mut self, entry: &str, field: &str, reason: &str) {
+        self.warnings
+            .push(FieldError::new(format!("{entry}.{field}"), reason));
+    }
+
+    /// Warns about every field of `other` that is set, `other` holding the
+    /// fields a reader does not map.
+    fn drop_unmapped(&mut self, entry: &str, other: &Map<String, Value>) {
+        for (field, value) in other {
+            if is_set(value) {
+                self.dropped(entry, field, "Not imported; no device field matches it");
+            }
+        }
+    }
+}
+
+/// Tells the format of `content` apart by its shape: JSON by the fields of
+/// its devices, `/etc/ethers` by lines starting with a MAC address, and CSV
+/// otherwise.
+pub fn detect(content: &str) -> ImportFormat {
+    let content = content.trim_start_matches('\u{feff}').trim();
+    if content.starts_with(['{', '[']) {
+        return serde_json::from_str(content)
+            .map_or(ImportFormat::Json, |document| detect_json(&document));
+    }
+    if ethers::matches(content) {
+        ImportFormat::Ethers
+    } else {
+        ImportFormat::Csv
+    }
+}
+
+fn detect_json(document: &Value) -> ImportFormat {
+    let has = |device: Option<&Value>, field: &str| {
+        device
+            .and_then(Value::as_object)
+            .is_some_and(|device| device.contains_key(field))
+    };
+    match document {
+        Value::Object(object) if object.contains_key("items") => ImportFormat::Upsnap,
+        Value::Object(object) => {
+            let first = object
+                .get("devices")
+                .and_then(Value::as_array)
+                .and_then(|devices| devices.first());
+            if has(first, "mac") {
+                ImportFormat::Wolweb
+            } else {
+                ImportFormat::Backup
+            }
+        }
+        Value::Array(devices) => {
+            let first = devices.first();
+            if has(first, "title") {
+                ImportFormat::Android
+            } else if has(first, "mac") {
+                ImportFormat::Upsnap
+            } else {
+                ImportFormat::Json
+            }
+        }
+        _ => ImportFormat::Json,
+    }
+}
+
+/// The error for a file that cannot be read as `format` at all.
+pub fn invalid_file(format: &str, err: impl std::fmt::Display) -> ValidationError {
+    ValidationError::Fields(vec![FieldError::new(
+        "file",
+        format!("Not {format}: {err}"),
+    )])
+}
+
+fn parse_json<T: DeserializeOwned>(content: &str, format: &str) -> Result<T, ValidationError> {
+    serde_json::from_str(content).map_err(|err| invalid_file(format, err))
+}
+
+/// Whether `value` carries anything worth a warning when it is dropped.
+fn is_set(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(value) => *value,
+        Value::Number(number) => number.as_f64() != Some(0.0),
+        Value::String(value) => !value.trim().is_empty(),
+        Value::Array(values) => !values.is_empty(),
+        Value::Object(object) => !object.is_empty(),
+    }
+}
+
+/// A text field that may be missing, `null` or blank.
+fn text(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Hands out host names made from device names: lowercase letters, digits
+/// and dashes, with a number appended to names already handed out.
+#[derive(Debug, Default)]
+struct HostNames(HashSet<String>);
+
+impl HostNames {
+    fn claim(&mut self, name: &str) -> String {
+        let mut base = String::new();
+        for c in name.trim().chars() {
+            if c.is_ascii_alphanumeric() {
+                base.push(c.to_ascii_lowercase());
+            } else if !base.is_empty() && !base.ends_with('-') {
+                base.push('-');
+            }
+        }
+        let base = match base.trim_end_matches('-') {
+            "" => "device",
+            trimmed => trimmed,
+        }
+        .to_string();
+
+        let mut host = base.clone();
+        let mut number = 2;
+        while !self.0.insert(host.clone()) {
+            host = format!("{base}-{number}");
+            number += 1;
+        }
+        host
+    }
+}
+
+/// `value` as a double-quoted YAML scalar, which shares JSON's escapes.
+fn yaml_string(value: &str) -> String {
+    Value::from(value).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_are_detected_by_shape() {
+        let cases = [
+            (
+                r#"[{"name":"PC","mac_address":"AA:BB:CC:DD:EE:01"}]"#,
+                ImportFormat::Json,
+            ),
+            (
+                r#"{"version":2,"devices":[{"id":"a","interfaces":[]}]}"#,
+                ImportFormat::Backup,
+            ),
+            (
+                r#"{"page":1,"items":[{"name":"PC","mac":"AA:BB:CC:DD:EE:01"}]}"#,
+                ImportFormat::Upsnap,
+            ),
+            (
+                r#"[{"name":"PC","mac":"AA:BB:CC:DD:EE:01","netmask":""}]"#,
+                ImportFormat::Upsnap,
+            ),
+            (
+                r#"{"devices":[{"name":"PC","mac":"AA:BB:CC:DD:EE:01"}]}"#,
+                ImportFormat::Wolweb,
+            ),
+            (
+                r#"[{"title":"PC","mac":"AA:BB:CC:DD:EE:01"}]"#,
+                ImportFormat::Android,
+            ),
+            ("# lab\n0:1a:2b:3c:4d:5e nas\n", ImportFormat::Ethers),
+            (
+                "name,mac_address\nPC,AA:BB:CC:DD:EE:01\n",
+                ImportFormat::Csv,
+            ),
+            ("[not json", ImportFormat::Json),
+        ];
+        for (content, format) in cases {
+            assert_eq!(detect(content), format, "{content}");
+        }
+    }
+
+    #[test]
+    fn host_names_are_unique_slugs() {
+        let mut names = HostNames::default();
+        assert_eq!(names.claim("Gaming PC"), "gaming-pc");
+        assert_eq!(names.claim("gaming  pc!"), "gaming-pc-2");
+        assert_eq!(names.claim("Büro"), "b-ro");
+        assert_eq!(names.claim("???"), "device");
+    }
+}
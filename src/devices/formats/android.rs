@@ -0,0 +1,98 @@
+//! Backup of the Android "Wake On Lan" app: an array of devices with a
+//! `title`, a `mac`, the `ip` the packet is sent to and its `port`. The
+//! app's group becomes a tag.
+
+use std::net::IpAddr;
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use super::{Converted, parse_json, text};
+use crate::api::devices::{ImportRequest, InterfaceRequest};
+use crate::models::ValidationError;
+
+#[derive(Deserialize)]
+struct Device {
+    title: String,
+    mac: String,
+    ip: Option<String>,
+    port: Option<Value>,
+    group: Option<String>,
+    #[serde(flatten)]
+    other: Map<String, Value>,
+}
+
+pub fn read_devices(content: &str) -> Result<Converted, ValidationError> {
+    let devices: Vec<Device> = parse_json(content, "a Wake On Lan backup")?;
+    let mut converted = Converted::default();
+    for (index, device) in devices.into_iter().enumerate() {
+        let entry = format!("[{index}]");
+        converted.drop_unmapped(&entry, &device.other);
+
+        // The app also takes host names, which cannot be broadcast to.
+        let broadcast = text(device.ip).and_then(|ip| {
+            if ip.parse::<IpAddr>().is_ok() {
+                Some(ip)
+            } else {
+                converted.dropped(
+                    &entry,
+                    "ip",
+                    "Not imported; only an IP address can be a broadcast address",
+                );
+                None
+            }
+        });
+        let port = device.port.and_then(|port| {
+            let number = match &port {
+                Value::Number(number) => number.as_u64(),
+                Value::String(text) => text.trim().parse().ok(),
+                _ => None,
+            };
+            let port = number.and_then(|number| u16::try_from(number).ok());
+            if port.is_none() {
+                converted.dropped(&entry, "port", "Not imported; not a port number");
+            }
+            port
+        });
+        let request = ImportRequest {
+            name: device.title.trim().to_string(),
+            interfaces: Some(vec![InterfaceRequest {
+                mac_address: device.mac.trim().to_string(),
+                label: None,
+                broadcast,
+            }]),
+            port,
+            tags: text(device.group).into_iter().collect(),
+            ..ImportRequest::default()
+        };
+        converted.entries.push((entry, request));
+    }
+    Ok(converted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn devices_map_and_report_lost_fields() {
+        let content = r#"[
+            {"title":"Desktop","mac":"AA:BB:CC:DD:EE:01","ip":"192.168.1.255","port":"9","group":"Home"},
+            {"title":"Server","mac":"AA:BB:CC:DD:EE:02","ip":"server.lan","port":9,"ssid":"HomeWifi"}
+        ]"#;
+
+        let converted = read_devices(content).unwrap();
+
+        let desktop = &converted.entries[0].1;
+        assert_eq!(desktop.port, Some(9));
+        assert_eq!(desktop.tags, ["Home"]);
+        let server = &converted.entries[1].1;
+        assert_eq!(server.interfaces.as_ref().unwrap()[0].broadcast, None);
+        let warned: Vec<&str> = converted
+            .warnings
+            .iter()
+            .map(|warning| warning.field.as_str())
+            .collect();
+        assert_eq!(warned, ["[1].ssid", "[1].ip"]);
+    }
+}
@@ -0,0 +1,277 @@
+//! Ansible inventory with a host per device and a group per tag, as INI or
+//! YAML. Hosts are named after their device; `ansible_host` is the device's
+//! address, and `mac_address` and `wol_port` are there for the
+//! `community.general.wakeonlan` module.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::Value;
+
+use super::HostNames;
+use crate::models::Device;
+
+const HEADER: &str = "# Ansible inventory written by jump_rs, grouped by tag.\n";
+
+struct Host<'a> {
+    name: String,
+    device: &'a Device,
+}
+
+impl Host<'_> {
+    /// Host variables. They are addresses and numbers, so none need quoting
+    /// in INI; in YAML they are written as JSON scalars.
+    fn vars(&self) -> Vec<(&'static str, Value)> {
+        let mut vars = Vec::new();
+        if let Some(address) = &self.device.ip_address {
+            vars.push(("ansible_host", Value::from(address.as_str())));
+        }
+        if let Some(interface) = self.device.interfaces.first() {
+            vars.push((
+                "mac_address",
+                Value::from(interface.mac_address.to_string()),
+            ));
+        }
+        vars.push(("wol_port", Value::from(self.device.port)));
+        vars
+    }
+}
+
+/// Ansible's own groups, which every inventory already has.
+const RESERVED_GROUPS: [&str; 2] = ["all", "ungrouped"];
+
+/// Hosts in device order and the hosts of each group, by group name.
+struct Inventory<'a> {
+    hosts: Vec<Host<'a>>,
+    groups: BTreeMap<String, Vec<String>>,
+    /// Why some tags got a group not named after them, as comment lines.
+    notes: Vec<String>,
+}
+
+fn inventory(devices: &[Device]) -> Inventory<'_> {
+    let mut names = HostNames::default();
+    let hosts: Vec<Host> = devices
+        .iter()
+        .map(|device| Host {
+            name: names.claim(&device.name),
+            device,
+        })
+        .collect();
+    let mut group_names = GroupNames::default();
+    let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for host in &hosts {
+        for tag in &host.device.tags {
+            let members = groups.entry(group_names.claim(tag)).or_default();
+            if !members.contains(&host.name) {
+                members.push(host.name.clone());
+            }
+        }
+    }
+    Inventory {
+        hosts,
+        groups,
+        notes: group_names.notes,
+    }
+}
+
+/// Hands out a group per tag. A tag named like one of Ansible's own groups
+/// gets a `tag_` prefix, and a tag whose group another tag already has gets
+/// a number appended, so no two tags share a group; both leave a note.
+#[derive(Debug, Default)]
+struct GroupNames {
+    by_tag: HashMap<String, String>,
+    /// The tag each group was handed out for.
+    tags: HashMap<String, String>,
+    notes: Vec<String>,
+}
+
+impl GroupNames {
+    fn claim(&mut self, tag: &str) -> String {
+        if let Some(group) = self.by_tag.get(tag) {
+            return group.clone();
+        }
+        let base = group_name(tag);
+        let mut group = base.clone();
+        if RESERVED_GROUPS.contains(&group.as_str()) {
+            group = format!("tag_{base}");
+            self.notes.push(format!(
+                "Tag \"{tag}\" is group {group}; {base} is a group of Ansible's own."
+            ));
+        }
+        let wanted = group.clone();
+        let mut number = 2;
+        while self.tags.contains_key(&group) {
+            group = format!("{wanted}_{number}");
+            number += 1;
+        }
+        if group != wanted {
+            self.notes.push(format!(
+                "Tag \"{tag}\" is group {group}; {wanted} is tag \"{}\".",
+                self.tags[&wanted]
+            ));
+        }
+        self.tags.insert(group.clone(), tag.to_string());
+        self.by_tag.insert(tag.to_string(), group.clone());
+        group
+    }
+}
+
+/// Ansible group names may only hold letters, digits and underscores and
+/// must not start with a digit.
+fn group_name(tag: &str) -> String {
+    let name: String = tag
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{name}")
+    } else {
+        name
+    }
+}
+
+/// The header, followed by the notes on renamed groups.
+fn header(notes: &[String]) -> String {
+    let mut content = String::from(HEADER);
+    for note in notes {
+        content.push_str(&format!("# {note}\n"));
+    }
+    content
+}
+
+/// Lists every host with its variables first, then one section per group.
+pub fn write_ini(devices: &[Device]) -> String {
+    let Inventory {
+        hosts,
+        groups,
+        notes,
+    } = inventory(devices);
+    let mut content = header(&notes);
+    for host in &hosts {
+        content.push_str(&host.name);
+        for (key, value) in host.vars() {
+            let value = match value {
+                Value::String(value) => value,
+                value => value.to_string(),
+            };
+            content.push_str(&format!(" {key}={value}"));
+        }
+        content.push('\n');
+    }
+    for (group, members) in &groups {
+        content.push_str(&format!("\n[{group}]\n"));
+        for member in members {
+            content.push_str(&format!("{member}\n"));
+        }
+    }
+    content
+}
+
+pub fn write_yaml(devices: &[Device]) -> String {
+    let Inventory {
+        hosts,
+        groups,
+        notes,
+    } = inventory(devices);
+    let mut content = header(&notes);
+    if hosts.is_empty() {
+        content.push_str("all:\n  hosts: {}\n");
+    } else {
+        content.push_str("all:\n  hosts:\n");
+    }
+    for host in &hosts {
+        content.push_str(&format!("    {}:\n", host.name));
+        for (key, value) in host.vars() {
+            content.push_str(&format!("      {key}: {value}\n"));
+        }
+    }
+    if !groups.is_empty() {
+        content.push_str("  children:\n");
+    }
+    for (group, members) in &groups {
+        content.push_str(&format!("    {group}:\n      hosts:\n"));
+        for member in members {
+            content.push_str(&format!("        {member}:\n"));
+        }
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::NetworkInterface;
+
+    fn devices() -> Vec<Device> {
+        let device = |name: &str, mac: &str, tags: &[&str]| {
+            Device::new(
+                name.to_string(),
+                vec![NetworkInterface::new(mac).unwrap()],
+                Some("192.168.1.10".to_string()),
+                9,
+                None,
+            )
+            .with_tags(tags.iter().map(ToString::to_string).collect())
+        };
+        vec![
+            device("Gaming PC", "AA:BB:CC:DD:EE:01", &["home-lab", "gpu"]),
+            device("NAS", "AA:BB:CC:DD:EE:02", &["home-lab"]),
+        ]
+    }
+
+    #[test]
+    fn ini_groups_hosts_by_tag() {
+        assert_eq!(
+            write_ini(&devices()),
+            "# Ansible inventory written by jump_rs, grouped by tag.\n\
+             gaming-pc ansible_host=192.168.1.10 mac_address=AA:BB:CC:DD:EE:01 wol_port=9\n\
+             nas ansible_host=192.168.1.10 mac_address=AA:BB:CC:DD:EE:02 wol_port=9\n\
+             \n[gpu]\ngaming-pc\n\
+             \n[home_lab]\ngaming-pc\nnas\n"
+        );
+    }
+
+    #[test]
+    fn yaml_groups_hosts_by_tag() {
+        let content = write_yaml(&devices());
+
+        assert!(content.contains(
+            "    gaming-pc:\n      ansible_host: \"192.168.1.10\"\n      mac_address: \"AA:BB:CC:DD:EE:01\"\n      wol_port: 9\n"
+        ));
+        assert!(content.ends_with(
+            "  children:\n    gpu:\n      hosts:\n        gaming-pc:\n    home_lab:\n      hosts:\n        gaming-pc:\n        nas:\n"
+        ));
+        assert_eq!(write_yaml(&[]), format!("{HEADER}all:\n  hosts: {{}}\n"));
+    }
+
+    #[test]
+    fn tags_never_share_a_group_or_take_ansibles_own() {
+        let device = devices().remove(0).with_tags(vec![
+            "a-b".to_string(),
+            "a_b".to_string(),
+            "all".to_string(),
+            "ungrouped".to_string(),
+        ]);
+
+        let content = write_ini(&[device]);
+
+        assert!(content.starts_with(
+            "# Ansible inventory written by jump_rs, grouped by tag.\n\
+             # Tag \"a_b\" is group a_b_2; a_b is tag \"a-b\".\n\
+             # Tag \"all\" is group tag_all; all is a group of Ansible's own.\n\
+             # Tag \"ungrouped\" is group tag_ungrouped; ungrouped is a group of Ansible's own.\n"
+        ));
+        assert!(content.ends_with(
+            "\n[a_b]\ngaming-pc\n\
+             \n[a_b_2]\ngaming-pc\n\
+             \n[tag_all]\ngaming-pc\n\
+             \n[tag_ungrouped]\ngaming-pc\n"
+        ));
+    }
+}
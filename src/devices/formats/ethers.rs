@@ -0,0 +1,156 @@
+//! `/etc/ethers`: one MAC address and host per line, `#` starting a comment.
+//! On import the host, a host name or an IP address, names the device and,
+//! if it resolves here, is where its status is checked. On export every
+//! interface gets a line with a host name made from the device name, for
+//! `etherwake <name>`.
+
+use super::{Converted, HostNames};
+use crate::api::devices::ImportRequest;
+use crate::devices::csv::line_field;
+use crate::models::{Device, FieldError, MacAddress, unresolvable_hosts};
+
+/// Whether `content` has at least one entry and every entry starts with a
+/// MAC address followed by a host.
+pub fn matches(content: &str) -> bool {
+    let mut entries = content.lines().filter_map(entry).peekable();
+    entries.peek().is_some()
+        && entries.all(|fields| fields.len() >= 2 && fields[0].parse::<MacAddress>().is_ok())
+}
+
+/// The fields of `line` without its comment, or `None` for a blank line.
+fn entry(line: &str) -> Option<Vec<&str>> {
+    let line = line.split('#').next().unwrap_or_default();
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    (!fields.is_empty()).then_some(fields)
+}
+
+/// Host names often only resolve on the machine the file comes from; those
+/// that do not resolve here are dropped from the IP address with a warning.
+pub async fn read_devices(content: &str) -> Converted {
+    let mut converted = Converted::default();
+    for (index, line) in content.lines().enumerate() {
+        let Some(fields) = entry(line) else {
+            continue;
+        };
+        let label = line_field(index as u64 + 1);
+        let host = fields.get(1).map(ToString::to_string);
+        if fields.len() > 2 {
+            converted.warnings.push(FieldError::new(
+                label.clone(),
+                format!(
+                    "Not imported; text after the host: {}",
+                    fields[2..].join(" ")
+                ),
+            ));
+        }
+        let request = ImportRequest {
+            name: host.clone().unwrap_or_default(),
+            mac_address: Some(fields[0].to_string()),
+            ip_address: host,
+            ..ImportRequest::default()
+        };
+        converted.entries.push((label, request));
+    }
+
+    let hosts = converted
+        .entries
+        .iter()
+        .filter_map(|(_, request)| request.ip_address.clone());
+    let unresolvable = unresolvable_hosts(hosts).await;
+    let mut dropped = Vec::new();
+    for (label, request) in &mut converted.entries {
+        if let Some(host) = request
+            .ip_address
+            .take_if(|host| unresolvable.contains(host))
+        {
+            dropped.push((label.clone(), host));
+        }
+    }
+    for (label, host) in dropped {
+        converted.dropped(
+            &label,
+            "ip_address",
+            &format!("Not imported; host name {host} does not resolve on this server"),
+        );
+    }
+    converted
+}
+
+/// Writes a line per interface. A device's first interface is named after
+/// the device, the others after the device and their label or position.
+pub fn write_devices(devices: &[Device]) -> String {
+    let mut names = HostNames::default();
+    let mut content =
+        String::from("# Written by jump_rs; wake a device with `etherwake <name>`.\n");
+    for device in devices {
+        for (index, interface) in device.interfaces.iter().enumerate() {
+            let name = match (index, &interface.label) {
+                (0, _) => names.claim(&device.name),
+                (_, Some(label)) => names.claim(&format!("{} {label}", device.name)),
+                (_, None) => names.claim(&format!("{} {}", device.name, index + 1)),
+            };
+            content.push_str(&format!("{} {name}\n", interface.mac_address));
+        }
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::NetworkInterface;
+
+    #[tokio::test]
+    async fn hosts_name_the_devices() {
+        let content = "# Lab machines\n\n0:1a:2b:3c:4d:5e  nas.invalid\naa-bb-cc-dd-ee-01 192.168.1.20 # rack\n";
+
+        assert!(matches(content));
+        let converted = read_devices(content).await;
+
+        let labels: Vec<&str> = converted
+            .entries
+            .iter()
+            .map(|(label, _)| label.as_str())
+            .collect();
+        assert_eq!(labels, ["line 3", "line 4"]);
+        let nas = &converted.entries[0].1;
+        assert_eq!(nas.name, "nas.invalid");
+        assert_eq!(nas.ip_address, None);
+        let rack = &converted.entries[1].1;
+        assert_eq!(rack.ip_address.as_deref(), Some("192.168.1.20"));
+        let fields: Vec<&str> = converted
+            .warnings
+            .iter()
+            .map(|warning| warning.field.as_str())
+            .collect();
+        assert_eq!(fields, ["line 3.ip_address"]);
+        assert!(!matches("name,mac_address\nPC,AA:BB:CC:DD:EE:01\n"));
+    }
+
+    #[tokio::test]
+    async fn written_file_reads_back() {
+        let router = Device::new(
+            "Router".to_string(),
+            vec![
+                NetworkInterface::new("AA:BB:CC:DD:EE:01").unwrap(),
+                NetworkInterface::parse("AA:BB:CC:DD:EE:02", Some("WAN".to_string()), None)
+                    .unwrap(),
+            ],
+            None,
+            9,
+            None,
+        );
+        let devices = [router.clone(), router];
+
+        let content = write_devices(&devices);
+
+        assert!(matches(&content));
+        let names: Vec<String> = read_devices(&content)
+            .await
+            .entries
+            .into_iter()
+            .map(|(_, request)| request.name)
+            .collect();
+        assert_eq!(names, ["router", "router-wan", "router-2", "router-wan-2"]);
+    }
+}
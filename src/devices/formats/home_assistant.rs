@@ -0,0 +1,82 @@
+//! Home Assistant `wake_on_lan` switches, one per interface, ready to paste
+//! into `configuration.yaml`. The switch reports the device as on while its
+//! address answers pings.
+
+use super::yaml_string;
+use crate::models::Device;
+
+pub fn write_devices(devices: &[Device]) -> String {
+    let mut content =
+        String::from("# Written by jump_rs; paste into configuration.yaml.\nswitch:\n");
+    for device in devices {
+        let several = device.interfaces.len() > 1;
+        for interface in &device.interfaces {
+            let name = if several {
+                let label = interface
+                    .label
+                    .clone()
+                    .unwrap_or_else(|| interface.mac_address.to_string());
+                format!("{} ({label})", device.name)
+            } else {
+                device.name.clone()
+            };
+            content.push_str("  - platform: wake_on_lan\n");
+            content.push_str(&format!("    name: {}\n", yaml_string(&name)));
+            content.push_str(&format!(
+                "    mac: {}\n",
+                yaml_string(&interface.mac_address.to_string())
+            ));
+            if let Some(host) = &device.ip_address {
+                content.push_str(&format!("    host: {}\n", yaml_string(host)));
+            }
+            if let Some(broadcast) = interface.broadcast {
+                content.push_str(&format!(
+                    "    broadcast_address: {}\n",
+                    yaml_string(&broadcast.to_string())
+                ));
+            }
+            content.push_str(&format!("    broadcast_port: {}\n", device.port));
+        }
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::NetworkInterface;
+
+    #[test]
+    fn each_interface_becomes_a_switch() {
+        let server = Device::new(
+            "Server \"A\"".to_string(),
+            vec![
+                NetworkInterface::parse("AA:BB:CC:DD:EE:01", None, Some("192.168.1.255")).unwrap(),
+                NetworkInterface::parse("AA:BB:CC:DD:EE:02", Some("IPMI".to_string()), None)
+                    .unwrap(),
+            ],
+            Some("192.168.1.10".to_string()),
+            7,
+            None,
+        );
+
+        let content = write_devices(&[server]);
+
+        assert_eq!(
+            content,
+            "# Written by jump_rs; paste into configuration.yaml.\n\
+             switch:\n  \
+               - platform: wake_on_lan\n    \
+                 name: \"Server \\\"A\\\" (AA:BB:CC:DD:EE:01)\"\n    \
+                 mac: \"AA:BB:CC:DD:EE:01\"\n    \
+                 host: \"192.168.1.10\"\n    \
+                 broadcast_address: \"192.168.1.255\"\n    \
+                 broadcast_port: 7\n  \
+               - platform: wake_on_lan\n    \
+                 name: \"Server \\\"A\\\" (IPMI)\"\n    \
+                 mac: \"AA:BB:CC:DD:EE:02\"\n    \
+                 host: \"192.168.1.10\"\n    \
+                 broadcast_port: 7\n"
+        );
+    }
+}
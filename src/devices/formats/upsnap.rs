@@ -0,0 +1,188 @@
+//! UpSnap's device records as its PocketBase API lists them: the list
+//! response `{"items": [...]}` or a bare array of records. Group names are
+//! only known when the records were fetched with `expand=groups`.
+
+use std::net::Ipv4Addr;
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use super::{Converted, invalid_file, parse_json, text};
+use crate::api::devices::{ImportRequest, InterfaceRequest};
+use crate::models::ValidationError;
+
+const FORMAT: &str = "an UpSnap export";
+
+/// PocketBase bookkeeping rather than device data, left out without a warning.
+const RECORD_FIELDS: [&str; 7] = [
+    "id",
+    "collectionId",
+    "collectionName",
+    "created",
+    "updated",
+    "created_by",
+    "status",
+];
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Export {
+    List { items: Vec<Value> },
+    Records(Vec<Value>),
+}
+
+#[derive(Deserialize)]
+struct Record {
+    name: String,
+    mac: String,
+    ip: Option<String>,
+    netmask: Option<String>,
+    description: Option<String>,
+    link: Option<String>,
+    #[serde(default)]
+    expand: Expand,
+    #[serde(flatten)]
+    other: Map<String, Value>,
+}
+
+#[derive(Default, Deserialize)]
+struct Expand {
+    #[serde(default)]
+    groups: Vec<Group>,
+}
+
+#[derive(Deserialize)]
+struct Group {
+    name: String,
+}
+
+pub fn read_devices(content: &str) -> Result<Converted, ValidationError> {
+    let records = match parse_json(content, FORMAT)? {
+        Export::List { items } | Export::Records(items) => items,
+    };
+    let mut converted = Converted::default();
+    for (index, record) in records.into_iter().enumerate() {
+        let entry = format!("[{index}]");
+        let mut record: Record = serde_json::from_value(record)
+            .map_err(|err| invalid_file(FORMAT, format!("{entry}: {err}")))?;
+        for field in RECORD_FIELDS {
+            record.other.remove(field);
+        }
+        let request = convert(&entry, record, &mut converted);
+        converted.entries.push((entry, request));
+    }
+    Ok(converted)
+}
+
+fn convert(entry: &str, mut record: Record, converted: &mut Converted) -> ImportRequest {
+    let ip_address = text(record.ip);
+    let mut broadcast = None;
+    if let Some(netmask) = text(record.netmask) {
+        broadcast = ip_address
+            .as_deref()
+            .and_then(|ip| broadcast_address(ip, &netmask));
+        if broadcast.is_none() {
+            converted.dropped(
+                entry,
+                "netmask",
+                "Not imported; the broadcast address needs an IPv4 address and netmask",
+            );
+        }
+    }
+
+    // Groups are linked by record id; only expanded groups carry a name.
+    let tags: Vec<String> = record
+        .expand
+        .groups
+        .into_iter()
+        .map(|group| group.name)
+        .collect();
+    if !tags.is_empty() {
+        record.other.remove("groups");
+    }
+    converted.drop_unmapped(entry, &record.other);
+
+    let mac = record.mac.trim().to_string();
+    let (mac_address, interfaces) = match broadcast {
+        Some(broadcast) => (
+            None,
+            Some(vec![InterfaceRequest {
+                mac_address: mac,
+                label: None,
+                broadcast: Some(broadcast.to_string()),
+            }]),
+        ),
+        None => (Some(mac), None),
+    };
+    ImportRequest {
+        name: record.name.trim().to_string(),
+        mac_address,
+        interfaces,
+        ip_address,
+        description: text(record.description),
+        tags,
+        metadata: text(record.link)
+            .map(|link| ("link".to_string(), link))
+            .into_iter()
+            .collect(),
+        ..ImportRequest::default()
+    }
+}
+
+/// The broadcast address of the network `ip` belongs to, with `netmask`
+/// given as an address (`255.255.255.0`) or a prefix length (`24`).
+fn broadcast_address(ip: &str, netmask: &str) -> Option<Ipv4Addr> {
+    let ip: Ipv4Addr = ip.parse().ok()?;
+    let mask = match netmask.parse::<u8>() {
+        Ok(prefix) if prefix <= 32 => u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0),
+        Ok(_) => return None,
+        Err(_) => u32::from(netmask.parse::<Ipv4Addr>().ok()?),
+    };
+    Some(Ipv4Addr::from(u32::from(ip) | !mask))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_keep_what_has_a_counterpart() {
+        let content = r#"{"page":1,"items":[{
+            "id":"r1","collectionName":"devices","status":"online",
+            "name":"NAS","mac":"aa:bb:cc:dd:ee:01","ip":"192.168.1.20",
+            "netmask":"255.255.255.0","description":"","link":"http://nas.lan",
+            "groups":["g1"],"expand":{"groups":[{"id":"g1","name":"storage"}]},
+            "wake_cron":"0 7 * * *","wake_cron_enabled":true,"shutdown_cmd":"",
+            "ports":[]
+        }]}"#;
+
+        let converted = read_devices(content).unwrap();
+
+        let (entry, request) = &converted.entries[0];
+        assert_eq!(entry, "[0]");
+        assert_eq!(request.name, "NAS");
+        assert_eq!(request.ip_address.as_deref(), Some("192.168.1.20"));
+        let interface = &request.interfaces.as_ref().unwrap()[0];
+        assert_eq!(interface.broadcast.as_deref(), Some("192.168.1.255"));
+        assert_eq!(request.tags, ["storage"]);
+        assert_eq!(request.metadata["link"], "http://nas.lan");
+        let warned: Vec<&str> = converted
+            .warnings
+            .iter()
+            .map(|warning| warning.field.as_str())
+            .collect();
+        assert_eq!(warned, ["[0].wake_cron", "[0].wake_cron_enabled"]);
+    }
+
+    #[test]
+    fn netmask_without_ipv4_address_is_dropped() {
+        assert_eq!(
+            broadcast_address("10.1.2.3", "16"),
+            Some(Ipv4Addr::new(10, 1, 255, 255))
+        );
+        let converted =
+            read_devices(r#"[{"name":"PC","mac":"aa:bb:cc:dd:ee:01","netmask":"24"}]"#).unwrap();
+        assert_eq!(converted.warnings[0].field, "[0].netmask");
+        assert!(converted.entries[0].1.mac_address.is_some());
+    }
+}
@@ -0,0 +1,79 @@
+//! wolweb's `devices.json`: `{"devices": [{"name", "mac", "ip"}]}`, where
+//! `ip` is the broadcast address the packet is sent to, optionally followed
+//! by the port, e.g. `192.168.1.255:9`.
+
+use std::net::SocketAddr;
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use super::{Converted, parse_json, text};
+use crate::api::devices::{ImportRequest, InterfaceRequest};
+use crate::models::ValidationError;
+
+#[derive(Deserialize)]
+struct File {
+    devices: Vec<Device>,
+}
+
+#[derive(Deserialize)]
+struct Device {
+    name: String,
+    mac: String,
+    ip: Option<String>,
+    #[serde(flatten)]
+    other: Map<String, Value>,
+}
+
+pub fn read_devices(content: &str) -> Result<Converted, ValidationError> {
+    let file: File = parse_json(content, "a wolweb devices.json")?;
+    let mut converted = Converted::default();
+    for (index, device) in file.devices.into_iter().enumerate() {
+        let entry = format!("[{index}]");
+        converted.drop_unmapped(&entry, &device.other);
+
+        let (broadcast, port) = match text(device.ip) {
+            Some(ip) => match ip.parse::<SocketAddr>() {
+                Ok(address) => (Some(address.ip().to_string()), Some(address.port())),
+                Err(_) => (Some(ip), None),
+            },
+            None => (None, None),
+        };
+        let request = ImportRequest {
+            name: device.name.trim().to_string(),
+            interfaces: Some(vec![InterfaceRequest {
+                mac_address: device.mac.trim().to_string(),
+                label: None,
+                broadcast,
+            }]),
+            port,
+            ..ImportRequest::default()
+        };
+        converted.entries.push((entry, request));
+    }
+    Ok(converted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ip_splits_into_broadcast_and_port() {
+        let content = r#"{"devices":[
+            {"name":"Desktop","mac":"AA:BB:CC:DD:EE:01","ip":"192.168.1.255:7"},
+            {"name":"Laptop","mac":"AA:BB:CC:DD:EE:02","ip":"10.0.0.255","icon":"laptop"}
+        ]}"#;
+
+        let converted = read_devices(content).unwrap();
+
+        let desktop = &converted.entries[0].1;
+        let interface = &desktop.interfaces.as_ref().unwrap()[0];
+        assert_eq!(interface.broadcast.as_deref(), Some("192.168.1.255"));
+        assert_eq!(desktop.port, Some(7));
+        let laptop = &converted.entries[1].1;
+        assert_eq!(laptop.port, None);
+        assert_eq!(converted.warnings.len(), 1);
+        assert_eq!(converted.warnings[0].field, "[1].icon");
+    }
+}
@@ -0,0 +1,529 @@
+//! Turning import entries into storage mutations according to the import
+//! mode, so a dry run can report exactly what a real import would change.
+
+use std::collections::{HashMap, HashSet};
+
+use time::OffsetDateTime;
+
+use super::formats::{self, Converted, ethers, invalid_file};
+use super::{csv, ensure_unique_macs, new_host, next_sort_order, parse_interfaces};
+use crate::api::ApiResult;
+use crate::api::devices::{
+    IdConflict, ImportChange, ImportFormat, ImportMode, ImportQuery, ImportReport, ImportRequest,
+    InterfaceRequest,
+};
+use crate::config;
+use crate::models::{Device, FieldErrors, MacAddress, unresolvable, unresolvable_hosts};
+use crate::storage::{DeviceIndex, Mutation, SharedStorage, parse_device_file};
+
+/// The outcome of an import: what it changes and the resulting devices,
+/// created ones first. Nothing is stored for a dry run.
+#[derive(Debug)]
+pub struct Imported {
+    pub report: ImportReport,
+    pub devices: Vec<Device>,
+}
+
+/// Where an imported device comes from: its label for error messages, e.g.
+/// `[0]` or `line 2`, and the identity a backup carries over.
+struct Entry {
+    label: String,
+    preserved: Option<Preserved>,
+}
+
+impl Entry {
+    const fn new(label: String) -> Self {
+        Self {
+            label,
+            preserved: None,
+        }
+    }
+
+    /// Where the entry's field errors are reported, e.g. `line 2.port`.
+    fn prefix(&self) -> String {
+        format!("{}.", self.label)
+    }
+}
+
+/// The fields of a backed-up device that an import normally assigns afresh.
+struct Preserved {
+    id: String,
+    revision: u64,
+    created_at: OffsetDateTime,
+    updated_at: Option<OffsetDateTime>,
+}
+
+/// Imports `content` in `query.format`, or the format its content has.
+/// Devices read from another tool's file come with a warning for each field
+/// that is left out.
+pub async fn import_file(
+    storage: &SharedStorage,
+    content: &str,
+    query: &ImportQuery,
+) -> ApiResult<Imported> {
+    let format = query.format.unwrap_or_else(|| formats::detect(content));
+    let mut imported = match format {
+        ImportFormat::Json => {
+            let req = serde_json::from_str(content)
+                .map_err(|err| invalid_file("a JSON device list", err))?;
+            import_devices(storage, req, query).await
+        }
+        ImportFormat::Csv => import_csv(storage, content, query).await,
+        ImportFormat::Backup => {
+            let document = serde_json::from_str(content)
+                .map_err(|err| invalid_file("a device backup", err))?;
+            import_backup(storage, document, query).await
+        }
+        ImportFormat::Upsnap => {
+            import_converted(storage, formats::upsnap::read_devices(content)?, query).await
+        }
+        ImportFormat::Wolweb => {
+            import_converted(storage, formats::wolweb::read_devices(content)?, query).await
+        }
+        ImportFormat::Android => {
+            import_converted(storage, formats::android::read_devices(content)?, query).await
+        }
+        ImportFormat::Ethers => {
+            import_converted(storage, ethers::read_devices(content).await, query).await
+        }
+    }?;
+    imported.report.format = Some(format);
+    Ok(imported)
+}
+
+async fn import_converted(
+    storage: &SharedStorage,
+    converted: Converted,
+    query: &ImportQuery,
+) -> ApiResult<Imported> {
+    let entries = converted
+        .entries
+        .into_iter()
+        .map(|(label, request)| (Entry::new(label), request))
+        .collect();
+    let mut imported = import_entries(storage, entries, FieldErrors::default(), query).await?;
+    imported.report.warnings = converted.warnings;
+    Ok(imported)
+}
+
+async fn import_devices(
+    storage: &SharedStorage,
+    req: Vec<ImportRequest>,
+    query: &ImportQuery,
+) -> ApiResult<Imported> {
+    let entries = req
+        .into_iter()
+        .enumerate()
+        .map(|(index, device)| (Entry::new(format!("[{index}]")), device))
+        .collect();
+    import_entries(storage, entries, FieldErrors::default(), query).await
+}
+
+/// Imports CSV rows, reporting every invalid row by its line number.
+async fn import_csv(
+    storage: &SharedStorage,
+    content: &str,
+    query: &ImportQuery,
+) -> ApiResult<Imported> {
+    let mut errors = FieldErrors::default();
+    let entries = csv::read_devices(content, &mut errors)
+        .into_iter()
+        .map(|row| (Entry::new(csv::line_field(row.line)), row.request))
+        .collect();
+    import_entries(storage, entries, errors, query).await
+}
+
+/// Imports a backup export, keeping each device's id and timestamps. An id
+/// that is already taken is handled as `query.id_conflict` says.
+async fn import_backup(
+    storage: &SharedStorage,
+    document: serde_json::Value,
+    query: &ImportQuery,
+) -> ApiResult<Imported> {
+    let devices =
+        parse_device_file(document).map_err(|err| invalid_file("a device backup", err))?;
+    let entries = devices
+        .into_iter()
+        .enumerate()
+        .map(|(index, device)| {
+            let entry = Entry {
+                label: format!("[{index}]"),
+                preserved: Some(Preserved {
+                    id: device.id,
+                    revision: device.revision,
+                    created_at: device.created_at,
+                    updated_at: device.updated_at,
+                }),
+            };
+            let request = ImportRequest {
+                name: device.name,
+                mac_address: None,
+                interfaces: Some(
+                    device
+                        .interfaces
+                        .into_iter()
+                        .map(|interface| InterfaceRequest {
+                            mac_address: interface.mac_address.to_string(),
+                            label: interface.label,
+                            broadcast: interface.broadcast.map(|ip| ip.to_string()),
+                        })
+                        .collect(),
+                ),
+                port: Some(device.port),
+                ip_address: device.ip_address,
+                description: device.description,
+                tags: device.tags,
+                metadata: device.metadata,
+                favorite: device.favorite,
+            };
+            (entry, request)
+        })
+        .collect();
+    import_entries(storage, entries, FieldErrors::default(), query).await
+}
+
+/// Validates `entries`, reporting each one's errors under its label on top
+/// of the `errors` found while reading them. Invalid entries fail even a dry
+/// run; conflicts are reported by a dry run and fail a real one, so an import
+/// is applied completely or not at all.
+async fn import_entries(
+    storage: &SharedStorage,
+    entries: Vec<(Entry, ImportRequest)>,
+    mut errors: FieldErrors,
+    query: &ImportQuery,
+) -> ApiResult<Imported> {
+    let existing = storage.snapshot();
+    let mut plan = Plan::new(&existing, query);
+    for (entry, req) in entries {
+        plan.add(&entry, req, &mut errors);
+    }
+    let hosts = unresolvable_hosts(plan.lookups.iter().map(|(_, host)| host.clone())).await;
+    for (prefix, host) in &plan.lookups {
+        if hosts.contains(host) {
+            let error = unresolvable(host);
+            errors.add(format!("{prefix}{}", error.field), error.message);
+        }
+    }
+    errors.into_result()?;
+
+    let (planned, mutations) = plan.finish();
+    if !query.dry_run {
+        let mut conflicts = FieldErrors::default();
+        for conflict in &planned.conflicts {
+            conflicts.add(
+                conflict.field.clone(),
+                conflict.change.reason.clone().unwrap_or_default(),
+            );
+        }
+        conflicts.into_result()?;
+        storage.apply(mutations).await?;
+    }
+    Ok(Imported {
+        report: planned.report,
+        devices: planned.created.into_iter().chain(planned.updated).collect(),
+    })
+}
+
+/// An entry that cannot be imported, and the field to blame in a real import.
+struct Conflict {
+    field: String,
+    change: ImportChange,
+}
+
+struct Planned {
+    report: ImportReport,
+    conflicts: Vec<Conflict>,
+    created: Vec<Device>,
+    updated: Vec<Device>,
+}
+
+struct Plan<'a> {
+    existing: &'a DeviceIndex,
+    mode: ImportMode,
+    id_conflict: IdConflict,
+    report: ImportReport,
+    conflicts: Vec<Conflict>,
+    mutations: Vec<Mutation>,
+    created: Vec<Device>,
+    updated: Vec<Device>,
+    /// MACs claimed by earlier entries, with the name of the entry's device.
+    pending_macs: HashMap<MacAddress, String>,
+    /// Ids kept from a backup by earlier entries.
+    pending_ids: HashSet<String>,
+    /// Stored devices earlier entries update, with the entry's label.
+    claimed: HashMap<String, String>,
+    /// Hostnames entries bring in, each with its entry's error prefix. They
+    /// are resolved together once every entry is planned.
+    lookups: Vec<(String, String)>,
+    sort_order: u32,
+}
+
+impl<'a> Plan<'a> {
+    fn new(existing: &'a DeviceIndex, query: &ImportQuery) -> Self {
+        let mut report = ImportReport {
+            dry_run: query.dry_run,
+            ..ImportReport::default()
+        };
+        if query.mode == ImportMode::Replace {
+            report.removed = existing
+                .all()
+                .iter()
+                .map(|device| ImportChange::stored(None, device, None))
+                .collect();
+        }
+        Self {
+            existing,
+            mode: query.mode,
+            id_conflict: query.id_conflict,
+            report,
+            conflicts: Vec::new(),
+            mutations: Vec::new(),
+            created: Vec::new(),
+            updated: Vec::new(),
+            pending_macs: HashMap::new(),
+            pending_ids: HashSet::new(),
+            claimed: HashMap::new(),
+            lookups: Vec::new(),
+            sort_order: next_sort_order(existing),
+        }
+    }
+
+    /// The report and the mutations that carry it out. Replaced devices go
+    /// to the trash, so a mistaken import can be undone, except those whose
+    /// id a restored backup takes over.
+    fn finish(self) -> (Planned, Vec<Mutation>) {
+        let now = OffsetDateTime::now_utc();
+        let mut mutations: Vec<Mutation> = Vec::new();
+        if self.mode == ImportMode::Replace {
+            for device in self.existing.all().iter() {
+                if self.pending_ids.contains(&device.id) {
+                    mutations.push(Mutation::Remove(device.id.clone()));
+                } else {
+                    mutations.push(Mutation::Update {
+                        id: device.id.clone(),
+                        device: Device {
+                            deleted_at: Some(now),
+                            ..device.clone()
+                        },
+                    });
+                }
+            }
+        }
+        mutations.extend(self.mutations);
+        let planned = Planned {
+            report: self.report,
+            conflicts: self.conflicts,
+            created: self.created,
+            updated: self.updated,
+        };
+        (planned, mutations)
+    }
+
+    fn add(&mut self, entry: &Entry, req: ImportRequest, errors: &mut FieldErrors) {
+        let prefix = entry.prefix();
+        let favorite = req.favorite;
+        let Some(interfaces) =
+            errors.merge(&prefix, parse_interfaces(req.mac_address, req.interfaces))
+        else {
+            return;
+        };
+        let mut device = Device::new(
+            req.name,
+            interfaces,
+            req.ip_address,
+            req.port.unwrap_or_else(|| config::get().wol.default_port),
+            req.description,
+        )
+        .with_tags(req.tags)
+        .with_metadata(req.metadata);
+        device.favorite = favorite;
+
+        let earlier = |mac| self.pending_macs.get(&mac).map(String::as_str);
+        if let Err(err) = ensure_unique_macs(&device, earlier) {
+            self.check(entry, &device, None, errors);
+            return self.conflict(entry, "mac_address", &device, err.to_string());
+        }
+        self.pending_macs
+            .extend(device.macs().map(|mac| (mac, device.name.clone())));
+
+        match self.mode {
+            ImportMode::Replace => self.create(entry, device, errors),
+            ImportMode::Append => {
+                let existing = self.existing;
+                let stored = |mac| existing.by_mac(mac).map(|other| other.name.as_str());
+                match ensure_unique_macs(&device, stored) {
+                    Ok(()) => self.create(entry, device, errors),
+                    Err(err) => {
+                        self.check(entry, &device, None, errors);
+                        self.conflict(entry, "mac_address", &device, err.to_string());
+                    }
+                }
+            }
+            ImportMode::Upsert => self.upsert(entry, device, errors),
+        }
+    }
+
+    fn create(&mut self, entry: &Entry, mut device: Device, errors: &mut FieldErrors) {
+        self.check(entry, &device, None, errors);
+        let mut reason = None;
+        if let Some(preserved) = &entry.preserved {
+            match self.take_id(&preserved.id) {
+                Ok(()) => device.id.clone_from(&preserved.id),
+                Err(owner) if self.id_conflict == IdConflict::Reject => {
+                    let reason = format!("Device id {} is already used by {owner}", preserved.id);
+                    return self.conflict(entry, "id", &device, reason);
+                }
+                Err(owner) => {
+                    reason = Some(format!(
+                        "Device id {} is already used by {owner}; imported as {}",
+                        preserved.id, device.id
+                    ));
+                }
+            }
+            device.revision = preserved.revision;
+            device.created_at = preserved.created_at;
+            device.updated_at = preserved.updated_at;
+        }
+        device.sort_order = self.sort_order;
+        self.sort_order = self.sort_order.saturating_add(1);
+        let preserved = entry.preserved.is_some().then(|| device.id.clone());
+        self.report.created.push(ImportChange::new(
+            Some(entry.label.clone()),
+            &device.name,
+            preserved,
+            reason,
+        ));
+        self.mutations.push(Mutation::Add(device.clone()));
+        self.created.push(device);
+    }
+
+    /// Claims a backed-up device's id, failing with the name of its current
+    /// owner. In replace mode the live devices are on their way out, so
+    /// their ids are free to take.
+    fn take_id(&mut self, id: &str) -> Result<(), String> {
+        if self.pending_ids.contains(id) {
+            return Err("another device in this import".to_string());
+        }
+        let replaced = self.mode == ImportMode::Replace;
+        let owner = self
+            .existing
+            .get(id)
+            .filter(|_| !replaced)
+            .or_else(|| self.existing.trashed(id));
+        if let Some(owner) = owner {
+            return Err(owner.name.clone());
+        }
+        self.pending_ids.insert(id.to_string());
+        Ok(())
+    }
+
+    /// Updates the stored device that has the entry's id, for backups, or
+    /// owns its MAC addresses, or creates one when none does. A stored
+    /// device is updated by one entry at most.
+    fn upsert(&mut self, entry: &Entry, device: Device, errors: &mut FieldErrors) {
+        let mut matches: Vec<&Device> = Vec::new();
+        let by_id = entry
+            .preserved
+            .as_ref()
+            .and_then(|preserved| self.existing.get(&preserved.id));
+        let by_mac = device.macs().filter_map(|mac| self.existing.by_mac(mac));
+        for stored in by_id.into_iter().chain(by_mac) {
+            if !matches.iter().any(|other| other.id == stored.id) {
+                matches.push(stored);
+            }
+        }
+
+        let current = match matches.as_slice() {
+            [] => return self.create(entry, device, errors),
+            [current] => (*current).clone(),
+            [first, second, ..] => {
+                self.check(entry, &device, None, errors);
+                let reason = format!("Matches both {} and {}", first.name, second.name);
+                return self.conflict(entry, "mac_address", &device, reason);
+            }
+        };
+        if let Some(earlier) = self.claimed.get(&current.id) {
+            let reason = format!("Matches the same device as {earlier}");
+            self.check(entry, &device, None, errors);
+            return self.conflict(entry, "mac_address", &device, reason);
+        }
+        self.claimed.insert(current.id.clone(), entry.label.clone());
+
+        let updated = Device {
+            name: device.name,
+            interfaces: device.interfaces,
+            ip_address: device.ip_address,
+            port: device.port,
+            description: device.description,
+            tags: device.tags,
+            metadata: device.metadata,
+            favorite: device.favorite,
+            ..current.clone()
+        };
+        self.check(entry, &updated, current.ip_address.as_deref(), errors);
+        let label = Some(entry.label.clone());
+        if updated == current {
+            self.report.skipped.push(ImportChange::stored(
+                label,
+                &current,
+                Some("Already up to date".to_string()),
+            ));
+            return;
+        }
+        self.report
+            .updated
+            .push(ImportChange::stored(label, &current, None));
+        self.mutations.push(Mutation::Update {
+            id: current.id.clone(),
+            device: updated.clone(),
+        });
+        self.updated.push(updated);
+    }
+
+    /// Validates `device` like [`super::check_device`], queueing the lookup
+    /// of a new hostname instead of waiting for it.
+    fn check(
+        &mut self,
+        entry: &Entry,
+        device: &Device,
+        previous_ip: Option<&str>,
+        errors: &mut FieldErrors,
+    ) {
+        let prefix = entry.prefix();
+        errors.merge(&prefix, device.validate());
+        if !errors.contains(&format!("{prefix}ip_address"))
+            && let Some(host) = new_host(device, previous_ip)
+        {
+            self.lookups.push((prefix, host.to_string()));
+        }
+    }
+
+    fn conflict(&mut self, entry: &Entry, field: &str, device: &Device, reason: String) {
+        let change = ImportChange::new(Some(entry.label.clone()), &device.name, None, Some(reason));
+        self.report.conflicts.push(change.clone());
+        self.conflicts.push(Conflict {
+            field: format!("{}{field}", entry.prefix()),
+            change,
+        });
+    }
+}
+
+impl ImportChange {
+    fn new(
+        entry: Option<String>,
+        name: &str,
+        device_id: Option<String>,
+        reason: Option<String>,
+    ) -> Self {
+        Self {
+            entry,
+            name: name.to_string(),
+            device_id,
+            reason,
+        }
+    }
+
+    fn stored(entry: Option<String>, device: &Device, reason: Option<String>) -> Self {
+        Self::new(entry, &device.name, Some(device.id.clone()), reason)
+    }
+}
@@ -0,0 +1,333 @@
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::process::Stdio;
+use std::sync::LazyLock;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tracing::{debug, info, instrument, warn};
+
+use crate::models::MacAddress;
+
+const RESTART_DELAY: Duration = Duration::from_secs(5);
+
+static NEIGHBORS: LazyLock<NeighborCache> = LazyLock::new(NeighborCache::default);
+
+/// Returns the process-wide neighbor cache. It stays empty unless the watcher
+/// is running.
+pub fn cache() -> &'static NeighborCache {
+    &NEIGHBORS
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Neighbor {
+    pub ip: Option<IpAddr>,
+    pub last_seen: Option<OffsetDateTime>,
+}
+
+/// MAC → IP → last-seen map fed by kernel neighbor table events.
+#[derive(Debug, Default)]
+pub struct NeighborCache {
+    table: RwLock<NeighborTable>,
+}
+
+#[derive(Debug, Default)]
+struct NeighborTable {
+    by_mac: HashMap<MacAddress, Neighbor>,
+    by_ip: HashMap<IpAddr, MacAddress>,
+}
+
+impl NeighborCache {
+    fn apply(&self, event: NeighborEvent, now: OffsetDateTime) {
+        let mut table = self.table.write();
+        match event {
+            NeighborEvent::Seen { ip, mac, reachable } => {
+                if let Some(previous) = table.by_ip.insert(ip, mac)
+                    && previous != mac
+                    && let Some(entry) = table.by_mac.get_mut(&previous)
+                    && entry.ip == Some(ip)
+                {
+                    entry.ip = None;
+                }
+
+                let entry = table.by_mac.entry(mac).or_insert(Neighbor {
+                    ip: None,
+                    last_seen: None,
+                });
+                // Dual-stack hosts report several addresses; keep showing IPv4.
+                if !entry
+                    .ip
+                    .is_some_and(|current| current.is_ipv4() && ip.is_ipv6())
+                {
+                    entry.ip = Some(ip);
+                }
+                if reachable {
+                    entry.last_seen = Some(now);
+                }
+            }
+            NeighborEvent::Gone { ip } => {
+                if let Some(mac) = table.by_ip.remove(&ip)
+                    && let Some(entry) = table.by_mac.get_mut(&mac)
+                    && entry.ip == Some(ip)
+                {
+                    entry.ip = None;
+                }
+            }
+        }
+    }
+
+    pub fn mac_for(&self, ip: IpAddr) -> Option<MacAddress> {
+        self.table.read().by_ip.get(&ip).copied()
+    }
+
+    pub fn get(&self, mac: MacAddress) -> Option<Neighbor> {
+        self.table.read().by_mac.get(&mac).cloned()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NeighborEvent {
+    Seen {
+        ip: IpAddr,
+        mac: MacAddress,
+        reachable: bool,
+    },
+    Gone {
+        ip: IpAddr,
+    },
+}
+
+/// Starts a background task that seeds the cache from `ip neigh show` and then
+/// follows `ip monitor neigh`. No packets are sent; the cache only reflects
+/// traffic the kernel already observed.
+pub fn spawn_watcher() {
+    tokio::spawn(async {
+        loop {
+            match watch().await {
+                Ok(()) => debug!("Neighbor monitor exited, restarting"),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    warn!("`ip` command not available, neighbor watcher disabled");
+                    return;
+                }
+                Err(err) => warn!(error = %err, "Neighbor monitor failed, restarting"),
+            }
+            tokio::time::sleep(RESTART_DELAY).await;
+        }
+    });
+}
+
+#[instrument(skip_all)]
+async fn watch() -> std::io::Result<()> {
+    let output = Command::new("ip").args(["neigh", "show"]).output().await?;
+    let seeded = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(parse_neighbor_event)
+        .map(|event| cache().apply(event, OffsetDateTime::now_utc()))
+        .count();
+    info!(entries = seeded, "Neighbor cache seeded");
+
+    let mut child = Command::new("ip")
+        .args(["monitor", "neigh"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+    let Some(stdout) = child.stdout.take() else {
+        return Ok(());
+    };
+
+    let mut lines = BufReader::new(stdout).lines();
+    while let Some(line) = lines.next_line().await? {
+        if let Some(event) = parse_neighbor_event(&line) {
+            debug!(?event, "Neighbor event");
+            cache().apply(event, OffsetDateTime::now_utc());
+        }
+    }
+
+    child.wait().await?;
+    Ok(())
+}
+
+/// Parses one line of `ip neigh show` or `ip monitor neigh` output.
+fn parse_neighbor_event(line: &str) -> Option<NeighborEvent> {
+    let mut fields = line.split_whitespace().peekable();
+    let deleted = match fields.peek() {
+        Some(&"Deleted") => {
+            fields.next();
+            true
+        }
+        Some(&"miss") => {
+            fields.next();
+            false
+        }
+        _ => false,
+    };
+
+    let ip: IpAddr = fields.next()?.parse().ok()?;
+    let rest: Vec<&str> = fields.collect();
+    if deleted
+        || rest
+            .iter()
+            .any(|field| matches!(*field, "FAILED" | "INCOMPLETE"))
+    {
+        return Some(NeighborEvent::Gone { ip });
+    }
+
+    let mac = rest
+        .iter()
+        .position(|field| *field == "lladdr")
+        .and_then(|index| rest.get(index + 1))
+        .and_then(|mac| mac.parse().ok())?;
+    let reachable = rest.contains(&"REACHABLE");
+
+    Some(NeighborEvent::Seen { ip, mac, reachable })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+
+    fn mac(value: &str) -> MacAddress {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_reachable_neighbor() {
+        let event =
+            parse_neighbor_event("192.168.0.20 dev eth0 lladdr 10:ff:e0:6b:65:3b REACHABLE");
+        assert_eq!(
+            event,
+            Some(NeighborEvent::Seen {
+                ip: "192.168.0.20".parse().unwrap(),
+                mac: mac("10:FF:E0:6B:65:3B"),
+                reachable: true,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_stale_ipv6_router() {
+        let event = parse_neighbor_event("fe80::1 dev eth0 lladdr 2:10:18:50:19:8c router STALE");
+        assert_eq!(
+            event,
+            Some(NeighborEvent::Seen {
+                ip: "fe80::1".parse().unwrap(),
+                mac: mac("02:10:18:50:19:8C"),
+                reachable: false,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_deleted_and_failed_entries() {
+        let ip: IpAddr = "192.168.0.20".parse().unwrap();
+        assert_eq!(
+            parse_neighbor_event("Deleted 192.168.0.20 dev eth0 lladdr 10:ff:e0:6b:65:3b STALE"),
+            Some(NeighborEvent::Gone { ip })
+        );
+        assert_eq!(
+            parse_neighbor_event("192.168.0.20 dev eth0  FAILED"),
+            Some(NeighborEvent::Gone { ip })
+        );
+    }
+
+    #[test]
+    fn ignores_unrelated_lines() {
+        assert_eq!(parse_neighbor_event(""), None);
+        assert_eq!(parse_neighbor_event("[NEIGH]"), None);
+    }
+
+    #[test]
+    fn cache_tracks_mac_ip_and_last_seen() {
+        let cache = NeighborCache::default();
+        let ip: IpAddr = "192.168.0.20".parse().unwrap();
+        let seen_at = now();
+        cache.apply(
+            NeighborEvent::Seen {
+                ip,
+                mac: mac("10:FF:E0:6B:65:3B"),
+                reachable: true,
+            },
+            seen_at,
+        );
+
+        assert_eq!(cache.mac_for(ip), Some(mac("10:FF:E0:6B:65:3B")));
+        let neighbor = cache.get(mac("10-ff-e0-6b-65-3b")).unwrap();
+        assert_eq!(neighbor.ip, Some(ip));
+        assert_eq!(neighbor.last_seen, Some(seen_at));
+    }
+
+    #[test]
+    fn stale_events_keep_previous_last_seen() {
+        let cache = NeighborCache::default();
+        let ip: IpAddr = "192.168.0.20".parse().unwrap();
+        let seen_at = now();
+        let mac = mac("10:FF:E0:6B:65:3B");
+        cache.apply(
+            NeighborEvent::Seen {
+                ip,
+                mac,
+                reachable: true,
+            },
+            seen_at,
+        );
+        cache.apply(
+            NeighborEvent::Seen {
+                ip,
+                mac,
+                reachable: false,
+            },
+            seen_at + time::Duration::minutes(5),
+        );
+
+        assert_eq!(cache.get(mac).unwrap().last_seen, Some(seen_at));
+    }
+
+    #[test]
+    fn gone_events_drop_ip_but_keep_last_seen() {
+        let cache = NeighborCache::default();
+        let ip: IpAddr = "192.168.0.20".parse().unwrap();
+        let mac = mac("10:FF:E0:6B:65:3B");
+        cache.apply(
+            NeighborEvent::Seen {
+                ip,
+                mac,
+                reachable: true,
+            },
+            now(),
+        );
+        cache.apply(NeighborEvent::Gone { ip }, now());
+
+        assert_eq!(cache.mac_for(ip), None);
+        let neighbor = cache.get(mac).unwrap();
+        assert_eq!(neighbor.ip, None);
+        assert!(neighbor.last_seen.is_some());
+    }
+
+    #[test]
+    fn dual_stack_neighbors_prefer_ipv4() {
+        let cache = NeighborCache::default();
+        let ipv4: IpAddr = "192.168.0.20".parse().unwrap();
+        let ipv6: IpAddr = "fe80::20".parse().unwrap();
+        let mac = mac("10:FF:E0:6B:65:3B");
+        for ip in [ipv4, ipv6] {
+            cache.apply(
+                NeighborEvent::Seen {
+                    ip,
+                    mac,
+                    reachable: true,
+                },
+                now(),
+            );
+        }
+
+        assert_eq!(cache.get(mac).unwrap().ip, Some(ipv4));
+        assert_eq!(cache.mac_for(ipv6), Some(mac));
+    }
+}
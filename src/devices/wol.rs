@@ -1,26 +1,25 @@
-use crate::models::Device;
+use crate::models::NetworkInterface;
 use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
 use thiserror::Error;
 use tracing::{debug, instrument};
 
 #[derive(Debug, Error)]
 pub enum WolError {
-    #[error("Invalid MAC address: {0}")]
-    InvalidMac(String),
-
     #[error("Network error: {0}")]
     Network(#[source] std::io::Error),
-}
 
-#[instrument(skip_all)]
-pub fn send_wol_packet(device: &Device) -> Result<(), WolError> {
-    let mac = parse_mac_address(&device.mac_address).map_err(WolError::InvalidMac)?;
+    #[error("Device has no interface with MAC address {0}")]
+    UnknownInterface(String),
+}
 
-    let magic_packet = create_magic_packet(mac);
+#[instrument(skip_all, fields(mac = %interface.mac_address))]
+pub fn send_wol_packet(interface: &NetworkInterface, port: u16) -> Result<(), WolError> {
+    let magic_packet = create_magic_packet(interface.mac_address.octets());
 
     let socket = UdpSocket::bind("0.0.0.0:0").map_err(WolError::Network)?;
 
-    let target_addr = SocketAddr::new(Ipv4Addr::BROADCAST.into(), device.port);
+    let broadcast = interface.broadcast.unwrap_or(Ipv4Addr::BROADCAST);
+    let target_addr = SocketAddr::new(broadcast.into(), port);
 
     socket.set_broadcast(true).map_err(WolError::Network)?;
 
@@ -32,25 +31,6 @@ pub fn send_wol_packet(device: &Device) -> Result<(), WolError> {
     Ok(())
 }
 
-fn parse_mac_address(mac_str: &str) -> Result<[u8; 6], String> {
-    let cleaned: String = mac_str.replace([':', '-', '.', ' '], "").to_lowercase();
-
-    if cleaned.len() != 12 {
-        return Err("MAC address must be 12 hex digits".to_string());
-    }
-
-    let mut mac = [0u8; 6];
-    for (i, chunk) in cleaned.as_bytes().chunks(2).enumerate() {
-        if i >= 6 {
-            return Err("MAC address too long".to_string());
-        }
-        mac[i] = u8::from_str_radix(std::str::from_utf8(chunk).unwrap(), 16)
-            .map_err(|_| "Invalid hex digit".to_string())?;
-    }
-
-    Ok(mac)
-}
-
 fn create_magic_packet(mac: [u8; 6]) -> Vec<u8> {
     let mut packet = Vec::with_capacity(102);
 
@@ -67,88 +47,6 @@ fn create_magic_packet(mac: [u8; 6]) -> Vec<u8> {
 mod tests {
     use super::*;
 
-    #[test]
-    fn parse_mac_with_colons() {
-        let result = parse_mac_address("AA:BB:CC:DD:EE:FF");
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), [0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]);
-    }
-
-    #[test]
-    fn parse_mac_with_dashes() {
-        let result = parse_mac_address("AA-BB-CC-DD-EE-FF");
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), [0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]);
-    }
-
-    #[test]
-    fn parse_mac_with_dots() {
-        let result = parse_mac_address("AABB.CCDD.EEFF");
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), [0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]);
-    }
-
-    #[test]
-    fn parse_mac_without_separators() {
-        let result = parse_mac_address("AABBCCDDEEFF");
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), [0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]);
-    }
-
-    #[test]
-    fn parse_mac_lowercase() {
-        let result = parse_mac_address("aa:bb:cc:dd:ee:ff");
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), [0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]);
-    }
-
-    #[test]
-    fn parse_mac_mixed_case() {
-        let result = parse_mac_address("Aa:Bb:Cc:Dd:Ee:Ff");
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), [0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]);
-    }
-
-    #[test]
-    fn parse_mac_all_zeros() {
-        let result = parse_mac_address("00:00:00:00:00:00");
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), [0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
-    }
-
-    #[test]
-    fn parse_mac_all_ones() {
-        let result = parse_mac_address("FF:FF:FF:FF:FF:FF");
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
-    }
-
-    #[test]
-    fn parse_mac_too_short_fails() {
-        let result = parse_mac_address("AA:BB:CC:DD:EE");
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("12 hex digits"));
-    }
-
-    #[test]
-    fn parse_mac_too_long_fails() {
-        let result = parse_mac_address("AA:BB:CC:DD:EE:FF:00");
-        assert!(result.is_err());
-    }
-
-    #[test]
-    fn parse_mac_empty_fails() {
-        let result = parse_mac_address("");
-        assert!(result.is_err());
-    }
-
-    #[test]
-    fn parse_mac_invalid_hex_fails() {
-        let result = parse_mac_address("GG:HH:II:JJ:KK:LL");
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Invalid hex"));
-    }
-
     #[test]
     fn magic_packet_has_correct_size() {
         let mac = [0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF];
@@ -4,8 +4,9 @@ use thiserror::Error;
 use tracing::{error, warn};
 use utoipa::ToSchema;
 
+use crate::backup::BackupError;
 use crate::devices::{ArpError, WolError};
-use crate::models::ValidationError;
+use crate::models::{FieldError, ValidationError};
 use crate::storage::StorageError;
 
 /// Standard error response returned by all API endpoints
@@ -17,6 +18,9 @@ pub struct ErrorResponse {
     /// Human-readable error message
     #[schema(example = "Invalid MAC address format: xyz")]
     pub message: String,
+    /// Every invalid field, for validation errors
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
 }
 
 #[derive(Debug, Error)]
@@ -32,30 +36,57 @@ pub enum ApiError {
 
     #[error(transparent)]
     Arp(#[from] ArpError),
+
+    #[error(transparent)]
+    Backup(#[from] BackupError),
 }
 
 impl ApiError {
     pub const fn status_code(&self) -> StatusCode {
         match self {
-            Self::Validation(_) => StatusCode::BAD_REQUEST,
+            Self::Validation(e) => match e {
+                ValidationError::InvalidMac(_)
+                | ValidationError::InvalidBroadcast(_)
+                | ValidationError::NoInterfaces
+                | ValidationError::AmbiguousInterfaces
+                | ValidationError::Fields(_) => StatusCode::BAD_REQUEST,
+                ValidationError::DuplicateMac { .. } => StatusCode::CONFLICT,
+            },
 
             Self::Storage(e) => match e {
                 StorageError::NotFound(_) => StatusCode::NOT_FOUND,
-                StorageError::Io(_) | StorageError::Parse(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                StorageError::Stale { .. } => StatusCode::PRECONDITION_FAILED,
+                StorageError::DuplicateId(_) | StorageError::DuplicateMac { .. } => {
+                    StatusCode::CONFLICT
+                }
+                StorageError::Io(_)
+                | StorageError::Parse(_)
+                | StorageError::Corrupt { .. }
+                | StorageError::Sqlite(_)
+                | StorageError::UnsupportedVersion { .. }
+                | StorageError::Invalid(_) => StatusCode::INTERNAL_SERVER_ERROR,
             },
 
             Self::Wol(e) => match e {
-                WolError::InvalidMac(_) => StatusCode::BAD_REQUEST,
                 WolError::Network(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                WolError::UnknownInterface(_) => StatusCode::NOT_FOUND,
             },
 
             Self::Arp(e) => match e {
-                ArpError::InvalidIp(_) => StatusCode::BAD_REQUEST,
+                ArpError::InvalidIp(_) | ArpError::InvalidScope(_) => StatusCode::BAD_REQUEST,
                 ArpError::NotDirectlyConnected { .. } | ArpError::NotFound(_) => {
                     StatusCode::NOT_FOUND
                 }
                 ArpError::Query(_) => StatusCode::INTERNAL_SERVER_ERROR,
             },
+
+            Self::Backup(e) => match e {
+                BackupError::NotFound(_) => StatusCode::NOT_FOUND,
+                BackupError::Store(_) => StatusCode::BAD_GATEWAY,
+                BackupError::Invalid { .. } | BackupError::Config(_) | BackupError::Restore(_) => {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                }
+            },
         }
     }
 
@@ -80,6 +111,32 @@ impl ApiError {
                         "Request failed"
                     );
                 }
+                StorageError::Stale { id, current } => {
+                    warn!(
+                        error_type = "storage_stale",
+                        status_code = status_code,
+                        device_id = %id,
+                        current_revision = current,
+                        "Request failed"
+                    );
+                }
+                StorageError::DuplicateId(id) => {
+                    warn!(
+                        error_type = "storage_duplicate_id",
+                        status_code = status_code,
+                        device_id = %id,
+                        "Request failed"
+                    );
+                }
+                StorageError::DuplicateMac { mac, device } => {
+                    warn!(
+                        error_type = "storage_duplicate_mac",
+                        status_code = status_code,
+                        mac = %mac,
+                        device = %device,
+                        "Request failed"
+                    );
+                }
                 StorageError::Io(err) => {
                     error!(
                         error_type = "storage_io",
@@ -96,16 +153,42 @@ impl ApiError {
                         "Request failed"
                     );
                 }
-            },
-            Self::Wol(e) => match e {
-                WolError::InvalidMac(mac) => {
-                    warn!(
-                        error_type = "wol_invalid_mac",
+                StorageError::Corrupt { error, backup } => {
+                    error!(
+                        error_type = "storage_corrupt",
                         status_code = status_code,
-                        mac = %mac,
+                        details = %error,
+                        backup = %backup.display(),
+                        "Request failed"
+                    );
+                }
+                StorageError::Sqlite(err) => {
+                    error!(
+                        error_type = "storage_sqlite",
+                        status_code = status_code,
+                        details = %err,
+                        "Request failed"
+                    );
+                }
+                StorageError::Invalid(err) => {
+                    error!(
+                        error_type = "storage_invalid",
+                        status_code = status_code,
+                        details = %err,
+                        "Request failed"
+                    );
+                }
+                StorageError::UnsupportedVersion { found, supported } => {
+                    error!(
+                        error_type = "storage_unsupported_version",
+                        status_code = status_code,
+                        found = found,
+                        supported = supported,
                         "Request failed"
                     );
                 }
+            },
+            Self::Wol(e) => match e {
                 WolError::Network(err) => {
                     error!(
                         error_type = "wol_network",
@@ -114,6 +197,14 @@ impl ApiError {
                         "Request failed"
                     );
                 }
+                WolError::UnknownInterface(mac) => {
+                    warn!(
+                        error_type = "wol_unknown_interface",
+                        status_code = status_code,
+                        mac = %mac,
+                        "Request failed"
+                    );
+                }
             },
             Self::Arp(e) => match e {
                 ArpError::InvalidIp(err) => {
@@ -124,6 +215,14 @@ impl ApiError {
                         "Request failed"
                     );
                 }
+                ArpError::InvalidScope(scope) => {
+                    warn!(
+                        error_type = "arp_invalid_scope",
+                        status_code = status_code,
+                        scope = %scope,
+                        "Request failed"
+                    );
+                }
                 ArpError::NotFound(ip) => {
                     warn!(
                         error_type = "arp_not_found",
@@ -150,6 +249,49 @@ impl ApiError {
                     );
                 }
             },
+            Self::Backup(e) => match e {
+                BackupError::NotFound(name) => {
+                    warn!(
+                        error_type = "backup_not_found",
+                        status_code = status_code,
+                        backup = %name,
+                        "Request failed"
+                    );
+                }
+                BackupError::Store(err) => {
+                    error!(
+                        error_type = "backup_store",
+                        status_code = status_code,
+                        details = %err,
+                        "Request failed"
+                    );
+                }
+                BackupError::Invalid { name, source } => {
+                    error!(
+                        error_type = "backup_invalid",
+                        status_code = status_code,
+                        backup = %name,
+                        details = %source,
+                        "Request failed"
+                    );
+                }
+                BackupError::Config(err) => {
+                    error!(
+                        error_type = "backup_config",
+                        status_code = status_code,
+                        details = %err,
+                        "Request failed"
+                    );
+                }
+                BackupError::Restore(err) => {
+                    error!(
+                        error_type = "backup_restore",
+                        status_code = status_code,
+                        details = %err,
+                        "Request failed"
+                    );
+                }
+            },
         }
     }
 }
@@ -162,6 +304,10 @@ impl IntoResponse for ApiError {
         let body = ErrorResponse {
             status: "error".to_string(),
             message: self.to_string(),
+            errors: match &self {
+                Self::Validation(e) => e.field_errors(),
+                _ => Vec::new(),
+            },
         };
 
         (status_code, axum::Json(body)).into_response()
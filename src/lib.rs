@@ -0,0 +1,19 @@
+//! Wake-on-LAN web server: device inventory, REST API and web interface.
+//!
+//! The `jump_rs` binary wires these modules together; they are exposed as a
+//! library so benchmarks can drive the storage and service layers directly.
+
+#![allow(clippy::needless_for_each)]
+
+pub mod api;
+pub mod app;
+pub mod auth;
+pub mod backup;
+pub mod cli;
+pub mod config;
+pub mod devices;
+pub mod error;
+pub mod logging;
+pub mod models;
+pub mod storage;
+pub mod web;
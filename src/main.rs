@@ -1,24 +1,13 @@
-#![allow(clippy::needless_for_each)]
-
-mod api;
-mod app;
-mod auth;
-mod cli;
-mod config;
-mod devices;
-mod error;
-mod logging;
-mod models;
-mod storage;
-mod web;
-
-use crate::app::build_app;
-use crate::cli::Cli;
-use crate::storage::SharedStorage;
 use clap::Parser;
+use jump_rs::app::build_app;
+use jump_rs::backup::{self, Backups};
+use jump_rs::cli::Cli;
+use jump_rs::storage::{SharedStorage, StorageLock};
+use jump_rs::{auth, config, devices, logging};
 use std::net::SocketAddr;
+use std::time::Duration;
 use tokio::signal;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 #[tokio::main]
 async fn main() {
@@ -26,7 +15,7 @@ async fn main() {
 
     // Handle CLI commands that exit before running the server
     if let Some(command) = cli.command {
-        if let Err(err) = command.execute() {
+        if let Err(err) = command.execute().await {
             eprintln!("{err:#}");
             std::process::exit(1);
         }
@@ -52,9 +41,26 @@ async fn main() {
     logging::init();
     info!(version = env!("CARGO_PKG_VERSION"), "Starting jump.rs");
 
+    // Held for the whole run, so the CLI does not write the store behind the
+    // server's back.
+    let _storage_lock = {
+        let file_path = config.storage.path(config.storage.backend);
+        match StorageLock::acquire(&config.storage) {
+            Ok(Some(lock)) => lock,
+            Ok(None) => {
+                error!(file = file_path, "Storage is in use by another server");
+                std::process::exit(1);
+            }
+            Err(err) => {
+                error!(error = %err, file = file_path, "Failed to lock storage");
+                std::process::exit(1);
+            }
+        }
+    };
+
     let storage: SharedStorage = {
-        let file_path = &config.storage.file_path;
-        match SharedStorage::load(file_path) {
+        let file_path = config.storage.path(config.storage.backend);
+        match SharedStorage::open(&config.storage) {
             Ok(storage) => {
                 info!(file = file_path, "Storage initialized");
                 storage
@@ -66,6 +72,39 @@ async fn main() {
         }
     };
 
+    // Dropping the watcher stops it, so keep it alive for the whole run.
+    let _storage_watcher = if config.storage.watch {
+        storage.watch().unwrap_or_else(|err| {
+            warn!(error = %err, "Failed to watch storage file, external edits need a restart");
+            None
+        })
+    } else {
+        None
+    };
+
+    if config.network.neighbor_watcher {
+        devices::spawn_neighbor_watcher();
+        info!("Neighbor watcher started");
+    }
+
+    devices::spawn_trash_purger(storage.clone(), config.storage.trash_retention_days);
+
+    if config.backup.enabled {
+        match Backups::open(&config.backup) {
+            Ok(backups) => {
+                let interval = Duration::from_secs(config.backup.interval_hours.max(1) * 3600);
+                backup::spawn_scheduler(storage.clone(), backups, interval);
+                info!(
+                    interval_hours = config.backup.interval_hours,
+                    "Scheduled backups enabled"
+                );
+            }
+            Err(err) => {
+                warn!(error = %err, "Failed to open backup target, scheduled backups disabled")
+            }
+        }
+    }
+
     let app = build_app(storage, auth_state);
 
     let addr = SocketAddr::from(([0, 0, 0, 0], config.server.port));
@@ -1,5 +1,10 @@
 use nanoid::nanoid;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr};
+use std::str::FromStr;
+use std::time::Duration;
 use thiserror::Error;
 use utoipa::ToSchema;
 
@@ -7,6 +12,235 @@ use utoipa::ToSchema;
 pub enum ValidationError {
     #[error("Invalid MAC address format: {0}")]
     InvalidMac(String),
+
+    #[error("Invalid broadcast address: {0}")]
+    InvalidBroadcast(String),
+
+    #[error("At least one MAC address is required")]
+    NoInterfaces,
+
+    #[error("Provide either mac_address or interfaces, not both")]
+    AmbiguousInterfaces,
+
+    #[error("MAC address {mac} is already used by {device}")]
+    DuplicateMac { mac: MacAddress, device: String },
+
+    #[error("{}", describe_fields(.0))]
+    Fields(Vec<FieldError>),
+}
+
+impl ValidationError {
+    /// The offending fields, for error bodies and form highlighting.
+    pub fn field_errors(&self) -> Vec<FieldError> {
+        let field = match self {
+            Self::Fields(errors) => return errors.clone(),
+            Self::InvalidMac(_) | Self::DuplicateMac { .. } => "mac_address",
+            Self::InvalidBroadcast(_) => "broadcast",
+            Self::NoInterfaces | Self::AmbiguousInterfaces => "interfaces",
+        };
+        vec![FieldError::new(field, self.to_string())]
+    }
+}
+
+pub const MAX_NAME_LEN: usize = 64;
+pub const MAX_DESCRIPTION_LEN: usize = 500;
+pub const MAX_HOST_LEN: usize = 253;
+pub const MAX_INTERFACES: usize = 8;
+pub const MAX_LABEL_LEN: usize = 32;
+pub const MAX_TAGS: usize = 16;
+pub const MAX_TAG_LEN: usize = 32;
+pub const MAX_METADATA_ENTRIES: usize = 32;
+pub const MAX_METADATA_KEY_LEN: usize = 64;
+pub const MAX_METADATA_VALUE_LEN: usize = 256;
+
+/// How long a hostname lookup may take before the name counts as unresolvable.
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_CONCURRENT_LOOKUPS: usize = 16;
+
+/// A single invalid request field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct FieldError {
+    /// Field path, e.g. `name`, `interfaces[1].broadcast` or `metadata.owner`
+    #[schema(example = "ip_address")]
+    pub field: String,
+    #[schema(example = "Must be an IP address or a hostname")]
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+fn describe_fields(errors: &[FieldError]) -> String {
+    let details: Vec<String> = errors
+        .iter()
+        .map(|error| format!("{}: {}", error.field, error.message))
+        .collect();
+    format!("Invalid fields: {}", details.join("; "))
+}
+
+/// Collects field errors so a request reports every violation at once.
+#[derive(Debug, Default)]
+pub struct FieldErrors(Vec<FieldError>);
+
+impl FieldErrors {
+    pub fn add(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.0.push(FieldError::new(field, message));
+    }
+
+    pub fn push(&mut self, error: FieldError) {
+        self.0.push(error);
+    }
+
+    pub fn contains(&self, field: &str) -> bool {
+        self.0.iter().any(|error| error.field == field)
+    }
+
+    /// Records the error of `result`, if any, with its fields nested under `prefix`.
+    pub fn merge<T>(&mut self, prefix: &str, result: Result<T, ValidationError>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.0
+                    .extend(err.field_errors().into_iter().map(|error| FieldError {
+                        field: format!("{prefix}{}", error.field),
+                        ..error
+                    }));
+                None
+            }
+        }
+    }
+
+    pub fn into_result(self) -> Result<(), ValidationError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError::Fields(self.0))
+        }
+    }
+}
+
+/// A 48-bit hardware address. Accepts the usual notations (`AA:BB:CC:DD:EE:FF`,
+/// `aa-bb-cc-dd-ee-ff`, `aabb.ccdd.eeff`, unpadded BSD octets) and always
+/// displays and serializes in uppercase colon form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MacAddress([u8; 6]);
+
+impl MacAddress {
+    pub const fn octets(self) -> [u8; 6] {
+        self.0
+    }
+}
+
+impl FromStr for MacAddress {
+    type Err = ValidationError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || ValidationError::InvalidMac(value.to_string());
+        let trimmed = value.trim();
+
+        // Colon or dash separated octets may omit leading zeros (`2:10:18:...`).
+        for separator in [':', '-'] {
+            let groups: Vec<&str> = trimmed.split(separator).collect();
+            if groups.len() == 6
+                && groups
+                    .iter()
+                    .all(|group| (1..=2).contains(&group.len()) && is_hex(group))
+            {
+                let mut octets = [0u8; 6];
+                for (octet, group) in octets.iter_mut().zip(groups) {
+                    *octet = u8::from_str_radix(group, 16).map_err(|_| invalid())?;
+                }
+                return Ok(Self(octets));
+            }
+        }
+
+        let cleaned = trimmed.replace([':', '-', '.', ' '], "");
+        if cleaned.len() != 12 || !is_hex(&cleaned) {
+            return Err(invalid());
+        }
+
+        let mut octets = [0u8; 6];
+        for (octet, chunk) in octets.iter_mut().zip(cleaned.as_bytes().chunks(2)) {
+            let chunk = std::str::from_utf8(chunk).map_err(|_| invalid())?;
+            *octet = u8::from_str_radix(chunk, 16).map_err(|_| invalid())?;
+        }
+        Ok(Self(octets))
+    }
+}
+
+fn is_hex(value: &str) -> bool {
+    value.chars().all(|ch| ch.is_ascii_hexdigit())
+}
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02X}:{b:02X}:{c:02X}:{d:02X}:{e:02X}:{g:02X}")
+    }
+}
+
+impl Serialize for MacAddress {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for MacAddress {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// One NIC of a device. Its magic packet goes to `broadcast` when set, so
+/// each NIC can be reached on its own subnet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct NetworkInterface {
+    #[schema(value_type = String, example = "00:11:22:33:44:55")]
+    pub mac_address: MacAddress,
+    #[serde(default)]
+    #[schema(example = "onboard")]
+    pub label: Option<String>,
+    /// Directed broadcast address; defaults to 255.255.255.255.
+    #[serde(default)]
+    #[schema(value_type = Option<String>, example = "192.168.1.255")]
+    pub broadcast: Option<Ipv4Addr>,
+}
+
+impl NetworkInterface {
+    pub fn new(mac_address: &str) -> Result<Self, ValidationError> {
+        Self::parse(mac_address, None, None)
+    }
+
+    pub fn parse(
+        mac_address: &str,
+        label: Option<String>,
+        broadcast: Option<&str>,
+    ) -> Result<Self, ValidationError> {
+        let broadcast = broadcast
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| ValidationError::InvalidBroadcast(value.to_string()))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            mac_address: mac_address.parse()?,
+            label: label
+                .map(|label| label.trim().to_string())
+                .filter(|label| !label.is_empty()),
+            broadcast,
+        })
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
@@ -15,59 +249,331 @@ pub struct Device {
     pub id: String,
     #[schema(example = "Gaming PC")]
     pub name: String,
-    #[schema(example = "00:11:22:33:44:55")]
-    pub mac_address: String,
+    /// Every NIC that may receive the magic packet; never empty. Records that
+    /// predate multiple interfaces stored a single `mac_address` instead.
+    #[serde(alias = "mac_address", deserialize_with = "deserialize_interfaces")]
+    pub interfaces: Vec<NetworkInterface>,
     #[schema(example = "192.168.1.100")]
     pub ip_address: Option<String>,
     #[schema(default = 9)]
     pub port: u16,
     #[schema(example = "My main computer")]
     pub description: Option<String>,
+    #[serde(default)]
+    #[schema(example = json!(["lab", "gpu"]))]
+    pub tags: Vec<String>,
+    /// Free-form attributes such as asset tag, rack or owner.
+    #[serde(default)]
+    #[schema(example = json!({"location": "Rack 4", "owner": "infra"}))]
+    pub metadata: BTreeMap<String, String>,
+    /// Position in the device grid; lower values come first.
+    #[serde(default)]
+    #[schema(example = 0)]
+    pub sort_order: u32,
+    /// Favorites are pinned above the other devices in the web UI.
+    #[serde(default)]
+    pub favorite: bool,
+    /// Incremented on every change; the device's `ETag` is derived from it.
+    #[serde(default)]
+    #[schema(example = 3)]
+    pub revision: u64,
     #[schema(value_type = String)]
     #[serde(with = "time::serde::rfc3339")]
     pub created_at: time::OffsetDateTime,
+    /// Missing on records that have not changed since `updated_at` was added.
+    #[schema(value_type = Option<String>)]
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub updated_at: Option<time::OffsetDateTime>,
+    /// Set while the device is in the trash; it is purged once the retention
+    /// period has passed.
+    #[schema(value_type = Option<String>)]
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub deleted_at: Option<time::OffsetDateTime>,
 }
 
 impl Device {
     pub fn new(
         name: String,
-        mac_address: String,
+        interfaces: Vec<NetworkInterface>,
         ip_address: Option<String>,
         port: u16,
         description: Option<String>,
-    ) -> Result<Self, ValidationError> {
-        validate_mac_address(&mac_address)?;
-
-        Ok(Self {
+    ) -> Self {
+        let now = time::OffsetDateTime::now_utc();
+        Self {
             id: nanoid!(10),
             name,
-            mac_address,
+            interfaces,
             ip_address,
             port,
             description,
-            created_at: time::OffsetDateTime::now_utc(),
-        })
+            tags: Vec::new(),
+            metadata: BTreeMap::new(),
+            sort_order: 0,
+            favorite: false,
+            revision: 1,
+            created_at: now,
+            updated_at: Some(now),
+            deleted_at: None,
+        }
     }
+
+    /// Checks every user-editable field and reports all violations together.
+    /// Hostnames are only checked for syntax here; see [`check_resolvable`].
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let mut errors = FieldErrors::default();
+
+        if self.name.trim().is_empty() {
+            errors.add("name", "Name is required");
+        } else if self.name.chars().count() > MAX_NAME_LEN {
+            errors.add("name", too_long(MAX_NAME_LEN));
+        }
+
+        if self.interfaces.len() > MAX_INTERFACES {
+            errors.add(
+                "interfaces",
+                format!("At most {MAX_INTERFACES} interfaces are allowed"),
+            );
+        }
+        for (index, interface) in self.interfaces.iter().enumerate() {
+            if interface
+                .label
+                .as_ref()
+                .is_some_and(|label| label.chars().count() > MAX_LABEL_LEN)
+            {
+                errors.add(
+                    format!("interfaces[{index}].label"),
+                    too_long(MAX_LABEL_LEN),
+                );
+            }
+        }
+
+        if let Some(ip_address) = &self.ip_address
+            && let Err(message) = check_host(ip_address)
+        {
+            errors.add("ip_address", message);
+        }
+
+        if self.port == 0 {
+            errors.add("port", "Port must be between 1 and 65535");
+        }
+
+        if self
+            .description
+            .as_ref()
+            .is_some_and(|description| description.chars().count() > MAX_DESCRIPTION_LEN)
+        {
+            errors.add("description", too_long(MAX_DESCRIPTION_LEN));
+        }
+
+        if self.tags.len() > MAX_TAGS {
+            errors.add("tags", format!("At most {MAX_TAGS} tags are allowed"));
+        }
+        if let Some(tag) = self
+            .tags
+            .iter()
+            .find(|tag| tag.chars().count() > MAX_TAG_LEN)
+        {
+            errors.add(
+                "tags",
+                format!("Tag \"{tag}\" is longer than {MAX_TAG_LEN} characters"),
+            );
+        }
+
+        if self.metadata.len() > MAX_METADATA_ENTRIES {
+            errors.add(
+                "metadata",
+                format!("At most {MAX_METADATA_ENTRIES} metadata fields are allowed"),
+            );
+        }
+        for (key, value) in &self.metadata {
+            if key.chars().count() > MAX_METADATA_KEY_LEN {
+                errors.add(
+                    "metadata",
+                    format!("Key \"{key}\" is longer than {MAX_METADATA_KEY_LEN} characters"),
+                );
+            } else if value.chars().count() > MAX_METADATA_VALUE_LEN {
+                errors.add(format!("metadata.{key}"), too_long(MAX_METADATA_VALUE_LEN));
+            }
+        }
+
+        errors.into_result()
+    }
+
+    /// Strong entity tag for the current revision, quotes included.
+    pub fn etag(&self) -> String {
+        revision_etag(self.revision)
+    }
+
+    /// Evaluates an `If-Match` header value: `*` or a list of entity tags, of
+    /// which one must name the current revision.
+    pub fn matches_etag(&self, if_match: &str) -> bool {
+        let etag = self.etag();
+        if_match
+            .split(',')
+            .map(str::trim)
+            .any(|candidate| candidate == "*" || candidate == etag)
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    pub fn macs(&self) -> impl Iterator<Item = MacAddress> + '_ {
+        self.interfaces
+            .iter()
+            .map(|interface| interface.mac_address)
+    }
+
+    #[must_use]
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = normalize_tags(tags);
+        self
+    }
+
+    #[must_use]
+    pub fn with_metadata(mut self, metadata: BTreeMap<String, String>) -> Self {
+        self.metadata = normalize_metadata(metadata);
+        self
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags
+            .iter()
+            .any(|own| own.eq_ignore_ascii_case(tag.trim()))
+    }
+}
+
+pub fn revision_etag(revision: u64) -> String {
+    format!("\"{revision}\"")
 }
 
-pub fn validate_mac_address(mac_str: &str) -> Result<(), ValidationError> {
-    let cleaned: String = mac_str.replace([':', '-', '.', ' '], "").to_lowercase();
+fn too_long(max: usize) -> String {
+    format!("Must be at most {max} characters")
+}
 
-    if cleaned.len() != 12 {
-        return Err(ValidationError::InvalidMac(mac_str.to_string()));
+/// Accepts IP literals and syntactically valid hostnames.
+fn check_host(value: &str) -> Result<(), String> {
+    if value.parse::<IpAddr>().is_ok() {
+        return Ok(());
     }
 
-    for (i, chunk) in cleaned.as_bytes().chunks(2).enumerate() {
-        if i >= 6 {
-            return Err(ValidationError::InvalidMac(mac_str.to_string()));
+    let valid_hostname = value.len() <= MAX_HOST_LEN
+        && value.trim_end_matches('.').split('.').all(|label| {
+            (1..=63).contains(&label.len())
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .chars()
+                    .all(|ch| ch.is_ascii_alphanumeric() || ch == '-')
+        });
+    if valid_hostname {
+        Ok(())
+    } else {
+        Err("Must be an IP address or a hostname".to_string())
+    }
+}
+
+/// Resolves a hostname through the system resolver. IP literals pass without
+/// a lookup. Kept apart from [`Device::validate`] so that only newly entered
+/// addresses pay for the DNS query.
+pub async fn check_resolvable(host: &str) -> Result<(), FieldError> {
+    if resolves(host.to_string()).await {
+        Ok(())
+    } else {
+        Err(unresolvable(host))
+    }
+}
+
+/// The `ip_address` error for a hostname that does not resolve.
+pub fn unresolvable(host: &str) -> FieldError {
+    FieldError::new("ip_address", format!("Hostname {host} does not resolve"))
+}
+
+/// The distinct `hosts` that do not resolve, looked up a few at a time so a
+/// large import does not wait for each name in turn.
+pub async fn unresolvable_hosts(hosts: impl IntoIterator<Item = String>) -> HashSet<String> {
+    let mut pending: Vec<String> = hosts
+        .into_iter()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let mut lookups = tokio::task::JoinSet::new();
+    let mut unresolvable = HashSet::new();
+    loop {
+        while lookups.len() < MAX_CONCURRENT_LOOKUPS
+            && let Some(host) = pending.pop()
+        {
+            lookups.spawn(async move { (resolves(host.clone()).await, host) });
         }
-        let hex_str = std::str::from_utf8(chunk).unwrap();
-        if u8::from_str_radix(hex_str, 16).is_err() {
-            return Err(ValidationError::InvalidMac(mac_str.to_string()));
+        match lookups.join_next().await {
+            Some(Ok((false, host))) => {
+                unresolvable.insert(host);
+            }
+            Some(_) => {}
+            None => return unresolvable,
         }
     }
+}
 
-    Ok(())
+/// Whether `host` is an IP literal or resolves within [`RESOLVE_TIMEOUT`].
+/// The lookup runs on tokio's blocking pool, never on a runtime thread.
+async fn resolves(host: String) -> bool {
+    if host.parse::<IpAddr>().is_ok() {
+        return true;
+    }
+    let lookup = tokio::net::lookup_host((host, 0));
+    tokio::time::timeout(RESOLVE_TIMEOUT, lookup)
+        .await
+        .is_ok_and(|result| result.is_ok_and(|mut addresses| addresses.next().is_some()))
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredInterfaces {
+    Legacy(MacAddress),
+    List(Vec<NetworkInterface>),
+}
+
+fn deserialize_interfaces<'de, D>(deserializer: D) -> Result<Vec<NetworkInterface>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let interfaces = match StoredInterfaces::deserialize(deserializer)? {
+        StoredInterfaces::Legacy(mac_address) => vec![NetworkInterface {
+            mac_address,
+            label: None,
+            broadcast: None,
+        }],
+        StoredInterfaces::List(interfaces) => interfaces,
+    };
+
+    if interfaces.is_empty() {
+        return Err(serde::de::Error::custom(ValidationError::NoInterfaces));
+    }
+    Ok(interfaces)
+}
+
+/// Trims and lowercases tags, dropping empty and repeated entries while
+/// keeping the order they were given in.
+pub fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+/// Trims keys and values, dropping entries where either ends up empty.
+pub fn normalize_metadata(metadata: BTreeMap<String, String>) -> BTreeMap<String, String> {
+    metadata
+        .into_iter()
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .filter(|(key, value)| !key.is_empty() && !value.is_empty())
+        .collect()
 }
 
 #[cfg(test)]
@@ -75,8 +581,8 @@ mod tests {
     use super::*;
 
     #[test]
-    fn validate_mac_with_colons() {
-        assert!(validate_mac_address("AA:BB:CC:DD:EE:FF").is_ok());
+    fn parse_mac_with_colons() {
+        assert!("AA:BB:CC:DD:EE:FF".parse::<MacAddress>().is_ok());
     }
 
     #[test]
@@ -86,43 +592,43 @@ mod tests {
     }
 
     #[test]
-    fn validate_mac_with_dashes() {
-        assert!(validate_mac_address("AA-BB-CC-DD-EE-FF").is_ok());
+    fn parse_mac_with_dashes() {
+        assert!("AA-BB-CC-DD-EE-FF".parse::<MacAddress>().is_ok());
     }
 
     #[test]
-    fn validate_mac_with_dots() {
-        assert!(validate_mac_address("AABB.CCDD.EEFF").is_ok());
+    fn parse_mac_with_dots() {
+        assert!("AABB.CCDD.EEFF".parse::<MacAddress>().is_ok());
     }
 
     #[test]
-    fn validate_mac_without_separators() {
-        assert!(validate_mac_address("AABBCCDDEEFF").is_ok());
+    fn parse_mac_without_separators() {
+        assert!("AABBCCDDEEFF".parse::<MacAddress>().is_ok());
     }
 
     #[test]
-    fn validate_mac_lowercase() {
-        assert!(validate_mac_address("aa:bb:cc:dd:ee:ff").is_ok());
+    fn parse_mac_lowercase() {
+        assert!("aa:bb:cc:dd:ee:ff".parse::<MacAddress>().is_ok());
     }
 
     #[test]
-    fn validate_mac_mixed_case() {
-        assert!(validate_mac_address("Aa:Bb:Cc:Dd:Ee:Ff").is_ok());
+    fn parse_mac_mixed_case() {
+        assert!("Aa:Bb:Cc:Dd:Ee:Ff".parse::<MacAddress>().is_ok());
     }
 
     #[test]
-    fn validate_mac_with_spaces() {
-        assert!(validate_mac_address("AA BB CC DD EE FF").is_ok());
+    fn parse_mac_with_spaces() {
+        assert!("AA BB CC DD EE FF".parse::<MacAddress>().is_ok());
     }
 
     #[test]
-    fn validate_mac_mixed_separators() {
-        assert!(validate_mac_address("AA:BB-CC.DD EE:FF").is_ok());
+    fn parse_mac_mixed_separators() {
+        assert!("AA:BB-CC.DD EE:FF".parse::<MacAddress>().is_ok());
     }
 
     #[test]
     fn reject_mac_too_short() {
-        let result = validate_mac_address("AA:BB:CC:DD:EE");
+        let result = "AA:BB:CC:DD:EE".parse::<MacAddress>();
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
@@ -132,7 +638,7 @@ mod tests {
 
     #[test]
     fn reject_mac_too_long() {
-        let result = validate_mac_address("AA:BB:CC:DD:EE:FF:00");
+        let result = "AA:BB:CC:DD:EE:FF:00".parse::<MacAddress>();
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
@@ -142,7 +648,7 @@ mod tests {
 
     #[test]
     fn reject_mac_empty() {
-        let result = validate_mac_address("");
+        let result = "".parse::<MacAddress>();
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
@@ -152,7 +658,7 @@ mod tests {
 
     #[test]
     fn reject_mac_invalid_hex_chars() {
-        let result = validate_mac_address("GG:HH:II:JJ:KK:LL");
+        let result = "GG:HH:II:JJ:KK:LL".parse::<MacAddress>();
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
@@ -162,13 +668,13 @@ mod tests {
 
     #[test]
     fn reject_mac_special_chars() {
-        let result = validate_mac_address("AA:BB:CC:DD:EE:F!");
+        let result = "AA:BB:CC:DD:EE:F!".parse::<MacAddress>();
         assert!(result.is_err());
     }
 
     #[test]
     fn reject_mac_partial_invalid() {
-        let result = validate_mac_address("AA:BB:CC:DD:EE:ZZ");
+        let result = "AA:BB:CC:DD:EE:ZZ".parse::<MacAddress>();
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
@@ -176,25 +682,69 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn parse_mac_octets() {
+        let mac: MacAddress = "Aa-Bb-Cc-Dd-Ee-Ff".parse().unwrap();
+        assert_eq!(mac.octets(), [0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]);
+    }
+
+    #[test]
+    fn parse_mac_all_zeros_and_ones() {
+        let zeros: MacAddress = "00:00:00:00:00:00".parse().unwrap();
+        let ones: MacAddress = "FF:FF:FF:FF:FF:FF".parse().unwrap();
+        assert_eq!(zeros.octets(), [0x00; 6]);
+        assert_eq!(ones.octets(), [0xFF; 6]);
+    }
+
+    #[test]
+    fn parse_mac_unpadded_octets() {
+        let mac: MacAddress = "2:10:18:50:19:8c".parse().unwrap();
+        assert_eq!(mac.to_string(), "02:10:18:50:19:8C");
+    }
+
+    #[test]
+    fn mac_displays_in_canonical_form() {
+        for input in ["aabb.ccdd.eeff", "AA-BB-CC-DD-EE-FF", "aabbccddeeff"] {
+            let mac: MacAddress = input.parse().unwrap();
+            assert_eq!(mac.to_string(), "AA:BB:CC:DD:EE:FF");
+        }
+    }
+
+    #[test]
+    fn mac_serde_round_trips_canonical_string() {
+        let mac: MacAddress = serde_json::from_str("\"aabb.ccdd.eeff\"").unwrap();
+        assert_eq!(
+            serde_json::to_string(&mac).unwrap(),
+            "\"AA:BB:CC:DD:EE:FF\""
+        );
+        assert!(serde_json::from_str::<MacAddress>("\"bad\"").is_err());
+    }
+
+    fn interfaces(mac: &str) -> Vec<NetworkInterface> {
+        vec![NetworkInterface::new(mac).unwrap()]
+    }
+
     #[test]
     fn create_device_with_valid_mac() {
         let device = Device::new(
             "Test Device".to_string(),
-            "AA:BB:CC:DD:EE:FF".to_string(),
+            interfaces("AA:BB:CC:DD:EE:FF"),
             Some("192.168.1.100".to_string()),
             9,
             Some("Test description".to_string()),
         );
-        assert!(device.is_ok());
-        let device = device.unwrap();
+        assert!(device.validate().is_ok());
         assert_eq!(device.name, "Test Device");
-        assert_eq!(device.mac_address, "AA:BB:CC:DD:EE:FF");
+        assert_eq!(
+            device.interfaces[0].mac_address.to_string(),
+            "AA:BB:CC:DD:EE:FF"
+        );
         assert_eq!(device.port, 9);
     }
 
     #[test]
-    fn create_device_with_invalid_mac_fails() {
-        let result = Device::new("Test".to_string(), "invalid-mac".to_string(), None, 9, None);
+    fn create_interface_with_invalid_mac_fails() {
+        let result = NetworkInterface::new("invalid-mac");
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
@@ -202,18 +752,208 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn create_interface_with_invalid_broadcast_fails() {
+        let result = NetworkInterface::parse("AA:BB:CC:DD:EE:FF", None, Some("10.0.0"));
+        assert!(matches!(result, Err(ValidationError::InvalidBroadcast(_))));
+    }
+
+    #[test]
+    fn device_with_too_many_interfaces_fails_validation() {
+        let interfaces = (0..=MAX_INTERFACES)
+            .map(|index| NetworkInterface::new(&format!("AA:BB:CC:DD:EE:{index:02X}")).unwrap())
+            .collect();
+        let device = Device::new("Test".to_string(), interfaces, None, 9, None);
+        let errors = device.validate().unwrap_err().field_errors();
+        assert_eq!(errors[0].field, "interfaces");
+    }
+
+    #[test]
+    fn validate_reports_every_invalid_field() {
+        let device = Device::new(
+            "  ".to_string(),
+            interfaces("AA:BB:CC:DD:EE:FF"),
+            Some("not a host!".to_string()),
+            0,
+            Some("x".repeat(MAX_DESCRIPTION_LEN + 1)),
+        );
+
+        let err = device.validate().unwrap_err();
+        let fields: Vec<String> = err.field_errors().into_iter().map(|e| e.field).collect();
+        assert_eq!(fields, ["name", "ip_address", "port", "description"]);
+        assert!(err.to_string().starts_with("Invalid fields: name: "));
+    }
+
+    #[test]
+    fn ip_address_accepts_literals_and_hostnames() {
+        for host in [
+            "192.168.1.10",
+            "fe80::1",
+            "nas",
+            "nas.lan.",
+            "host-01.example.com",
+        ] {
+            assert!(check_host(host).is_ok(), "{host}");
+        }
+        for host in ["-nas", "nas..lan", "nas_lan", "192.168.1.10:9"] {
+            assert!(check_host(host).is_err(), "{host}");
+        }
+    }
+
+    #[tokio::test]
+    async fn hostnames_are_resolved() {
+        assert!(check_resolvable("10.0.0.1").await.is_ok());
+        assert!(check_resolvable("localhost").await.is_ok());
+        assert!(check_resolvable("nowhere.invalid").await.is_err());
+
+        let hosts = ["localhost", "10.0.0.1", "nowhere.invalid", "localhost"];
+        let unresolvable = unresolvable_hosts(hosts.map(String::from)).await;
+        assert_eq!(unresolvable, HashSet::from(["nowhere.invalid".to_string()]));
+    }
+
+    #[test]
+    fn field_errors_merge_with_prefix() {
+        let mut errors = FieldErrors::default();
+        errors.add("name", "Name is required");
+        let interface = errors.merge("interfaces[1].", NetworkInterface::new("nope"));
+        assert!(interface.is_none());
+
+        let fields: Vec<String> = errors
+            .into_result()
+            .unwrap_err()
+            .field_errors()
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(fields, ["name", "interfaces[1].mac_address"]);
+    }
+
     #[test]
     fn create_device_with_optional_fields_none() {
         let device = Device::new(
             "Minimal Device".to_string(),
-            "11:22:33:44:55:66".to_string(),
+            interfaces("11:22:33:44:55:66"),
             None,
             9,
             None,
-        )
-        .unwrap();
+        );
 
         assert!(device.ip_address.is_none());
         assert!(device.description.is_none());
+        assert!(device.tags.is_empty());
+    }
+
+    #[test]
+    fn tags_are_normalized() {
+        let device = Device::new(
+            "Tagged".to_string(),
+            interfaces("11:22:33:44:55:66"),
+            None,
+            9,
+            None,
+        )
+        .with_tags(vec![
+            " Lab ".to_string(),
+            "gpu".to_string(),
+            "LAB".to_string(),
+            String::new(),
+        ]);
+
+        assert_eq!(device.tags, vec!["lab", "gpu"]);
+        assert!(device.has_tag("GPU"));
+        assert!(!device.has_tag("rack"));
+    }
+
+    #[test]
+    fn metadata_is_trimmed_and_blank_entries_dropped() {
+        let metadata = BTreeMap::from([
+            (" location ".to_string(), " Rack 4 ".to_string()),
+            ("owner".to_string(), "   ".to_string()),
+            (String::new(), "orphan".to_string()),
+        ]);
+        let device = Device::new(
+            "Lab".to_string(),
+            interfaces("AA:BB:CC:DD:EE:FF"),
+            None,
+            9,
+            None,
+        )
+        .with_metadata(metadata);
+
+        assert_eq!(
+            device.metadata,
+            BTreeMap::from([("location".to_string(), "Rack 4".to_string())])
+        );
+    }
+
+    #[test]
+    fn etag_follows_revision() {
+        let mut device = Device::new(
+            "Tagged".to_string(),
+            interfaces("11:22:33:44:55:66"),
+            None,
+            9,
+            None,
+        );
+        assert_eq!(device.etag(), "\"1\"");
+        assert!(device.matches_etag("\"1\""));
+        assert!(device.matches_etag("\"0\", \"1\""));
+        assert!(device.matches_etag("*"));
+
+        device.revision = 2;
+        assert!(!device.matches_etag("\"1\""));
+        assert!(!device.matches_etag("W/\"2\""));
+        assert!(!device.matches_etag("garbage"));
+    }
+
+    #[test]
+    fn legacy_device_without_new_fields_deserializes() {
+        let device: Device = serde_json::from_str(
+            r#"{"id":"a","name":"Old","mac_address":"11:22:33:44:55:66","ip_address":null,"port":9,"description":null,"created_at":"2024-01-01T00:00:00Z"}"#,
+        )
+        .unwrap();
+        assert!(device.tags.is_empty());
+        assert!(device.metadata.is_empty());
+        assert_eq!(device.sort_order, 0);
+        assert!(!device.favorite);
+        assert_eq!(device.revision, 0);
+        assert!(device.updated_at.is_none());
+        assert_eq!(device.interfaces, interfaces("11:22:33:44:55:66"));
+    }
+
+    #[test]
+    fn device_with_interface_list_round_trips() {
+        let mut device = Device::new(
+            "Dual".to_string(),
+            interfaces("11:22:33:44:55:66"),
+            None,
+            9,
+            None,
+        );
+        device.interfaces.push(
+            NetworkInterface::parse(
+                "aa-bb-cc-dd-ee-ff",
+                Some("10GbE".to_string()),
+                Some("10.0.0.255"),
+            )
+            .unwrap(),
+        );
+
+        let json = serde_json::to_string(&device).unwrap();
+        assert!(json.contains("\"interfaces\""));
+        let parsed: Device = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, device);
+        assert_eq!(
+            parsed.macs().map(|mac| mac.to_string()).collect::<Vec<_>>(),
+            ["11:22:33:44:55:66", "AA:BB:CC:DD:EE:FF"]
+        );
+    }
+
+    #[test]
+    fn device_with_empty_interface_list_is_rejected() {
+        let result = serde_json::from_str::<Device>(
+            r#"{"id":"a","name":"None","interfaces":[],"ip_address":null,"port":9,"description":null,"created_at":"2024-01-01T00:00:00Z"}"#,
+        );
+        assert!(result.is_err());
     }
 }
@@ -1,11 +1,29 @@
+mod index;
+mod json;
+mod sqlite;
+mod table;
+mod watch;
+mod writer;
+
+pub use index::DeviceIndex;
+pub use json::{JsonStorage, decode_devices, encode_devices, parse_device_file};
+pub use sqlite::SqliteStorage;
+pub use watch::ReloadSummary;
+
+use table::DeviceTable;
+
 use parking_lot::RwLock;
 use thiserror::Error;
-use tracing::{debug, info, instrument};
+use tracing::warn;
 
-use crate::models::Device;
-use std::fs;
-use std::path::Path;
+use crate::config::{StorageConfig, StorageKind};
+use crate::models::{Device, MacAddress, ValidationError};
+use std::collections::HashMap;
+use std::fs::{File, TryLockError};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
 
 #[derive(Debug, Error)]
 pub enum StorageError {
@@ -15,445 +33,365 @@ pub enum StorageError {
     #[error("Storage data corruption: {0}")]
     Parse(#[from] serde_json::Error),
 
-    #[error("Device not found: {0}")]
-    NotFound(String),
-}
-
-#[derive(Debug, Clone)]
-pub struct DeviceStorage {
-    path: String,
-    pub devices: Vec<Device>,
-}
-
-impl DeviceStorage {
-    pub fn new(path: &str) -> Self {
-        Self {
-            path: path.to_string(),
-            devices: Vec::new(),
-        }
-    }
-
-    #[instrument(skip_all, fields(path = %path))]
-    pub fn load(path: &str) -> Result<Self, StorageError> {
-        if !Path::new(path).exists() {
-            info!("Storage file not found, starting fresh");
-            return Ok(Self::new(path));
-        }
+    #[error(
+        "Storage data corruption: {error}; the newest readable backup is {}, \
+         restore it with `jumpers storage recover`",
+        backup.display()
+    )]
+    Corrupt {
+        error: serde_json::Error,
+        backup: PathBuf,
+    },
 
-        let content = fs::read_to_string(path)?;
-        let devices: Vec<Device> = if content.trim().is_empty() {
-            Vec::new()
-        } else {
-            serde_json::from_str(&content)?
-        };
+    #[error("Storage database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
 
-        info!(device_count = devices.len(), "Storage loaded");
-        Ok(Self {
-            path: path.to_string(),
-            devices,
-        })
-    }
+    #[error(
+        "Storage file has version {found}, but this build only supports up to {supported}; \
+         upgrade jumpers to read it"
+    )]
+    UnsupportedVersion { found: u32, supported: u32 },
 
-    #[instrument(skip_all, fields(path = %self.path))]
-    pub fn save(&self) -> Result<(), StorageError> {
-        let content = serde_json::to_string_pretty(&self.devices)?;
+    #[error("Storage file holds invalid devices: {0}")]
+    Invalid(ValidationError),
 
-        fs::write(&self.path, content)?;
-        debug!(device_count = self.devices.len(), "Storage saved");
-        Ok(())
-    }
-
-    #[instrument(skip_all)]
-    pub fn add(&mut self, device: Device) -> Result<(), StorageError> {
-        self.devices.push(device);
-        self.save()
-    }
-
-    #[instrument(skip_all)]
-    pub fn add_all(&mut self, devices: Vec<Device>) -> Result<(), StorageError> {
-        self.devices.extend(devices);
-        self.save()
-    }
-
-    #[instrument(skip_all)]
-    pub fn remove(&mut self, id: &str) -> Result<Device, StorageError> {
-        let index = self.devices.iter().position(|d| d.id == id);
-        if let Some(i) = index {
-            let device = self.devices.remove(i);
-            self.save()?;
-            debug!("Device removed from storage");
-            Ok(device)
-        } else {
-            debug!("Device not found in storage");
-            Err(StorageError::NotFound(id.to_string()))
-        }
-    }
+    #[error("Device not found: {0}")]
+    NotFound(String),
 
-    #[instrument(skip_all)]
-    pub fn update(&mut self, id: &str, device: Device) -> Result<Device, StorageError> {
-        let index = self.devices.iter().position(|d| d.id == id);
-        if let Some(i) = index {
-            self.devices[i] = Device {
-                id: self.devices[i].id.clone(),
-                ..device
-            };
-            self.save()?;
-            debug!("Device updated in storage");
-            Ok(self.devices.get(i).cloned().unwrap())
-        } else {
-            debug!("Device not found in storage");
-            Err(StorageError::NotFound(id.to_string()))
-        }
-    }
+    #[error("Device {id} was changed by someone else (now at revision {current})")]
+    Stale { id: String, current: u64 },
 
-    pub fn get(&self, id: &str) -> Option<Device> {
-        self.devices.iter().find(|d| d.id == id).cloned()
-    }
+    #[error("Device id {0} is already in use")]
+    DuplicateId(String),
 
-    pub fn get_all(&self) -> Vec<Device> {
-        self.devices.clone()
-    }
+    #[error("MAC address {mac} is already used by {device}")]
+    DuplicateMac { mac: MacAddress, device: String },
 }
 
+/// One step of a [`StorageBackend::apply`] batch.
 #[derive(Debug, Clone)]
-pub struct SharedStorage(Arc<RwLock<DeviceStorage>>);
-
-impl SharedStorage {
-    pub fn load(path: &str) -> Result<Self, StorageError> {
-        Ok(Self(Arc::new(RwLock::new(DeviceStorage::load(path)?))))
-    }
+pub enum Mutation {
+    Add(Device),
+    Remove(String),
+    /// Replaces a device. `device.revision` must still be the stored one, so
+    /// an edit based on an outdated copy fails with [`StorageError::Stale`]
+    /// instead of discarding the newer changes. Bumps the revision on success.
+    Update {
+        id: String,
+        device: Device,
+    },
+    /// Rearranges the listed devices into the given order within the slots
+    /// they already occupy; unlisted devices keep their positions. Bumps the
+    /// revision of every device whose sort order changes.
+    Reorder(Vec<String>),
+    /// Removes every device, including those in the trash.
+    Clear,
+}
 
-    pub fn add(&self, device: Device) -> Result<(), StorageError> {
-        self.0.write().add(device)
-    }
+/// Where devices are persisted. Implementations keep devices in their
+/// `sort_order`, enforce revisions on [`Mutation::Update`] and keep ids and
+/// the MACs of live devices unique.
+///
+/// Every change goes through [`StorageBackend::apply`], which only keeps it
+/// in memory once it is persisted. A failed write leaves both the stored and
+/// the in-memory devices as they were.
+pub trait StorageBackend: Send + Sync + std::fmt::Debug {
+    /// Applies the mutations in order and commits them all-or-nothing: if any
+    /// step fails, none of them is kept.
+    fn apply(&mut self, mutations: Vec<Mutation>) -> Result<(), StorageError>;
 
-    pub fn add_all(&self, devices: Vec<Device>) -> Result<(), StorageError> {
-        self.0.write().add_all(devices)
-    }
+    fn get_all(&self) -> Vec<Device>;
 
-    pub fn remove(&self, id: &str) -> Result<Device, StorageError> {
-        self.0.write().remove(id)
+    /// File that other programs may edit while the server runs, for backends
+    /// that can pick such edits up with [`StorageBackend::reload`].
+    fn watched_path(&self) -> Option<&str> {
+        None
     }
 
-    pub fn update(&self, id: &str, device: Device) -> Result<Device, StorageError> {
-        self.0.write().update(id, device)
+    /// Rereads the devices from disk, keeping the current ones if the stored
+    /// data is invalid.
+    fn reload(&mut self) -> Result<ReloadSummary, StorageError> {
+        Ok(ReloadSummary::default())
     }
+}
 
-    pub fn get(&self, id: &str) -> Option<Device> {
-        self.0.read().get(id)
-    }
+/// Opens the backend of the given kind at the path configured for it.
+pub fn open_backend(
+    kind: StorageKind,
+    config: &StorageConfig,
+) -> Result<Box<dyn StorageBackend>, StorageError> {
+    let path = config.path(kind);
+    Ok(match kind {
+        StorageKind::Json => Box::new(JsonStorage::open(path, config.backups)?),
+        StorageKind::Sqlite => Box::new(SqliteStorage::open(path)?),
+    })
+}
 
-    pub fn get_all(&self) -> Vec<Device> {
-        self.0.read().get_all()
+fn warn_duplicate_macs(devices: &[Device]) {
+    let mut seen = HashMap::new();
+    for device in devices {
+        for mac in device.macs() {
+            if let Some(first) = seen.insert(mac, &device.name) {
+                warn!(
+                    mac = %mac,
+                    first = %first,
+                    duplicate = %device.name,
+                    "Stored devices share a MAC address"
+                );
+            }
+        }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::TempDir;
+/// Advisory lock on the configured store, held by a running server so the
+/// CLI does not write the store behind its back. The OS releases it when the
+/// process exits, however it exits.
+#[derive(Debug)]
+pub struct StorageLock {
+    _file: File,
+}
 
-    /// Helper to create a test device with a given name
-    fn create_test_device(name: &str) -> Device {
-        Device {
-            id: nanoid::nanoid!(10),
-            name: name.to_string(),
-            mac_address: "AA:BB:CC:DD:EE:FF".to_string(),
-            ip_address: Some("192.168.1.100".to_string()),
-            port: 9,
-            description: Some("Test device".to_string()),
-            created_at: time::OffsetDateTime::now_utc(),
+impl StorageLock {
+    /// Locks the `[storage] backend` store through a `.lock` file next to
+    /// it, or returns `None` while another process holds the lock.
+    pub fn acquire(config: &StorageConfig) -> Result<Option<Self>, StorageError> {
+        let path = format!("{}.lock", config.path(config.backend));
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(Self { _file: file })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(err)) => Err(err.into()),
         }
     }
+}
 
-    /// Helper to create a temp file path
-    fn temp_storage_path(dir: &TempDir) -> String {
-        dir.path()
-            .join("devices.json")
-            .to_string_lossy()
-            .to_string()
-    }
+/// Devices shared by all request handlers.
+///
+/// Reads are served from memory and never wait on disk. Changes are handed to
+/// a writer task that owns the backend; each call returns once its change is
+/// durably stored, and only then becomes visible to readers.
+#[derive(Debug, Clone)]
+pub struct SharedStorage {
+    index: Arc<RwLock<Arc<DeviceIndex>>>,
+    commands: mpsc::UnboundedSender<writer::Command>,
+    watched_path: Option<Arc<str>>,
+}
 
-    #[test]
-    fn new_storage_is_empty() {
-        let storage = DeviceStorage::new("/tmp/test.json");
-        assert!(storage.devices.is_empty());
-        assert_eq!(storage.get_all().len(), 0);
+impl SharedStorage {
+    /// Starts the writer task for `backend`. Changes arriving within
+    /// `write_delay` of each other are saved in a single write.
+    pub fn new(backend: Box<dyn StorageBackend>, write_delay: Duration) -> Self {
+        let index = Arc::new(RwLock::new(Arc::new(DeviceIndex::new(backend.get_all()))));
+        let watched_path = backend.watched_path().map(Arc::from);
+        let (commands, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(writer::run(
+            backend,
+            receiver,
+            Arc::clone(&index),
+            write_delay,
+        ));
+        Self {
+            index,
+            commands,
+            watched_path,
+        }
     }
 
-    #[test]
-    fn add_device_increases_count() {
-        let dir = TempDir::new().unwrap();
-        let path = temp_storage_path(&dir);
-        let mut storage = DeviceStorage::new(&path);
-
-        let device = create_test_device("Device 1");
-        storage.add(device).unwrap();
-
-        assert_eq!(storage.devices.len(), 1);
+    /// Opens the backend selected by `[storage] backend`.
+    pub fn open(config: &StorageConfig) -> Result<Self, StorageError> {
+        let backend = open_backend(config.backend, config)?;
+        Ok(Self::new(
+            backend,
+            Duration::from_millis(config.write_delay_ms),
+        ))
     }
 
-    #[test]
-    fn get_returns_added_device() {
-        let dir = TempDir::new().unwrap();
-        let path = temp_storage_path(&dir);
-        let mut storage = DeviceStorage::new(&path);
-
-        let device = create_test_device("Device 1");
-        storage.add(device.clone()).unwrap();
-
-        let retrieved = storage.get(&device.id);
-        assert!(retrieved.is_some());
-        assert_eq!(retrieved.unwrap(), device);
+    pub async fn add(&self, device: Device) -> Result<(), StorageError> {
+        self.apply(vec![Mutation::Add(device)]).await
     }
 
-    #[test]
-    fn get_returns_none_for_unknown_id() {
-        let storage = DeviceStorage::new("/tmp/test.json");
-        let result = storage.get("nonexistent-id");
-        assert!(result.is_none());
+    pub async fn remove(&self, id: &str) -> Result<Device, StorageError> {
+        let device = self
+            .get(id)
+            .ok_or_else(|| StorageError::NotFound(id.to_string()))?;
+        self.apply(vec![Mutation::Remove(id.to_string())]).await?;
+        Ok(device)
     }
 
-    #[test]
-    fn get_all_returns_all_devices() {
-        let dir = TempDir::new().unwrap();
-        let path = temp_storage_path(&dir);
-        let mut storage = DeviceStorage::new(&path);
-
-        storage.add(create_test_device("Device 1")).unwrap();
-        storage.add(create_test_device("Device 2")).unwrap();
-        storage.add(create_test_device("Device 3")).unwrap();
-
-        let all = storage.get_all();
-        assert_eq!(all.len(), 3);
+    pub async fn update(&self, id: &str, device: Device) -> Result<Device, StorageError> {
+        self.apply(vec![Mutation::Update {
+            id: id.to_string(),
+            device,
+        }])
+        .await?;
+        let snapshot = self.snapshot();
+        snapshot
+            .get(id)
+            .or_else(|| snapshot.trashed(id))
+            .cloned()
+            .ok_or_else(|| StorageError::NotFound(id.to_string()))
     }
 
-    #[test]
-    fn remove_existing_device_returns_it() {
-        let dir = TempDir::new().unwrap();
-        let path = temp_storage_path(&dir);
-        let mut storage = DeviceStorage::new(&path);
-
-        let device = create_test_device("Device 1");
-        storage.add(device.clone()).unwrap();
-
-        let removed = storage.remove(&device.id).unwrap();
-        assert_eq!(removed, device);
-        assert_eq!(storage.devices.len(), 0);
+    pub async fn reorder(&self, ids: &[String]) -> Result<(), StorageError> {
+        self.apply(vec![Mutation::Reorder(ids.to_vec())]).await
     }
 
-    #[test]
-    fn remove_nonexistent_device_returns_error() {
-        let dir = TempDir::new().unwrap();
-        let path = temp_storage_path(&dir);
-        let mut storage = DeviceStorage::new(&path);
-
-        let result = storage.remove("nonexistent-id");
-        assert!(matches!(result, Err(StorageError::NotFound(_))));
+    /// Commits the mutations all-or-nothing, see [`StorageBackend::apply`].
+    pub async fn apply(&self, mutations: Vec<Mutation>) -> Result<(), StorageError> {
+        let (done, acknowledged) = oneshot::channel();
+        self.send(writer::Command::Apply { mutations, done })?;
+        acknowledged.await.map_err(|_| writer_stopped())?
     }
 
-    #[test]
-    fn remove_nonexistent_device_maintains_others() {
-        let dir = TempDir::new().unwrap();
-        let path = temp_storage_path(&dir);
-        let mut storage = DeviceStorage::new(&path);
-
-        let device = create_test_device("Device 1");
-        storage.add(device).unwrap();
-
-        let _ = storage.remove("nonexistent-id");
-        assert!(storage.devices.len() == 1);
+    pub(super) async fn reload_backend(&self) -> Result<ReloadSummary, StorageError> {
+        let (done, acknowledged) = oneshot::channel();
+        self.send(writer::Command::Reload { done })?;
+        acknowledged.await.map_err(|_| writer_stopped())?
     }
 
-    #[test]
-    fn update_maintains_old_id() {
-        let dir = TempDir::new().unwrap();
-        let path = temp_storage_path(&dir);
-        let mut storage = DeviceStorage::new(&path);
-
-        let device = create_test_device("Device 1");
-        storage.add(device.clone()).unwrap();
-
-        let mut updated_device = create_test_device("Updated Name");
-
-        let result = storage.update(&device.id, updated_device.clone()).unwrap();
-
-        updated_device.id = device.id.clone();
-        assert_eq!(result, updated_device);
+    fn send(&self, command: writer::Command) -> Result<(), StorageError> {
+        self.commands.send(command).map_err(|_| writer_stopped())
     }
 
-    #[test]
-    fn update_existing_device_succeeds() {
-        let dir = TempDir::new().unwrap();
-        let path = temp_storage_path(&dir);
-        let mut storage = DeviceStorage::new(&path);
-
-        let device = create_test_device("Original Name");
-        storage.add(device.clone()).unwrap();
-
-        let mut updated_device = create_test_device("Updated Name");
-        updated_device.id = device.id.clone();
-
-        let result = storage.update(&device.id, updated_device.clone()).unwrap();
-        assert_eq!(result, updated_device);
+    /// The current devices and their lookups. Holding on to the snapshot
+    /// keeps it consistent while later changes are published.
+    pub fn snapshot(&self) -> Arc<DeviceIndex> {
+        Arc::clone(&self.index.read())
     }
 
-    #[test]
-    fn update_nonexistent_device_returns_error() {
-        let dir = TempDir::new().unwrap();
-        let path = temp_storage_path(&dir);
-        let mut storage = DeviceStorage::new(&path);
-
-        let device = create_test_device("Test");
-        let result = storage.update("nonexistent-id", device);
-        assert!(matches!(result, Err(StorageError::NotFound(_))));
+    pub fn get(&self, id: &str) -> Option<Device> {
+        self.snapshot().get(id).cloned()
     }
 
-    #[test]
-    fn add_all_adds_multiple_devices() {
-        let dir = TempDir::new().unwrap();
-        let path = temp_storage_path(&dir);
-        let mut storage = DeviceStorage::new(&path);
-
-        let devices = vec![
-            create_test_device("Device 1"),
-            create_test_device("Device 2"),
-            create_test_device("Device 3"),
-        ];
-
-        storage.add_all(devices).unwrap();
-        assert_eq!(storage.devices.len(), 3);
+    pub fn get_all(&self) -> Arc<[Device]> {
+        self.snapshot().all()
     }
+}
 
-    #[test]
-    fn save_creates_file() {
-        let dir = TempDir::new().unwrap();
-        let path = temp_storage_path(&dir);
-        let mut storage = DeviceStorage::new(&path);
-
-        storage.add(create_test_device("Test")).unwrap();
-
-        assert!(std::path::Path::new(&path).exists());
-    }
+fn writer_stopped() -> StorageError {
+    std::io::Error::new(std::io::ErrorKind::BrokenPipe, "storage writer has stopped").into()
+}
 
-    #[test]
-    fn load_restores_saved_devices() {
-        let dir = TempDir::new().unwrap();
-        let path = temp_storage_path(&dir);
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use tempfile::TempDir;
 
-        // Save some devices
-        {
-            let mut storage = DeviceStorage::new(&path);
-            storage.add(create_test_device("Device 1")).unwrap();
-            storage.add(create_test_device("Device 2")).unwrap();
+    /// Single-step shorthands for driving a backend directly.
+    pub(super) trait BackendExt: StorageBackend {
+        fn get(&self, id: &str) -> Option<Device> {
+            self.get_all().into_iter().find(|d| d.id == id)
         }
 
-        // Load them back
-        let loaded = DeviceStorage::load(&path).unwrap();
-        assert_eq!(loaded.devices.len(), 2);
-    }
-
-    #[test]
-    fn load_missing_file_returns_empty_storage() {
-        let dir = TempDir::new().unwrap();
-        let path = dir
-            .path()
-            .join("nonexistent.json")
-            .to_string_lossy()
-            .to_string();
-
-        let storage = DeviceStorage::load(&path).unwrap();
-        assert!(storage.devices.is_empty());
-    }
-
-    #[test]
-    fn load_empty_file_returns_empty_storage() {
-        let dir = TempDir::new().unwrap();
-        let path = temp_storage_path(&dir);
-
-        // Create empty file
-        std::fs::write(&path, "").unwrap();
+        fn add(&mut self, device: Device) -> Result<(), StorageError> {
+            self.apply(vec![Mutation::Add(device)])
+        }
 
-        let storage = DeviceStorage::load(&path).unwrap();
-        assert!(storage.devices.is_empty());
-    }
+        fn add_all(&mut self, devices: Vec<Device>) -> Result<(), StorageError> {
+            self.apply(devices.into_iter().map(Mutation::Add).collect())
+        }
 
-    #[test]
-    fn load_whitespace_only_file_returns_empty_storage() {
-        let dir = TempDir::new().unwrap();
-        let path = temp_storage_path(&dir);
+        fn remove(&mut self, id: &str) -> Result<Device, StorageError> {
+            let device = self
+                .get(id)
+                .ok_or_else(|| StorageError::NotFound(id.to_string()))?;
+            self.apply(vec![Mutation::Remove(id.to_string())])?;
+            Ok(device)
+        }
 
-        // Create file with only whitespace
-        std::fs::write(&path, "   \n\t  ").unwrap();
+        fn update(&mut self, id: &str, device: Device) -> Result<Device, StorageError> {
+            self.apply(vec![Mutation::Update {
+                id: id.to_string(),
+                device,
+            }])?;
+            self.get(id)
+                .ok_or_else(|| StorageError::NotFound(id.to_string()))
+        }
 
-        let storage = DeviceStorage::load(&path).unwrap();
-        assert!(storage.devices.is_empty());
+        fn reorder(&mut self, ids: &[String]) -> Result<(), StorageError> {
+            self.apply(vec![Mutation::Reorder(ids.to_vec())])
+        }
     }
 
-    #[test]
-    fn save_and_load_preserves_device_data() {
-        let dir = TempDir::new().unwrap();
-        let path = temp_storage_path(&dir);
+    impl<T: StorageBackend + ?Sized> BackendExt for T {}
 
-        let original_device = Device {
-            id: "test-id-123".to_string(),
-            name: "My Server".to_string(),
-            mac_address: "11:22:33:44:55:66".to_string(),
-            ip_address: Some("10.0.0.50".to_string()),
-            port: 7,
-            description: Some("Production server".to_string()),
+    /// Helper to create a test device with a given name and a MAC address
+    /// of its own
+    pub(crate) fn create_test_device(name: &str) -> Device {
+        static NEXT_MAC: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+        let [_, high, middle, low] = NEXT_MAC
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+            .to_be_bytes();
+        let mac = format!("AA:BB:CC:{high:02X}:{middle:02X}:{low:02X}");
+        Device {
+            id: nanoid::nanoid!(10),
+            name: name.to_string(),
+            interfaces: vec![crate::models::NetworkInterface::new(&mac).unwrap()],
+            ip_address: Some("192.168.1.100".to_string()),
+            port: 9,
+            description: Some("Test device".to_string()),
+            tags: Vec::new(),
+            metadata: std::collections::BTreeMap::new(),
+            sort_order: 0,
+            favorite: false,
+            revision: 1,
             created_at: time::OffsetDateTime::now_utc(),
-        };
-
-        // Save
-        {
-            let mut storage = DeviceStorage::new(&path);
-            storage.add(original_device).unwrap();
+            updated_at: None,
+            deleted_at: None,
         }
+    }
 
-        // Load and verify
-        let loaded = DeviceStorage::load(&path).unwrap();
-        let device = loaded.get("test-id-123").unwrap();
-
-        assert_eq!(device.name, "My Server");
-        assert_eq!(device.mac_address, "11:22:33:44:55:66");
-        assert_eq!(device.ip_address, Some("10.0.0.50".to_string()));
-        assert_eq!(device.port, 7);
-        assert_eq!(device.description, Some("Production server".to_string()));
+    /// Helper to create a temp file path
+    pub(super) fn temp_storage_path(dir: &TempDir) -> String {
+        dir.path()
+            .join("devices.json")
+            .to_string_lossy()
+            .to_string()
     }
 
-    #[test]
-    fn shared_storage_load_creates_instance() {
+    #[tokio::test]
+    async fn shared_storage_load_creates_instance() {
         let dir = TempDir::new().unwrap();
         let path = temp_storage_path(&dir);
 
-        let storage = SharedStorage::load(&path);
+        let storage = JsonStorage::open(&path, 0)
+            .map(|json| SharedStorage::new(Box::new(json), Duration::ZERO));
         assert!(storage.is_ok());
     }
 
-    #[test]
-    fn shared_storage_add_and_get() {
+    #[tokio::test]
+    async fn shared_storage_add_and_get() {
         let dir = TempDir::new().unwrap();
         let path = temp_storage_path(&dir);
 
-        let storage = SharedStorage::load(&path).unwrap();
+        let storage = SharedStorage::new(
+            Box::new(JsonStorage::open(&path, 0).unwrap()),
+            Duration::ZERO,
+        );
         let device = create_test_device("Shared Test");
 
-        storage.add(device.clone()).unwrap();
+        storage.add(device.clone()).await.unwrap();
 
         let retrieved = storage.get(&device.id);
         assert!(retrieved.is_some());
         assert_eq!(retrieved.unwrap(), device);
     }
 
-    #[test]
-    fn shared_storage_concurrent_reads() {
+    #[tokio::test]
+    async fn shared_storage_concurrent_reads() {
         let dir = TempDir::new().unwrap();
         let path = temp_storage_path(&dir);
 
-        let storage = SharedStorage::load(&path).unwrap();
+        let storage = SharedStorage::new(
+            Box::new(JsonStorage::open(&path, 0).unwrap()),
+            Duration::ZERO,
+        );
         let device = create_test_device("Concurrent Test");
-        storage.add(device.clone()).unwrap();
+        storage.add(device.clone()).await.unwrap();
 
         // Simulate concurrent reads by getting multiple references
         let all1 = storage.get_all();
@@ -465,50 +403,146 @@ mod tests {
         assert!(single.is_some());
     }
 
-    #[test]
-    fn shared_storage_remove() {
+    #[tokio::test]
+    async fn shared_storage_remove() {
         let dir = TempDir::new().unwrap();
         let path = temp_storage_path(&dir);
 
-        let storage = SharedStorage::load(&path).unwrap();
+        let storage = SharedStorage::new(
+            Box::new(JsonStorage::open(&path, 0).unwrap()),
+            Duration::ZERO,
+        );
         let device = create_test_device("To Remove");
-        storage.add(device.clone()).unwrap();
+        storage.add(device.clone()).await.unwrap();
 
-        let removed = storage.remove(&device.id).unwrap();
+        let removed = storage.remove(&device.id).await.unwrap();
         assert_eq!(removed, device);
         assert!(storage.get(&device.id).is_none());
     }
 
-    #[test]
-    fn shared_storage_update() {
+    #[tokio::test]
+    async fn shared_storage_update() {
         let dir = TempDir::new().unwrap();
         let path = temp_storage_path(&dir);
 
-        let storage = SharedStorage::load(&path).unwrap();
+        let storage = SharedStorage::new(
+            Box::new(JsonStorage::open(&path, 0).unwrap()),
+            Duration::ZERO,
+        );
         let device = create_test_device("Original");
-        storage.add(device.clone()).unwrap();
+        storage.add(device.clone()).await.unwrap();
 
         let mut updated = create_test_device("Updated");
 
-        storage.update(&device.id, updated.clone()).unwrap();
+        storage.update(&device.id, updated.clone()).await.unwrap();
 
         updated.id = device.id.clone();
         let retrieved = storage.get(&device.id).unwrap();
+        updated.revision = 2;
+        updated.updated_at = retrieved.updated_at;
         assert_eq!(retrieved, updated);
     }
 
+    #[tokio::test]
+    async fn shared_storage_opens_configured_backend() {
+        let dir = TempDir::new().unwrap();
+        let config = StorageConfig {
+            backend: StorageKind::Sqlite,
+            file_path: temp_storage_path(&dir),
+            sqlite_path: dir.path().join("jumpers.db").to_string_lossy().to_string(),
+            backups: 0,
+            watch: false,
+            write_delay_ms: 0,
+            trash_retention_days: 30,
+        };
+
+        let storage = SharedStorage::open(&config).unwrap();
+        storage.add(create_test_device("In SQLite")).await.unwrap();
+
+        assert!(dir.path().join("jumpers.db").exists());
+        assert!(!dir.path().join("devices.json").exists());
+        let reopened = SharedStorage::open(&config).unwrap();
+        assert_eq!(reopened.get_all().len(), 1);
+    }
+
     #[test]
-    fn shared_storage_add_all() {
+    fn storage_lock_is_held_until_dropped() {
+        let dir = TempDir::new().unwrap();
+        let config = StorageConfig {
+            file_path: temp_storage_path(&dir),
+            ..StorageConfig::default()
+        };
+
+        let lock = StorageLock::acquire(&config).unwrap();
+        assert!(lock.is_some());
+        assert!(StorageLock::acquire(&config).unwrap().is_none());
+
+        drop(lock);
+        assert!(StorageLock::acquire(&config).unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn shared_storage_apply() {
         let dir = TempDir::new().unwrap();
         let path = temp_storage_path(&dir);
 
-        let storage = SharedStorage::load(&path).unwrap();
+        let storage = SharedStorage::new(
+            Box::new(JsonStorage::open(&path, 0).unwrap()),
+            Duration::ZERO,
+        );
         let devices = vec![
             create_test_device("Device 1"),
             create_test_device("Device 2"),
         ];
 
-        storage.add_all(devices).unwrap();
+        storage
+            .apply(devices.into_iter().map(Mutation::Add).collect())
+            .await
+            .unwrap();
         assert_eq!(storage.get_all().len(), 2);
     }
+
+    #[tokio::test]
+    async fn shared_storage_rejects_taken_ids_and_macs() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+
+        let storage = SharedStorage::new(
+            Box::new(JsonStorage::open(&path, 0).unwrap()),
+            Duration::ZERO,
+        );
+        let device = create_test_device("First");
+        let mut same_mac = create_test_device("Second");
+        same_mac.interfaces.clone_from(&device.interfaces);
+        let (first, second) =
+            tokio::join!(storage.add(device.clone()), storage.add(same_mac.clone()));
+        assert!(first.is_ok());
+        assert!(matches!(second, Err(StorageError::DuplicateMac { .. })));
+
+        let same_id = Device {
+            id: device.id.clone(),
+            ..create_test_device("Third")
+        };
+        let result = storage
+            .apply(vec![
+                Mutation::Add(create_test_device("Fourth")),
+                Mutation::Add(same_id),
+            ])
+            .await;
+        assert!(matches!(result, Err(StorageError::DuplicateId(_))));
+        assert_eq!(storage.get_all().len(), 1);
+
+        let trashed = Device {
+            deleted_at: Some(time::OffsetDateTime::now_utc()),
+            ..device.clone()
+        };
+        storage.update(&device.id, trashed).await.unwrap();
+        storage.add(same_mac).await.unwrap();
+        let restored = Device {
+            deleted_at: None,
+            ..storage.snapshot().trashed(&device.id).unwrap().clone()
+        };
+        let result = storage.update(&device.id, restored).await;
+        assert!(matches!(result, Err(StorageError::DuplicateMac { .. })));
+    }
 }
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::models::{Device, MacAddress};
+
+/// Immutable view of every device in storage order, with lookups by id and
+/// by MAC address. Readers share it through an `Arc`, so listing devices
+/// never copies them. Devices in the trash are kept apart and are not found
+/// by the lookups.
+#[derive(Debug, Default)]
+pub struct DeviceIndex {
+    devices: Arc<[Device]>,
+    trash: Arc<[Device]>,
+    by_id: HashMap<String, usize>,
+    by_mac: HashMap<MacAddress, usize>,
+}
+
+impl DeviceIndex {
+    pub fn new(devices: Vec<Device>) -> Self {
+        let (trash, devices): (Vec<Device>, Vec<Device>) =
+            devices.into_iter().partition(Device::is_deleted);
+        let mut by_id = HashMap::with_capacity(devices.len());
+        let mut by_mac = HashMap::with_capacity(devices.len());
+        for (position, device) in devices.iter().enumerate() {
+            by_id.insert(device.id.clone(), position);
+            for mac in device.macs() {
+                // Keep the first owner, matching the order duplicates are reported in.
+                by_mac.entry(mac).or_insert(position);
+            }
+        }
+
+        Self {
+            devices: devices.into(),
+            trash: trash.into(),
+            by_id,
+            by_mac,
+        }
+    }
+
+    /// All devices in storage order, without those in the trash.
+    pub fn all(&self) -> Arc<[Device]> {
+        Arc::clone(&self.devices)
+    }
+
+    /// Devices in the trash, in storage order.
+    pub fn trash(&self) -> Arc<[Device]> {
+        Arc::clone(&self.trash)
+    }
+
+    /// Every stored device, those in the trash last.
+    pub fn stored(&self) -> impl Iterator<Item = &Device> {
+        self.devices.iter().chain(self.trash.iter())
+    }
+
+    pub fn trashed(&self, id: &str) -> Option<&Device> {
+        self.trash.iter().find(|device| device.id == id)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Device> {
+        self.by_id.get(id).map(|&position| &self.devices[position])
+    }
+
+    /// The device one of whose interfaces has `mac`.
+    pub fn by_mac(&self, mac: MacAddress) -> Option<&Device> {
+        self.by_mac
+            .get(&mac)
+            .map(|&position| &self.devices[position])
+    }
+
+    pub fn len(&self) -> usize {
+        self.devices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::NetworkInterface;
+    use crate::storage::tests::create_test_device;
+
+    #[test]
+    fn looks_up_devices_by_id_and_mac() {
+        let mut second = create_test_device("Second");
+        second.interfaces = vec![
+            NetworkInterface::new("11:22:33:44:55:66").unwrap(),
+            NetworkInterface::new("11:22:33:44:55:77").unwrap(),
+        ];
+        let first = create_test_device("First");
+        let index = DeviceIndex::new(vec![first.clone(), second.clone()]);
+
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.get(&second.id), Some(&second));
+        assert_eq!(index.get("missing"), None);
+        let mac = "11:22:33:44:55:77".parse().unwrap();
+        assert_eq!(index.by_mac(mac).map(|d| &d.name), Some(&second.name));
+        assert_eq!(index.all()[0], first);
+    }
+
+    #[test]
+    fn shared_macs_resolve_to_first_owner() {
+        let first = create_test_device("First");
+        let mut duplicate = create_test_device("Duplicate");
+        duplicate.interfaces.clone_from(&first.interfaces);
+        let mac = first.interfaces[0].mac_address;
+        let index = DeviceIndex::new(vec![first, duplicate]);
+
+        assert_eq!(index.by_mac(mac).unwrap().name, "First");
+    }
+
+    #[test]
+    fn trashed_devices_are_kept_out_of_lookups() {
+        let mut trashed = create_test_device("Trashed");
+        trashed.deleted_at = Some(time::OffsetDateTime::now_utc());
+        let mut live = create_test_device("Live");
+        live.interfaces.clone_from(&trashed.interfaces);
+        let index = DeviceIndex::new(vec![trashed.clone(), live.clone()]);
+
+        assert_eq!(index.len(), 1);
+        assert_eq!(index.get(&trashed.id), None);
+        assert_eq!(index.trashed(&trashed.id), Some(&trashed));
+        assert_eq!(index.by_mac(live.interfaces[0].mac_address), Some(&live));
+        assert_eq!(&*index.trash(), std::slice::from_ref(&trashed));
+    }
+}
@@ -0,0 +1,1001 @@
+mod migrations;
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::{debug, info, instrument, warn};
+
+use self::migrations::CURRENT_VERSION;
+use super::watch::reconcile;
+use super::{
+    DeviceTable, Mutation, ReloadSummary, StorageBackend, StorageError, warn_duplicate_macs,
+};
+use crate::models::{Device, FieldErrors, ValidationError};
+
+/// Keeps every device in one pretty-printed JSON file, rewritten on each change.
+///
+/// Writes go to a temporary file that is synced and renamed over the live one,
+/// so a crash leaves either the old or the new inventory, never a truncated
+/// file. The previous `backups` versions are kept as `devices.json.1` (newest)
+/// through `devices.json.N`.
+#[derive(Debug, Clone)]
+pub struct JsonStorage {
+    path: String,
+    backups: usize,
+    devices: DeviceTable,
+    /// The file as this storage last wrote it, so a reload triggered by that
+    /// write can be skipped.
+    written: Option<FileStamp>,
+}
+
+/// Size and modification time of a file, telling whether it was replaced
+/// since it was stamped without reading it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    len: u64,
+    modified: SystemTime,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self {
+            len: metadata.len(),
+            modified: metadata.modified().ok()?,
+        })
+    }
+}
+
+impl JsonStorage {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            backups: 0,
+            devices: DeviceTable::default(),
+            written: None,
+        }
+    }
+
+    /// Loads the file, keeping `backups` rolling copies from now on. A file
+    /// that no longer parses is left alone: the error names its newest
+    /// readable backup, which [`JsonStorage::recover`] puts back in its place.
+    #[instrument(skip_all, fields(path = %path))]
+    pub fn open(path: &str, backups: usize) -> Result<Self, StorageError> {
+        if !Path::new(path).exists() {
+            info!("Storage file not found, starting fresh");
+            return Ok(Self {
+                backups,
+                ..Self::new(path)
+            });
+        }
+
+        let content = fs::read_to_string(path)?;
+        let (version, mut devices) = match parse_devices(&content) {
+            Ok(parsed) => parsed,
+            Err(StorageError::Parse(error)) => {
+                return Err(match newest_valid_backup(path, backups) {
+                    Some((backup, ..)) => StorageError::Corrupt { error, backup },
+                    None => StorageError::Parse(error),
+                });
+            }
+            Err(err) => return Err(err),
+        };
+        // Stable, so files written before `sort_order` existed keep their order.
+        devices.sort_by_key(|device| device.sort_order);
+
+        info!(device_count = devices.len(), version, "Storage loaded");
+        let mut storage = Self {
+            path: path.to_string(),
+            backups,
+            devices: devices.into(),
+            written: FileStamp::of(Path::new(path)),
+        };
+
+        if version < CURRENT_VERSION {
+            fs::copy(path, format!("{path}.v{version}"))?;
+            storage.save()?;
+            info!(
+                from = version,
+                to = CURRENT_VERSION,
+                "Migrated storage file, previous layout kept as backup"
+            );
+        } else if !is_canonical(&content, &storage.devices) {
+            storage.save()?;
+            info!("Rewrote storage file with canonical values, such as MAC address spellings");
+        }
+        warn_duplicate_macs(&storage.devices);
+
+        Ok(storage)
+    }
+
+    /// Replaces a file that no longer parses with its newest readable backup
+    /// and returns that backup, or `None` when the file is fine. The damaged
+    /// file is kept as `devices.json.corrupt.<timestamp>`, never overwriting
+    /// an earlier one.
+    #[instrument(skip_all, fields(path = %path))]
+    pub fn recover(path: &str, backups: usize) -> Result<Option<PathBuf>, StorageError> {
+        let damaged = fs::read(path)?;
+        let error = match parse_devices(&String::from_utf8_lossy(&damaged)) {
+            Ok(_) => return Ok(None),
+            Err(StorageError::Parse(error)) => error,
+            Err(err) => return Err(err),
+        };
+        let Some((backup, content, _)) = newest_valid_backup(path, backups) else {
+            return Err(StorageError::Parse(error));
+        };
+
+        let format = time::format_description::parse(CORRUPT_STAMP_FORMAT)
+            .expect("valid corrupt file timestamp format");
+        let stamp = OffsetDateTime::now_utc()
+            .format(&format)
+            .expect("UTC timestamps format");
+        let mut kept = File::create_new(format!("{path}.corrupt.{stamp}"))?;
+        kept.write_all(&damaged)?;
+        kept.sync_all()?;
+        write_atomically(Path::new(path), &content)?;
+        warn!(backup = %backup.display(), "Replaced unreadable storage file with backup");
+        Ok(Some(backup))
+    }
+
+    pub fn save(&mut self) -> Result<(), StorageError> {
+        let devices = std::mem::take(&mut self.devices);
+        let result = self.write(&devices);
+        self.devices = devices;
+        result
+    }
+
+    #[instrument(skip_all, fields(path = %self.path))]
+    fn write(&mut self, devices: &[Device]) -> Result<(), StorageError> {
+        let content = encode_devices(devices)?;
+        let path = Path::new(&self.path);
+
+        if self.backups > 0 && path.exists() {
+            rotate_backups(&self.path, self.backups)?;
+        }
+        write_atomically(path, &content)?;
+        self.written = FileStamp::of(path);
+        debug!(device_count = devices.len(), "Storage saved");
+        Ok(())
+    }
+}
+
+/// On-disk layout of the current [`CURRENT_VERSION`].
+#[derive(Serialize, Deserialize)]
+struct DeviceFile<'a> {
+    version: u32,
+    devices: Cow<'a, [Device]>,
+}
+
+/// Reads a file of any supported version, returning that version along with
+/// the devices upgraded to the current layout.
+fn parse_devices(content: &str) -> Result<(u32, Vec<Device>), StorageError> {
+    if content.trim().is_empty() {
+        return Ok((CURRENT_VERSION, Vec::new()));
+    }
+    let (version, value) = migrations::migrate(serde_json::from_str(content)?)?;
+    let file: DeviceFile = serde_json::from_value(value)?;
+    Ok((version, file.devices.into_owned()))
+}
+
+/// Whether `content` holds the devices as they serialize now, rather than
+/// e.g. MAC addresses spelled `aa-bb-cc-dd-ee-ff` by another program.
+/// Formatting is not compared.
+fn is_canonical(content: &str, devices: &[Device]) -> bool {
+    let Ok(raw) = serde_json::from_str::<serde_json::Value>(content) else {
+        return true;
+    };
+    serde_json::to_value(DeviceFile {
+        version: CURRENT_VERSION,
+        devices: Cow::Borrowed(devices),
+    })
+    .is_ok_and(|canonical| canonical == raw)
+}
+
+/// Serializes devices in the current layout of the device file.
+pub fn encode_devices(devices: &[Device]) -> Result<String, StorageError> {
+    Ok(serde_json::to_string_pretty(&DeviceFile {
+        version: CURRENT_VERSION,
+        devices: Cow::Borrowed(devices),
+    })?)
+}
+
+/// Reads devices in any supported layout of the device file and checks them,
+/// for content that did not come from this server's own storage.
+pub fn decode_devices(content: &str) -> Result<Vec<Device>, StorageError> {
+    let (_, mut devices) = parse_devices(content)?;
+    validate_devices(&devices).map_err(StorageError::Invalid)?;
+    devices.sort_by_key(|device| device.sort_order);
+    Ok(devices)
+}
+
+/// Reads a device file of any supported version that is already parsed as
+/// JSON. The devices are not checked; imports validate each one themselves.
+pub fn parse_device_file(value: serde_json::Value) -> Result<Vec<Device>, StorageError> {
+    let (_, value) = migrations::migrate(value)?;
+    let file: DeviceFile = serde_json::from_value(value)?;
+    Ok(file.devices.into_owned())
+}
+
+/// Suffix of a damaged file kept by [`JsonStorage::recover`].
+const CORRUPT_STAMP_FORMAT: &str = "[year][month][day]T[hour][minute][second][subsecond digits:3]Z";
+
+fn backup_path(path: &str, index: usize) -> PathBuf {
+    PathBuf::from(format!("{path}.{index}"))
+}
+
+/// Shifts `path.1` … `path.{count - 1}` up by one and copies the live file to
+/// `path.1`; the oldest backup falls off the end.
+fn rotate_backups(path: &str, count: usize) -> Result<(), StorageError> {
+    for index in (1..count).rev() {
+        let from = backup_path(path, index);
+        if from.exists() {
+            fs::rename(&from, backup_path(path, index + 1))?;
+        }
+    }
+    fs::copy(path, backup_path(path, 1))?;
+    Ok(())
+}
+
+fn newest_valid_backup(path: &str, count: usize) -> Option<(PathBuf, String, (u32, Vec<Device>))> {
+    (1..=count).find_map(|index| {
+        let backup = backup_path(path, index);
+        let content = fs::read_to_string(&backup).ok()?;
+        let devices = parse_devices(&content).ok()?;
+        Some((backup, content, devices))
+    })
+}
+
+/// Writes `content` to a temporary file in the same directory, syncs it and
+/// renames it over `path`. The rename is atomic on the same filesystem.
+fn write_atomically(path: &Path, content: &str) -> Result<(), StorageError> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let file_name = path
+        .file_name()
+        .map_or_else(|| "devices.json".into(), |name| name.to_string_lossy());
+    let temp_path = directory.join(format!(".{file_name}.tmp"));
+
+    let mut file = File::create(&temp_path)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+
+    if let Err(err) = fs::rename(&temp_path, path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(err.into());
+    }
+    // Persist the rename itself; not every platform can sync a directory.
+    if let Ok(directory) = File::open(directory) {
+        let _ = directory.sync_all();
+    }
+    Ok(())
+}
+
+impl StorageBackend for JsonStorage {
+    #[instrument(skip_all, fields(count = mutations.len()))]
+    fn apply(&mut self, mutations: Vec<Mutation>) -> Result<(), StorageError> {
+        let changes = self.devices.apply(mutations)?;
+        if let Err(err) = self.save() {
+            self.devices.undo(changes);
+            return Err(err);
+        }
+        debug!("Storage changes applied");
+        Ok(())
+    }
+
+    fn get_all(&self) -> Vec<Device> {
+        self.devices.to_vec()
+    }
+
+    fn watched_path(&self) -> Option<&str> {
+        Some(&self.path)
+    }
+
+    /// Skips the file while it is the one this storage last wrote: the
+    /// watcher also reports the server's own saves.
+    #[instrument(skip_all, fields(path = %self.path))]
+    fn reload(&mut self) -> Result<ReloadSummary, StorageError> {
+        let stamp = FileStamp::of(Path::new(&self.path));
+        if stamp.is_some() && stamp == self.written {
+            debug!("Storage file unchanged since it was saved, not reloading");
+            return Ok(ReloadSummary::default());
+        }
+        let devices = decode_devices(&fs::read_to_string(&self.path)?)?;
+        let (devices, summary) = reconcile(&self.devices, devices);
+        self.written = stamp;
+        if !summary.is_empty() {
+            warn_duplicate_macs(&devices);
+            self.devices = devices.into();
+        }
+        Ok(summary)
+    }
+}
+
+/// Checks devices that were written by another program, reporting problems
+/// under `[index].field` like an import does.
+fn validate_devices(devices: &[Device]) -> Result<(), ValidationError> {
+    let mut errors = FieldErrors::default();
+    let mut ids = HashSet::new();
+    for (index, device) in devices.iter().enumerate() {
+        let prefix = format!("[{index}].");
+        if !ids.insert(device.id.as_str()) {
+            errors.add(format!("{prefix}id"), "Duplicate device id");
+        }
+        errors.merge(&prefix, device.validate());
+    }
+    errors.into_result()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::{BackendExt, create_test_device, temp_storage_path};
+    use tempfile::TempDir;
+
+    #[test]
+    fn new_storage_is_empty() {
+        let storage = JsonStorage::new("/tmp/test.json");
+        assert!(storage.devices.is_empty());
+        assert_eq!(storage.get_all().len(), 0);
+    }
+
+    #[test]
+    fn add_device_increases_count() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::new(&path);
+
+        let device = create_test_device("Device 1");
+        storage.add(device).unwrap();
+
+        assert_eq!(storage.devices.len(), 1);
+    }
+
+    #[test]
+    fn get_returns_added_device() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::new(&path);
+
+        let device = create_test_device("Device 1");
+        storage.add(device.clone()).unwrap();
+
+        let retrieved = storage.get(&device.id);
+        assert!(retrieved.is_some());
+        assert_eq!(retrieved.unwrap(), device);
+    }
+
+    #[test]
+    fn get_returns_none_for_unknown_id() {
+        let storage = JsonStorage::new("/tmp/test.json");
+        let result = storage.get("nonexistent-id");
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn get_all_returns_all_devices() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::new(&path);
+
+        storage.add(create_test_device("Device 1")).unwrap();
+        storage.add(create_test_device("Device 2")).unwrap();
+        storage.add(create_test_device("Device 3")).unwrap();
+
+        let all = storage.get_all();
+        assert_eq!(all.len(), 3);
+    }
+
+    #[test]
+    fn remove_existing_device_returns_it() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::new(&path);
+
+        let device = create_test_device("Device 1");
+        storage.add(device.clone()).unwrap();
+
+        let removed = storage.remove(&device.id).unwrap();
+        assert_eq!(removed, device);
+        assert_eq!(storage.devices.len(), 0);
+    }
+
+    #[test]
+    fn remove_nonexistent_device_returns_error() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::new(&path);
+
+        let result = storage.remove("nonexistent-id");
+        assert!(matches!(result, Err(StorageError::NotFound(_))));
+    }
+
+    #[test]
+    fn remove_nonexistent_device_maintains_others() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::new(&path);
+
+        let device = create_test_device("Device 1");
+        storage.add(device).unwrap();
+
+        let _ = storage.remove("nonexistent-id");
+        assert!(storage.devices.len() == 1);
+    }
+
+    #[test]
+    fn update_maintains_old_id() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::new(&path);
+
+        let device = create_test_device("Device 1");
+        storage.add(device.clone()).unwrap();
+
+        let mut updated_device = create_test_device("Updated Name");
+
+        let result = storage.update(&device.id, updated_device.clone()).unwrap();
+
+        updated_device.id = device.id.clone();
+        updated_device.revision = 2;
+        updated_device.updated_at = result.updated_at;
+        assert_eq!(result, updated_device);
+    }
+
+    #[test]
+    fn update_existing_device_succeeds() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::new(&path);
+
+        let device = create_test_device("Original Name");
+        storage.add(device.clone()).unwrap();
+
+        let mut updated_device = create_test_device("Updated Name");
+        updated_device.id = device.id.clone();
+
+        let result = storage.update(&device.id, updated_device.clone()).unwrap();
+        updated_device.revision = 2;
+        updated_device.updated_at = result.updated_at;
+        assert_eq!(result, updated_device);
+        assert!(result.updated_at.is_some());
+    }
+
+    #[test]
+    fn update_from_stale_revision_is_rejected() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::new(&path);
+
+        let device = create_test_device("Original Name");
+        storage.add(device.clone()).unwrap();
+        storage.update(&device.id, device.clone()).unwrap();
+
+        let stale = Device {
+            name: "Lost Update".to_string(),
+            ..device.clone()
+        };
+        let result = storage.update(&device.id, stale);
+        assert!(matches!(
+            result,
+            Err(StorageError::Stale { current: 2, .. })
+        ));
+        assert_eq!(storage.get(&device.id).unwrap().name, "Original Name");
+    }
+
+    #[test]
+    fn update_nonexistent_device_returns_error() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::new(&path);
+
+        let device = create_test_device("Test");
+        let result = storage.update("nonexistent-id", device);
+        assert!(matches!(result, Err(StorageError::NotFound(_))));
+    }
+
+    #[test]
+    fn add_all_adds_multiple_devices() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::new(&path);
+
+        let devices = vec![
+            create_test_device("Device 1"),
+            create_test_device("Device 2"),
+            create_test_device("Device 3"),
+        ];
+
+        storage.add_all(devices).unwrap();
+        assert_eq!(storage.devices.len(), 3);
+    }
+
+    #[test]
+    fn save_creates_file() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::new(&path);
+
+        storage.add(create_test_device("Test")).unwrap();
+
+        assert!(std::path::Path::new(&path).exists());
+    }
+
+    #[test]
+    fn load_restores_saved_devices() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+
+        // Save some devices
+        {
+            let mut storage = JsonStorage::new(&path);
+            storage.add(create_test_device("Device 1")).unwrap();
+            storage.add(create_test_device("Device 2")).unwrap();
+        }
+
+        // Load them back
+        let loaded = JsonStorage::open(&path, 0).unwrap();
+        assert_eq!(loaded.devices.len(), 2);
+    }
+
+    #[test]
+    fn load_missing_file_returns_empty_storage() {
+        let dir = TempDir::new().unwrap();
+        let path = dir
+            .path()
+            .join("nonexistent.json")
+            .to_string_lossy()
+            .to_string();
+
+        let storage = JsonStorage::open(&path, 0).unwrap();
+        assert!(storage.devices.is_empty());
+    }
+
+    #[test]
+    fn load_empty_file_returns_empty_storage() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+
+        // Create empty file
+        std::fs::write(&path, "").unwrap();
+
+        let storage = JsonStorage::open(&path, 0).unwrap();
+        assert!(storage.devices.is_empty());
+    }
+
+    #[test]
+    fn load_whitespace_only_file_returns_empty_storage() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+
+        // Create file with only whitespace
+        std::fs::write(&path, "   \n\t  ").unwrap();
+
+        let storage = JsonStorage::open(&path, 0).unwrap();
+        assert!(storage.devices.is_empty());
+    }
+
+    #[test]
+    fn save_and_load_preserves_device_data() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+
+        let original_device = Device {
+            id: "test-id-123".to_string(),
+            name: "My Server".to_string(),
+            interfaces: vec![crate::models::NetworkInterface::new("11:22:33:44:55:66").unwrap()],
+            ip_address: Some("10.0.0.50".to_string()),
+            port: 7,
+            description: Some("Production server".to_string()),
+            tags: vec!["prod".to_string()],
+            metadata: [("rack".to_string(), "A1".to_string())].into(),
+            sort_order: 0,
+            favorite: true,
+            revision: 1,
+            created_at: time::OffsetDateTime::now_utc(),
+            updated_at: None,
+            deleted_at: None,
+        };
+
+        // Save
+        {
+            let mut storage = JsonStorage::new(&path);
+            storage.add(original_device).unwrap();
+        }
+
+        // Load and verify
+        let loaded = JsonStorage::open(&path, 0).unwrap();
+        let device = loaded.get("test-id-123").unwrap();
+
+        assert_eq!(device.name, "My Server");
+        assert_eq!(
+            device.interfaces[0].mac_address.to_string(),
+            "11:22:33:44:55:66"
+        );
+        assert_eq!(device.ip_address, Some("10.0.0.50".to_string()));
+        assert_eq!(device.port, 7);
+        assert_eq!(device.description, Some("Production server".to_string()));
+        assert_eq!(device.tags, vec!["prod"]);
+        assert_eq!(device.metadata.get("rack").map(String::as_str), Some("A1"));
+    }
+
+    #[test]
+    fn load_migrates_legacy_mac_addresses_to_interfaces() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        std::fs::write(
+            &path,
+            r#"[{"id":"legacy","name":"Legacy","mac_address":"aabb.ccdd.eeff","ip_address":null,"port":9,"description":null,"created_at":"2024-01-01T00:00:00Z"}]"#,
+        )
+        .unwrap();
+
+        let storage = JsonStorage::open(&path, 0).unwrap();
+        assert_eq!(
+            storage.get("legacy").unwrap().interfaces[0]
+                .mac_address
+                .to_string(),
+            "AA:BB:CC:DD:EE:FF"
+        );
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("\"AA:BB:CC:DD:EE:FF\""));
+        assert!(content.contains("\"interfaces\""));
+    }
+
+    #[test]
+    fn load_normalizes_legacy_mac_addresses() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut file: serde_json::Value =
+            serde_json::from_str(&encode_devices(&[create_test_device("Templated")]).unwrap())
+                .unwrap();
+        file["devices"][0]["interfaces"][0]["mac_address"] = "aabb.ccdd.eeff".into();
+        std::fs::write(&path, file.to_string()).unwrap();
+
+        let storage = JsonStorage::open(&path, 0).unwrap();
+        assert_eq!(
+            storage.devices[0].interfaces[0].mac_address.to_string(),
+            "AA:BB:CC:DD:EE:FF"
+        );
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("\"AA:BB:CC:DD:EE:FF\""));
+
+        JsonStorage::open(&path, 0).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+    }
+
+    #[test]
+    fn load_upgrades_bare_array_and_keeps_original_as_backup() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let legacy = serde_json::to_string(&[create_test_device("Legacy")]).unwrap();
+        std::fs::write(&path, &legacy).unwrap();
+
+        let storage = JsonStorage::open(&path, 0).unwrap();
+        assert_eq!(storage.devices.len(), 1);
+
+        let upgraded: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(upgraded["version"], CURRENT_VERSION);
+        assert_eq!(upgraded["devices"][0]["name"], "Legacy");
+        assert_eq!(
+            std::fs::read_to_string(format!("{path}.v1")).unwrap(),
+            legacy
+        );
+    }
+
+    #[test]
+    fn load_refuses_newer_version_and_leaves_file_alone() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let future = format!(r#"{{"version":{},"devices":[]}}"#, CURRENT_VERSION + 1);
+        std::fs::write(&path, &future).unwrap();
+
+        let result = JsonStorage::open(&path, 3);
+        assert!(matches!(
+            result,
+            Err(StorageError::UnsupportedVersion { found, .. }) if found == CURRENT_VERSION + 1
+        ));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), future);
+    }
+
+    #[test]
+    fn reorder_moves_listed_devices_within_their_slots() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::new(&path);
+        let devices: Vec<Device> = ["A", "B", "C", "D"]
+            .into_iter()
+            .map(create_test_device)
+            .collect();
+        storage.add_all(devices.clone()).unwrap();
+
+        storage
+            .reorder(&[devices[3].id.clone(), devices[1].id.clone()])
+            .unwrap();
+
+        let names: Vec<String> = storage.get_all().into_iter().map(|d| d.name).collect();
+        assert_eq!(names, ["A", "D", "C", "B"]);
+        let orders: Vec<u32> = storage.get_all().iter().map(|d| d.sort_order).collect();
+        assert_eq!(orders, [0, 1, 2, 3]);
+
+        let reloaded = JsonStorage::open(&path, 0).unwrap();
+        let names: Vec<String> = reloaded.get_all().into_iter().map(|d| d.name).collect();
+        assert_eq!(names, ["A", "D", "C", "B"]);
+    }
+
+    #[test]
+    fn reorder_rejects_unknown_ids() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::new(&path);
+        let device = create_test_device("A");
+        storage.add(device.clone()).unwrap();
+
+        let result = storage.reorder(&["missing".to_string(), device.id]);
+        assert!(matches!(result, Err(StorageError::NotFound(id)) if id == "missing"));
+    }
+
+    #[test]
+    fn load_sorts_devices_by_sort_order() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut first = create_test_device("First");
+        first.sort_order = 1;
+        let mut second = create_test_device("Second");
+        second.sort_order = 0;
+        std::fs::write(&path, serde_json::to_string(&[first, second]).unwrap()).unwrap();
+
+        let storage = JsonStorage::open(&path, 0).unwrap();
+        let names: Vec<String> = storage.get_all().into_iter().map(|d| d.name).collect();
+        assert_eq!(names, ["Second", "First"]);
+    }
+
+    #[test]
+    fn save_replaces_file_without_leaving_temp_file() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::new(&path);
+        storage.add(create_test_device("A")).unwrap();
+        storage.add(create_test_device("B")).unwrap();
+
+        let entries: Vec<String> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(entries, ["devices.json"]);
+        assert_eq!(JsonStorage::open(&path, 0).unwrap().devices.len(), 2);
+    }
+
+    #[test]
+    fn save_rotates_backups() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::open(&path, 2).unwrap();
+        for name in ["A", "B", "C", "D"] {
+            storage.add(create_test_device(name)).unwrap();
+        }
+
+        let count = |file: String| JsonStorage::open(&file, 0).unwrap().devices.len();
+        assert_eq!(count(path.clone()), 4);
+        assert_eq!(count(format!("{path}.1")), 3);
+        assert_eq!(count(format!("{path}.2")), 2);
+        assert!(!std::path::Path::new(&format!("{path}.3")).exists());
+    }
+
+    #[test]
+    fn open_names_newest_valid_backup_and_recover_restores_it() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::open(&path, 3).unwrap();
+        storage.add(create_test_device("A")).unwrap();
+        storage.add(create_test_device("B")).unwrap();
+        storage.add(create_test_device("C")).unwrap();
+        std::fs::write(&path, "[{\"truncated").unwrap();
+        std::fs::write(format!("{path}.1"), "not json").unwrap();
+
+        let result = JsonStorage::open(&path, 3);
+        assert!(
+            matches!(result, Err(StorageError::Corrupt { ref backup, .. }) if *backup == backup_path(&path, 2))
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[{\"truncated");
+
+        for _ in 0..2 {
+            assert_eq!(
+                JsonStorage::recover(&path, 3).unwrap(),
+                Some(backup_path(&path, 2))
+            );
+            std::fs::write(&path, "[{\"truncated").unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        let kept: Vec<String> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name.starts_with("devices.json.corrupt."))
+            .collect();
+        assert_eq!(kept.len(), 2);
+
+        JsonStorage::recover(&path, 3).unwrap();
+        assert_eq!(JsonStorage::recover(&path, 3).unwrap(), None);
+        let recovered = JsonStorage::open(&path, 3).unwrap();
+        let names: Vec<String> = recovered.get_all().iter().map(|d| d.name.clone()).collect();
+        assert_eq!(names, ["A"]);
+    }
+
+    #[test]
+    fn open_fails_when_no_backup_is_readable() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        std::fs::write(&path, "not json").unwrap();
+
+        let result = JsonStorage::open(&path, 3);
+        assert!(matches!(result, Err(StorageError::Parse(_))));
+        assert!(std::path::Path::new(&path).exists());
+    }
+
+    /// Storage whose file lives in a directory that is gone by the next write.
+    fn storage_with_failing_writes(dir: &TempDir, devices: &[Device]) -> JsonStorage {
+        let subdir = dir.path().join("data");
+        std::fs::create_dir(&subdir).unwrap();
+        let path = subdir.join("devices.json").to_string_lossy().to_string();
+        let mut storage = JsonStorage::new(&path);
+        storage.add_all(devices.to_vec()).unwrap();
+        std::fs::remove_dir_all(&subdir).unwrap();
+        storage
+    }
+
+    #[test]
+    fn failed_write_leaves_devices_unchanged() {
+        let dir = TempDir::new().unwrap();
+        let device = create_test_device("A");
+        let mut storage = storage_with_failing_writes(&dir, std::slice::from_ref(&device));
+
+        assert!(matches!(
+            storage.add(create_test_device("B")),
+            Err(StorageError::Io(_))
+        ));
+        assert!(storage.remove(&device.id).is_err());
+        let renamed = Device {
+            name: "Renamed".to_string(),
+            ..device.clone()
+        };
+        assert!(storage.update(&device.id, renamed).is_err());
+
+        assert_eq!(storage.get_all(), [device]);
+    }
+
+    #[test]
+    fn apply_commits_all_mutations_together() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::new(&path);
+        let a = create_test_device("A");
+        let b = create_test_device("B");
+        storage.add(a.clone()).unwrap();
+
+        storage
+            .apply(vec![
+                Mutation::Add(b.clone()),
+                Mutation::Remove(a.id.clone()),
+                Mutation::Update {
+                    id: b.id.clone(),
+                    device: Device {
+                        name: "B2".to_string(),
+                        ..b.clone()
+                    },
+                },
+            ])
+            .unwrap();
+
+        let names: Vec<String> = JsonStorage::open(&path, 0)
+            .unwrap()
+            .get_all()
+            .into_iter()
+            .map(|d| d.name)
+            .collect();
+        assert_eq!(names, ["B2"]);
+    }
+
+    #[test]
+    fn apply_discards_every_mutation_when_one_fails() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::new(&path);
+        let a = create_test_device("A");
+        storage.add(a.clone()).unwrap();
+
+        let result = storage.apply(vec![
+            Mutation::Add(create_test_device("B")),
+            Mutation::Remove("missing".to_string()),
+        ]);
+
+        assert!(matches!(result, Err(StorageError::NotFound(_))));
+        assert_eq!(storage.get_all(), std::slice::from_ref(&a));
+        assert_eq!(JsonStorage::open(&path, 0).unwrap().get_all(), [a]);
+    }
+
+    #[test]
+    fn apply_discards_batch_when_write_fails() {
+        let dir = TempDir::new().unwrap();
+        let device = create_test_device("A");
+        let mut storage = storage_with_failing_writes(&dir, std::slice::from_ref(&device));
+
+        let result = storage.apply(vec![
+            Mutation::Add(create_test_device("B")),
+            Mutation::Reorder(vec![device.id.clone()]),
+        ]);
+
+        assert!(matches!(result, Err(StorageError::Io(_))));
+        assert_eq!(storage.get_all(), [device]);
+    }
+
+    #[test]
+    fn reload_applies_external_changes() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::new(&path);
+        let device = create_test_device("A");
+        storage.add(device.clone()).unwrap();
+
+        let mut external = JsonStorage::open(&path, 0).unwrap();
+        external.remove(&device.id).unwrap();
+        external.add(create_test_device("B")).unwrap();
+
+        let summary = storage.reload().unwrap();
+        assert_eq!(summary.added, ["B"]);
+        assert_eq!(summary.removed, ["A"]);
+        let names: Vec<String> = storage.get_all().into_iter().map(|d| d.name).collect();
+        assert_eq!(names, ["B"]);
+    }
+
+    #[test]
+    fn reload_after_own_write_changes_nothing() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::new(&path);
+        storage.add(create_test_device("A")).unwrap();
+
+        // Not reread: the devices dropped from memory are not loaded back.
+        storage.devices = DeviceTable::default();
+        assert!(storage.reload().unwrap().is_empty());
+        assert!(storage.devices.is_empty());
+    }
+
+    #[test]
+    fn reload_keeps_devices_when_file_is_invalid() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let mut storage = JsonStorage::new(&path);
+        let device = create_test_device("A");
+        storage.add(device.clone()).unwrap();
+
+        let invalid = Device {
+            name: String::new(),
+            ..device.clone()
+        };
+        std::fs::write(&path, serde_json::to_string(&[invalid]).unwrap()).unwrap();
+        let result = storage.reload();
+        assert!(matches!(
+            result,
+            Err(StorageError::Invalid(ref err)) if err.to_string().contains("[0].name")
+        ));
+
+        std::fs::write(&path, "{ not json").unwrap();
+        assert!(matches!(storage.reload(), Err(StorageError::Parse(_))));
+
+        std::fs::write(
+            &path,
+            serde_json::to_string(&[device.clone(), device.clone()]).unwrap(),
+        )
+        .unwrap();
+        assert!(matches!(storage.reload(), Err(StorageError::Invalid(_))));
+        assert_eq!(storage.get_all(), [device]);
+    }
+}
@@ -0,0 +1,90 @@
+//! Upgrades older layouts of the device file to [`CURRENT_VERSION`].
+//!
+//! Each migration turns the raw JSON of one version into the next, so a file
+//! of any supported age is brought up to date one step at a time before it is
+//! deserialized.
+
+use serde::de::Error as _;
+use serde_json::{Value, json};
+
+use crate::storage::StorageError;
+
+/// Layout this build reads and writes.
+pub const CURRENT_VERSION: u32 = 2;
+
+type Migration = fn(Value) -> Value;
+
+/// `MIGRATIONS[n]` upgrades a version `n + 1` file to version `n + 2`.
+const MIGRATIONS: [Migration; CURRENT_VERSION as usize - 1] = [wrap_device_array];
+
+/// Version 1 was a bare array of devices. Older single-MAC records inside it
+/// are still understood by the `Device` deserializer.
+fn wrap_device_array(devices: Value) -> Value {
+    json!({ "version": 2, "devices": devices })
+}
+
+/// Version of a raw file: bare arrays predate versioning and count as 1.
+fn version_of(value: &Value) -> Result<u32, StorageError> {
+    if value.is_array() {
+        return Ok(1);
+    }
+    value
+        .get("version")
+        .and_then(Value::as_u64)
+        .and_then(|version| u32::try_from(version).ok())
+        .filter(|version| *version >= 1)
+        .ok_or_else(|| serde_json::Error::custom("missing or invalid storage version").into())
+}
+
+/// Brings `value` up to [`CURRENT_VERSION`], returning the version it had.
+pub fn migrate(mut value: Value) -> Result<(u32, Value), StorageError> {
+    let found = version_of(&value)?;
+    if found > CURRENT_VERSION {
+        return Err(StorageError::UnsupportedVersion {
+            found,
+            supported: CURRENT_VERSION,
+        });
+    }
+
+    for migration in &MIGRATIONS[found as usize - 1..] {
+        value = migration(value);
+    }
+    Ok((found, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bare_array_is_upgraded_to_current_version() {
+        let (found, value) = migrate(json!([{ "id": "a" }])).unwrap();
+        assert_eq!(found, 1);
+        assert_eq!(
+            value,
+            json!({ "version": CURRENT_VERSION, "devices": [{ "id": "a" }] })
+        );
+    }
+
+    #[test]
+    fn current_version_is_left_alone() {
+        let file = json!({ "version": CURRENT_VERSION, "devices": [] });
+        assert_eq!(migrate(file.clone()).unwrap(), (CURRENT_VERSION, file));
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let result = migrate(json!({ "version": CURRENT_VERSION + 1, "devices": [] }));
+        assert!(matches!(
+            result,
+            Err(StorageError::UnsupportedVersion { found, supported })
+                if found == CURRENT_VERSION + 1 && supported == CURRENT_VERSION
+        ));
+    }
+
+    #[test]
+    fn object_without_version_is_rejected() {
+        let result = migrate(json!({ "devices": [] }));
+        assert!(matches!(result, Err(StorageError::Parse(_))));
+    }
+}
@@ -0,0 +1,205 @@
+use parking_lot::Mutex;
+use rusqlite::{Connection, params};
+use tracing::{debug, info, instrument};
+
+use super::table::Changes;
+use super::{DeviceTable, Mutation, StorageBackend, StorageError, warn_duplicate_macs};
+use crate::models::Device;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS devices (
+        id TEXT PRIMARY KEY NOT NULL,
+        sort_order INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+";
+
+/// Stores one row per device in an embedded SQLite database. Reads are served
+/// from an in-memory copy that is loaded once; every change is written to the
+/// database before the copy is updated.
+#[derive(Debug)]
+pub struct SqliteStorage {
+    connection: Mutex<Connection>,
+    devices: DeviceTable,
+}
+
+impl SqliteStorage {
+    #[instrument(skip_all, fields(path = %path))]
+    pub fn open(path: &str) -> Result<Self, StorageError> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    fn from_connection(connection: Connection) -> Result<Self, StorageError> {
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.execute_batch(SCHEMA)?;
+
+        let devices = {
+            let mut statement =
+                connection.prepare("SELECT data FROM devices ORDER BY sort_order, rowid")?;
+            let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+            let mut devices = Vec::new();
+            for data in rows {
+                devices.push(serde_json::from_str(&data?)?);
+            }
+            devices
+        };
+
+        info!(device_count = devices.len(), "Storage loaded");
+        warn_duplicate_macs(&devices);
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+            devices: devices.into(),
+        })
+    }
+
+    /// Writes the rows of the devices that `changes` touched.
+    fn write_changes(
+        connection: &Connection,
+        devices: &DeviceTable,
+        changes: &Changes,
+    ) -> Result<(), StorageError> {
+        if changes.cleared {
+            connection.execute("DELETE FROM devices", [])?;
+        }
+        for id in &changes.touched {
+            match devices.get(id) {
+                Some(device) => {
+                    connection.execute(
+                        "INSERT INTO devices (id, sort_order, data) VALUES (?1, ?2, ?3)
+                         ON CONFLICT (id) DO UPDATE
+                         SET sort_order = excluded.sort_order, data = excluded.data",
+                        params![device.id, device.sort_order, serde_json::to_string(device)?],
+                    )?;
+                }
+                None => {
+                    connection.execute("DELETE FROM devices WHERE id = ?1", params![id])?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StorageBackend for SqliteStorage {
+    #[instrument(skip_all, fields(count = mutations.len()))]
+    fn apply(&mut self, mutations: Vec<Mutation>) -> Result<(), StorageError> {
+        let changes = self.devices.apply(mutations)?;
+        let written = self
+            .connection
+            .get_mut()
+            .transaction()
+            .map_err(StorageError::from)
+            .and_then(|transaction| {
+                Self::write_changes(&transaction, &self.devices, &changes)?;
+                Ok(transaction.commit()?)
+            });
+        if let Err(err) = written {
+            self.devices.undo(changes);
+            return Err(err);
+        }
+        debug!("Storage changes applied");
+        Ok(())
+    }
+
+    fn get_all(&self) -> Vec<Device> {
+        self.devices.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::{BackendExt, create_test_device};
+    use rusqlite::OptionalExtension;
+    use tempfile::TempDir;
+
+    fn temp_database(dir: &TempDir) -> String {
+        dir.path().join("jumpers.db").to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn changes_survive_reopening() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_database(&dir);
+        let devices: Vec<Device> = ["A", "B", "C"]
+            .into_iter()
+            .map(create_test_device)
+            .collect();
+
+        {
+            let mut storage = SqliteStorage::open(&path).unwrap();
+            storage.add_all(devices.clone()).unwrap();
+            storage.remove(&devices[0].id).unwrap();
+            let renamed = Device {
+                name: "B2".to_string(),
+                ..devices[1].clone()
+            };
+            storage.update(&devices[1].id, renamed).unwrap();
+            storage
+                .reorder(&[devices[2].id.clone(), devices[1].id.clone()])
+                .unwrap();
+        }
+
+        let storage = SqliteStorage::open(&path).unwrap();
+        let names: Vec<String> = storage.get_all().into_iter().map(|d| d.name).collect();
+        assert_eq!(names, ["C", "B2"]);
+        assert_eq!(storage.get(&devices[1].id).unwrap().revision, 3);
+    }
+
+    #[test]
+    fn failed_update_leaves_database_untouched() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_database(&dir);
+        let device = create_test_device("A");
+
+        let mut storage = SqliteStorage::open(&path).unwrap();
+        storage.add(device.clone()).unwrap();
+        let stale = Device {
+            revision: 7,
+            ..device.clone()
+        };
+        assert!(matches!(
+            storage.update(&device.id, stale),
+            Err(StorageError::Stale { current: 1, .. })
+        ));
+
+        let stored: Option<String> = storage
+            .connection
+            .get_mut()
+            .query_row(
+                "SELECT data FROM devices WHERE id = ?1",
+                params![device.id],
+                |row| row.get(0),
+            )
+            .optional()
+            .unwrap();
+        let stored: Device = serde_json::from_str(&stored.unwrap()).unwrap();
+        assert_eq!(stored, device);
+    }
+
+    #[test]
+    fn failed_batch_write_leaves_devices_unchanged() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_database(&dir);
+        let device = create_test_device("A");
+
+        let mut storage = SqliteStorage::open(&path).unwrap();
+        storage.add(device.clone()).unwrap();
+        storage
+            .connection
+            .get_mut()
+            .pragma_update(None, "query_only", true)
+            .unwrap();
+
+        let result = storage.apply(vec![
+            Mutation::Add(create_test_device("B")),
+            Mutation::Remove(device.id.clone()),
+        ]);
+        assert!(matches!(result, Err(StorageError::Sqlite(_))));
+        assert_eq!(storage.get_all(), std::slice::from_ref(&device));
+
+        drop(storage);
+        assert_eq!(SqliteStorage::open(&path).unwrap().get_all(), [device]);
+    }
+}
@@ -0,0 +1,485 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Deref;
+
+use time::OffsetDateTime;
+
+use super::{Mutation, StorageError};
+use crate::models::{Device, MacAddress};
+
+/// The devices of a backend in storage order, trash included, with the
+/// position of every device by id and the live owners of every MAC address.
+///
+/// [`DeviceTable::apply`] is the in-memory half of
+/// [`StorageBackend::apply`](super::StorageBackend::apply), shared by the
+/// backends. It looks devices up through the maps instead of scanning, and
+/// keeps what it needs to take a batch back instead of working on a copy.
+#[derive(Debug, Clone, Default)]
+pub(super) struct DeviceTable {
+    devices: Vec<Device>,
+    by_id: HashMap<String, usize>,
+    /// Ids of the live devices with each MAC address. Only files that came
+    /// with duplicates have more than one.
+    owners: HashMap<MacAddress, Vec<String>>,
+    /// Positions of devices removed by the batch being applied, which leave
+    /// `devices` once the batch is through.
+    removed: Vec<usize>,
+}
+
+/// What [`DeviceTable::apply`] changed, to write it out or take it back.
+#[derive(Debug, Default)]
+pub(super) struct Changes {
+    steps: Vec<Step>,
+    /// Whether every device stored before the batch was removed.
+    pub cleared: bool,
+    /// Ids of the devices added, changed or removed since the last clear.
+    pub touched: HashSet<String>,
+}
+
+#[derive(Debug)]
+enum Step {
+    /// A device was pushed at the end.
+    Added,
+    /// The device at this position was replaced by the one it holds now.
+    Replaced(usize, Device),
+    /// The device at this position was marked as removed.
+    Removed,
+    /// `devices` was rearranged so that position `i` holds what was at
+    /// `from[i]`, then renumbered. `renumbered` has the position, sort order,
+    /// revision and update time of each device that got a new number.
+    Reordered {
+        from: Vec<usize>,
+        renumbered: Vec<(usize, u32, u64, Option<OffsetDateTime>)>,
+    },
+    /// The marked devices left `devices`; they were at these positions.
+    Compacted(Vec<(usize, Device)>),
+    /// Every device was removed.
+    Cleared {
+        devices: Vec<Device>,
+        removed: Vec<usize>,
+    },
+}
+
+impl Deref for DeviceTable {
+    type Target = [Device];
+
+    fn deref(&self) -> &[Device] {
+        &self.devices
+    }
+}
+
+impl From<Vec<Device>> for DeviceTable {
+    fn from(devices: Vec<Device>) -> Self {
+        let mut table = Self {
+            devices,
+            ..Self::default()
+        };
+        table.reindex();
+        table
+    }
+}
+
+impl DeviceTable {
+    pub fn get(&self, id: &str) -> Option<&Device> {
+        self.by_id.get(id).map(|&position| &self.devices[position])
+    }
+
+    pub fn to_vec(&self) -> Vec<Device> {
+        self.devices.clone()
+    }
+
+    /// Applies the mutations in order, all or none of them. Ids stay unique
+    /// and no MAC address joins a second live device, however callers raced
+    /// each other to queue their mutations.
+    pub fn apply(&mut self, mutations: Vec<Mutation>) -> Result<Changes, StorageError> {
+        let mut changes = Changes::default();
+        for mutation in mutations {
+            if let Err(err) = self.step(mutation, &mut changes) {
+                self.undo(changes);
+                return Err(err);
+            }
+        }
+        self.compact(&mut changes);
+        Ok(changes)
+    }
+
+    /// Takes back a batch that [`DeviceTable::apply`] committed, as long as
+    /// no later batch is still applied.
+    pub fn undo(&mut self, changes: Changes) {
+        for step in changes.steps.into_iter().rev() {
+            match step {
+                Step::Added => {
+                    let position = self.devices.len() - 1;
+                    self.unlink(position);
+                    self.devices.pop();
+                }
+                Step::Replaced(position, previous) => {
+                    self.unlink(position);
+                    self.devices[position] = previous;
+                    self.link(position);
+                }
+                Step::Removed => {
+                    if let Some(position) = self.removed.pop() {
+                        self.link(position);
+                    }
+                }
+                Step::Reordered { from, renumbered } => {
+                    for (position, sort_order, revision, updated_at) in renumbered {
+                        let device = &mut self.devices[position];
+                        device.sort_order = sort_order;
+                        device.revision = revision;
+                        device.updated_at = updated_at;
+                    }
+                    let mut back = vec![0; from.len()];
+                    for (position, &origin) in from.iter().enumerate() {
+                        back[origin] = position;
+                    }
+                    self.rearrange(&back);
+                }
+                Step::Compacted(gone) => {
+                    let kept = std::mem::take(&mut self.devices);
+                    let mut kept = kept.into_iter();
+                    for (position, device) in gone {
+                        self.devices
+                            .extend(kept.by_ref().take(position - self.devices.len()));
+                        self.devices.push(device);
+                        self.removed.push(position);
+                    }
+                    self.devices.extend(kept);
+                    self.reindex();
+                }
+                Step::Cleared { devices, removed } => {
+                    self.devices = devices;
+                    self.removed = removed;
+                    self.reindex();
+                }
+            }
+        }
+    }
+
+    fn step(&mut self, mutation: Mutation, changes: &mut Changes) -> Result<(), StorageError> {
+        match mutation {
+            Mutation::Add(device) => {
+                if self.by_id.contains_key(&device.id) {
+                    return Err(StorageError::DuplicateId(device.id));
+                }
+                self.ensure_free_macs(&device.id, &device, &[])?;
+                changes.touched.insert(device.id.clone());
+                self.devices.push(device);
+                self.link(self.devices.len() - 1);
+                changes.steps.push(Step::Added);
+            }
+            Mutation::Remove(id) => {
+                let position = self.position(&id)?;
+                self.unlink(position);
+                self.removed.push(position);
+                changes.touched.insert(id);
+                changes.steps.push(Step::Removed);
+            }
+            Mutation::Update { id, device } => {
+                let position = self.position(&id)?;
+                let stored = &self.devices[position];
+                if device.revision != stored.revision {
+                    return Err(StorageError::Stale {
+                        id,
+                        current: stored.revision,
+                    });
+                }
+                let had: Vec<MacAddress> = if stored.is_deleted() {
+                    Vec::new()
+                } else {
+                    stored.macs().collect()
+                };
+                self.ensure_free_macs(&id, &device, &had)?;
+
+                self.unlink(position);
+                let stored = &self.devices[position];
+                let updated = Device {
+                    id: stored.id.clone(),
+                    revision: stored.revision + 1,
+                    updated_at: Some(OffsetDateTime::now_utc()),
+                    ..device
+                };
+                let previous = std::mem::replace(&mut self.devices[position], updated);
+                self.link(position);
+                changes.touched.insert(id);
+                changes.steps.push(Step::Replaced(position, previous));
+            }
+            Mutation::Reorder(ids) => self.reorder(&ids, changes)?,
+            Mutation::Clear => {
+                let devices = std::mem::take(&mut self.devices);
+                let removed = std::mem::take(&mut self.removed);
+                self.by_id.clear();
+                self.owners.clear();
+                changes.cleared = true;
+                changes.touched.clear();
+                changes.steps.push(Step::Cleared { devices, removed });
+            }
+        }
+        Ok(())
+    }
+
+    fn position(&self, id: &str) -> Result<usize, StorageError> {
+        self.by_id
+            .get(id)
+            .copied()
+            .ok_or_else(|| StorageError::NotFound(id.to_string()))
+    }
+
+    /// Fails when live `device`, stored under `id`, has a MAC address other
+    /// than those it already `had` that another live device uses. MACs shared
+    /// before are left alone, so a file that came with duplicates can still be
+    /// edited.
+    fn ensure_free_macs(
+        &self,
+        id: &str,
+        device: &Device,
+        had: &[MacAddress],
+    ) -> Result<(), StorageError> {
+        if device.is_deleted() {
+            return Ok(());
+        }
+        for mac in device.macs().filter(|mac| !had.contains(mac)) {
+            let owner = self
+                .owners
+                .get(&mac)
+                .and_then(|owners| owners.iter().find(|owner| *owner != id));
+            if let Some(owner) = owner {
+                return Err(StorageError::DuplicateMac {
+                    mac,
+                    device: self.devices[self.by_id[owner]].name.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Rearranges the listed devices into the given order within the slots
+    /// they already occupy and renumbers every device's `sort_order`.
+    fn reorder(&mut self, ids: &[String], changes: &mut Changes) -> Result<(), StorageError> {
+        self.compact(changes);
+        let mut listed: Vec<usize> = Vec::with_capacity(ids.len());
+        for id in ids {
+            let position = self.position(id)?;
+            if !listed.contains(&position) {
+                listed.push(position);
+            }
+        }
+
+        let mut slots = listed.clone();
+        slots.sort_unstable();
+        let mut from: Vec<usize> = (0..self.devices.len()).collect();
+        for (slot, position) in slots.into_iter().zip(listed) {
+            from[slot] = position;
+        }
+        self.rearrange(&from);
+
+        let now = OffsetDateTime::now_utc();
+        let mut renumbered = Vec::new();
+        for (sort_order, (position, device)) in (0..).zip(self.devices.iter_mut().enumerate()) {
+            if device.sort_order != sort_order {
+                renumbered.push((
+                    position,
+                    device.sort_order,
+                    device.revision,
+                    device.updated_at,
+                ));
+                device.sort_order = sort_order;
+                device.revision += 1;
+                device.updated_at = Some(now);
+                changes.touched.insert(device.id.clone());
+            }
+        }
+        changes.steps.push(Step::Reordered { from, renumbered });
+        Ok(())
+    }
+
+    /// Moves what is at `from[i]` to position `i`.
+    fn rearrange(&mut self, from: &[usize]) {
+        let mut devices: Vec<Option<Device>> = std::mem::take(&mut self.devices)
+            .into_iter()
+            .map(Some)
+            .collect();
+        self.devices = from
+            .iter()
+            .map(|&origin| devices[origin].take().expect("each position moves once"))
+            .collect();
+        for (position, device) in self.devices.iter().enumerate() {
+            if from[position] != position {
+                self.by_id.insert(device.id.clone(), position);
+            }
+        }
+    }
+
+    /// Drops the devices marked as removed.
+    fn compact(&mut self, changes: &mut Changes) {
+        if self.removed.is_empty() {
+            return;
+        }
+        let mut removed = std::mem::take(&mut self.removed);
+        removed.sort_unstable();
+        let mut gone = Vec::with_capacity(removed.len());
+        let mut marked = removed.into_iter().peekable();
+        let devices = std::mem::take(&mut self.devices);
+        for (position, device) in devices.into_iter().enumerate() {
+            if marked.next_if_eq(&position).is_some() {
+                gone.push((position, device));
+            } else {
+                self.devices.push(device);
+            }
+        }
+        self.reindex();
+        changes.steps.push(Step::Compacted(gone));
+    }
+
+    fn reindex(&mut self) {
+        self.by_id.clear();
+        self.owners.clear();
+        let removed: HashSet<usize> = self.removed.iter().copied().collect();
+        for position in 0..self.devices.len() {
+            if !removed.contains(&position) {
+                self.link(position);
+            }
+        }
+    }
+
+    fn link(&mut self, position: usize) {
+        let device = &self.devices[position];
+        self.by_id.insert(device.id.clone(), position);
+        if !device.is_deleted() {
+            for mac in device.macs() {
+                self.owners.entry(mac).or_default().push(device.id.clone());
+            }
+        }
+    }
+
+    fn unlink(&mut self, position: usize) {
+        let device = &self.devices[position];
+        self.by_id.remove(&device.id);
+        if !device.is_deleted() {
+            for mac in device.macs() {
+                if let Some(owners) = self.owners.get_mut(&mac) {
+                    owners.retain(|owner| *owner != device.id);
+                    if owners.is_empty() {
+                        self.owners.remove(&mac);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::create_test_device;
+
+    fn names(table: &DeviceTable) -> Vec<&str> {
+        table.iter().map(|device| device.name.as_str()).collect()
+    }
+
+    #[test]
+    fn failed_batch_leaves_table_as_it_was() {
+        let devices: Vec<Device> = ["A", "B", "C"]
+            .into_iter()
+            .map(create_test_device)
+            .collect();
+        let mut table = DeviceTable::from(devices.clone());
+        let added = create_test_device("D");
+        let taken = Device {
+            interfaces: devices[2].interfaces.clone(),
+            ..create_test_device("E")
+        };
+
+        let result = table.apply(vec![
+            Mutation::Remove(devices[0].id.clone()),
+            Mutation::Add(added.clone()),
+            Mutation::Update {
+                id: devices[1].id.clone(),
+                device: Device {
+                    name: "B2".to_string(),
+                    ..devices[1].clone()
+                },
+            },
+            Mutation::Reorder(vec![added.id.clone(), devices[2].id.clone()]),
+            Mutation::Clear,
+            Mutation::Add(devices[2].clone()),
+            Mutation::Add(taken),
+        ]);
+
+        assert!(matches!(result, Err(StorageError::DuplicateMac { .. })));
+        assert_eq!(*table, devices[..]);
+        assert!(table.get(&added.id).is_none());
+        assert_eq!(table.get(&devices[0].id), Some(&devices[0]));
+        let reused = Device {
+            interfaces: devices[0].interfaces.clone(),
+            ..create_test_device("F")
+        };
+        assert!(matches!(
+            table.apply(vec![Mutation::Add(reused)]),
+            Err(StorageError::DuplicateMac { .. })
+        ));
+    }
+
+    #[test]
+    fn undo_takes_back_a_committed_batch() {
+        let devices: Vec<Device> = ["A", "B", "C", "D"]
+            .into_iter()
+            .zip(0..)
+            .map(|(name, sort_order)| Device {
+                sort_order,
+                ..create_test_device(name)
+            })
+            .collect();
+        let mut table = DeviceTable::from(devices.clone());
+
+        let changes = table
+            .apply(vec![
+                Mutation::Remove(devices[1].id.clone()),
+                Mutation::Reorder(vec![devices[3].id.clone(), devices[0].id.clone()]),
+                Mutation::Remove(devices[2].id.clone()),
+            ])
+            .unwrap();
+        assert_eq!(names(&table), ["D", "A"]);
+        assert_eq!(table.get(&devices[0].id).unwrap().sort_order, 2);
+        assert_eq!(
+            table.get(&devices[0].id).unwrap().revision,
+            devices[0].revision + 1
+        );
+        assert!(!changes.cleared);
+        assert_eq!(changes.touched.len(), 4);
+
+        table.undo(changes);
+        assert_eq!(*table, devices[..]);
+        for device in &devices {
+            assert_eq!(table.get(&device.id), Some(device));
+        }
+    }
+
+    #[test]
+    fn reorder_bumps_only_renumbered_devices() {
+        let devices: Vec<Device> = ["A", "B", "C"]
+            .into_iter()
+            .zip(0..)
+            .map(|(name, sort_order)| Device {
+                sort_order,
+                ..create_test_device(name)
+            })
+            .collect();
+        let mut table = DeviceTable::from(devices.clone());
+
+        let changes = table
+            .apply(vec![Mutation::Reorder(vec![
+                devices[2].id.clone(),
+                devices[0].id.clone(),
+            ])])
+            .unwrap();
+        assert_eq!(names(&table), ["C", "B", "A"]);
+        assert_eq!(changes.touched.len(), 2);
+        assert_eq!(table.get(&devices[1].id), Some(&devices[1]));
+        for moved in [&devices[0], &devices[2]] {
+            let device = table.get(&moved.id).unwrap();
+            assert_eq!(device.revision, moved.revision + 1);
+            assert_ne!(device.updated_at, moved.updated_at);
+        }
+    }
+}
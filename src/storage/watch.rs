@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tracing::{info, warn};
+
+use super::SharedStorage;
+use crate::models::Device;
+
+/// What an external edit of the storage file changed, by device name.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ReloadSummary {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl ReloadSummary {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for ReloadSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts = [
+            ("added", &self.added),
+            ("removed", &self.removed),
+            ("changed", &self.changed),
+        ];
+        let mut first = true;
+        for (label, names) in parts.into_iter().filter(|(_, names)| !names.is_empty()) {
+            if !first {
+                write!(f, "; ")?;
+            }
+            first = false;
+            write!(f, "{label}: {}", names.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Merges devices read back from disk into the ones in memory. Devices that
+/// are unchanged apart from their revision keep the in-memory copy; changed
+/// ones get a revision past the old one, so edits based on the old copy are
+/// rejected as stale.
+pub(super) fn reconcile(current: &[Device], loaded: Vec<Device>) -> (Vec<Device>, ReloadSummary) {
+    let mut previous: HashMap<&str, &Device> = current.iter().map(|d| (d.id.as_str(), d)).collect();
+    let mut summary = ReloadSummary::default();
+
+    let devices = loaded
+        .into_iter()
+        .map(|mut device| match previous.remove(device.id.as_str()) {
+            None => {
+                summary.added.push(device.name.clone());
+                device
+            }
+            Some(old)
+                if *old
+                    == Device {
+                        revision: old.revision,
+                        ..device.clone()
+                    } =>
+            {
+                old.clone()
+            }
+            Some(old) => {
+                summary.changed.push(device.name.clone());
+                device.revision = device.revision.max(old.revision + 1);
+                device
+            }
+        })
+        .collect();
+
+    summary.removed = current
+        .iter()
+        .filter(|d| previous.contains_key(d.id.as_str()))
+        .map(|d| d.name.clone())
+        .collect();
+    (devices, summary)
+}
+
+impl SharedStorage {
+    /// Rereads the storage file after an external edit. An invalid file is
+    /// logged and ignored, keeping the current devices.
+    pub async fn reload(&self) {
+        match self.reload_backend().await {
+            Ok(summary) if summary.is_empty() => {}
+            Ok(summary) => info!(
+                added = summary.added.len(),
+                removed = summary.removed.len(),
+                changed = summary.changed.len(),
+                details = %summary,
+                "Storage file changed on disk, devices reloaded"
+            ),
+            Err(err) => warn!(
+                error = %err,
+                "Storage file changed on disk but could not be loaded, keeping current devices"
+            ),
+        }
+    }
+
+    /// Reloads the devices whenever the backend's file is changed by another
+    /// program. Returns `None` for backends without a file to watch; the
+    /// watcher stops when it is dropped.
+    pub fn watch(&self) -> notify::Result<Option<RecommendedWatcher>> {
+        let Some(path) = self.watched_path.as_deref().map(PathBuf::from) else {
+            return Ok(None);
+        };
+        // Watch the directory: atomic saves replace the file, which would end
+        // a watch on the file itself.
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let file_name = path.file_name().map(ToOwned::to_owned);
+
+        let storage = self.clone();
+        let runtime = tokio::runtime::Handle::current();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(err) => {
+                        warn!(error = %err, "Storage file watcher failed");
+                        return;
+                    }
+                };
+                let touches_file = event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name().map(ToOwned::to_owned) == file_name);
+                // The server's own saves land here too; the backend skips a
+                // file that is still the one it wrote.
+                if touches_file && matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                {
+                    let storage = storage.clone();
+                    runtime.spawn(async move { storage.reload().await });
+                }
+            })?;
+        watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+        info!(file = %path.display(), "Watching storage file for external changes");
+        Ok(Some(watcher))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::JsonStorage;
+    use crate::storage::tests::BackendExt;
+    use crate::storage::tests::{create_test_device, temp_storage_path};
+    use std::time::{Duration, Instant};
+    use tempfile::TempDir;
+
+    #[test]
+    fn reconcile_reports_added_removed_and_changed_devices() {
+        let kept = create_test_device("Kept");
+        let edited = create_test_device("Edited");
+        let dropped = create_test_device("Dropped");
+        let current = vec![kept.clone(), edited.clone(), dropped];
+
+        let new = create_test_device("New");
+        let loaded = vec![
+            kept.clone(),
+            Device {
+                port: 7,
+                ..edited.clone()
+            },
+            new,
+        ];
+        let (devices, summary) = reconcile(&current, loaded);
+
+        assert_eq!(
+            summary,
+            ReloadSummary {
+                added: vec!["New".to_string()],
+                removed: vec!["Dropped".to_string()],
+                changed: vec!["Edited".to_string()],
+            }
+        );
+        assert_eq!(
+            summary.to_string(),
+            "added: New; removed: Dropped; changed: Edited"
+        );
+        assert_eq!(devices[0], kept);
+        assert_eq!(devices[1].revision, edited.revision + 1);
+    }
+
+    #[test]
+    fn reconcile_ignores_revision_only_differences() {
+        let mut device = create_test_device("A");
+        device.revision = 4;
+        let on_disk = Device {
+            revision: 1,
+            ..device.clone()
+        };
+
+        let (devices, summary) = reconcile(std::slice::from_ref(&device), vec![on_disk]);
+        assert!(summary.is_empty());
+        assert_eq!(devices, [device]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn watcher_picks_up_external_edits() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_storage_path(&dir);
+        let storage = SharedStorage::new(
+            Box::new(JsonStorage::open(&path, 0).unwrap()),
+            Duration::ZERO,
+        );
+        storage.add(create_test_device("Original")).await.unwrap();
+        let _watcher = storage.watch().unwrap().unwrap();
+
+        let mut external = JsonStorage::open(&path, 0).unwrap();
+        external.add(create_test_device("Provisioned")).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while storage.get_all().len() < 2 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let names: Vec<String> = storage.get_all().iter().map(|d| d.name.clone()).collect();
+        assert_eq!(names, ["Original", "Provisioned"]);
+    }
+}
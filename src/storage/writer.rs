@@ -0,0 +1,274 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::RwLock;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, error};
+
+use super::{DeviceIndex, DeviceTable, Mutation, ReloadSummary, StorageBackend, StorageError};
+
+pub(super) enum Command {
+    Apply {
+        mutations: Vec<Mutation>,
+        done: oneshot::Sender<Result<(), StorageError>>,
+    },
+    Reload {
+        done: oneshot::Sender<Result<ReloadSummary, StorageError>>,
+    },
+}
+
+type Batch = (Vec<Mutation>, oneshot::Sender<Result<(), StorageError>>);
+
+/// Owns the backend and performs every disk access on the blocking pool, so
+/// request handlers only ever wait on their own acknowledgement.
+///
+/// Mutations that arrive within `delay` of each other, or while a write is
+/// still in flight, are saved together. Each caller is acknowledged once the
+/// write containing its mutations is durable, and the published `index`
+/// only change after that point.
+pub(super) async fn run(
+    mut backend: Box<dyn StorageBackend>,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+    index: Arc<RwLock<Arc<DeviceIndex>>>,
+    delay: Duration,
+) {
+    // The backend's devices, to check each batch against before writing.
+    let mut preview = DeviceTable::from(backend.get_all());
+    let mut next = None;
+    loop {
+        let command = match next.take() {
+            Some(command) => command,
+            None => match commands.recv().await {
+                Some(command) => command,
+                None => break,
+            },
+        };
+        match command {
+            Command::Reload { done } => {
+                let (returned, result) = blocking(backend, |backend| backend.reload()).await;
+                backend = returned;
+                if result.is_ok() {
+                    preview = backend.get_all().into();
+                    publish(&index, backend.as_ref());
+                }
+                let _ = done.send(result);
+            }
+            Command::Apply { mutations, done } => {
+                let mut batches = vec![(mutations, done)];
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+                while let Ok(command) = commands.try_recv() {
+                    match command {
+                        Command::Apply { mutations, done } => batches.push((mutations, done)),
+                        other => {
+                            next = Some(other);
+                            break;
+                        }
+                    }
+                }
+                backend = commit(backend, batches, &index, &mut preview).await;
+            }
+        }
+    }
+    debug!("Storage writer stopped");
+}
+
+/// Saves the batches in a single write. Batches that cannot be applied are
+/// rejected on their own; if the combined write fails, each batch is retried
+/// separately so every caller learns whether its own change was kept.
+async fn commit(
+    mut backend: Box<dyn StorageBackend>,
+    batches: Vec<Batch>,
+    index: &RwLock<Arc<DeviceIndex>>,
+    preview: &mut DeviceTable,
+) -> Box<dyn StorageBackend> {
+    let mut accepted = Vec::with_capacity(batches.len());
+    for (mutations, done) in batches {
+        match preview.apply(mutations.clone()) {
+            Ok(changes) => accepted.push((mutations, done, changes)),
+            Err(err) => {
+                let _ = done.send(Err(err));
+            }
+        }
+    }
+    if accepted.is_empty() {
+        return backend;
+    }
+
+    let combined: Vec<Mutation> = accepted
+        .iter()
+        .flat_map(|(mutations, _, _)| mutations.iter().cloned())
+        .collect();
+    debug!(
+        batches = accepted.len(),
+        mutations = combined.len(),
+        "Writing storage changes"
+    );
+    let (returned, result) = blocking(backend, |backend| backend.apply(combined)).await;
+    backend = returned;
+
+    match result {
+        Ok(()) => {
+            publish(index, backend.as_ref());
+            for (_, done, _) in accepted {
+                let _ = done.send(Ok(()));
+            }
+        }
+        Err(err) if accepted.len() == 1 => {
+            error!(error = %err, "Failed to write storage changes");
+            let (_, done, changes) = accepted.remove(0);
+            preview.undo(changes);
+            let _ = done.send(Err(err));
+        }
+        Err(err) => {
+            error!(error = %err, "Failed to write combined storage changes, retrying one by one");
+            for (mutations, done, _) in accepted {
+                let (returned, result) =
+                    blocking(backend, |backend| backend.apply(mutations)).await;
+                backend = returned;
+                if result.is_ok() {
+                    publish(index, backend.as_ref());
+                }
+                let _ = done.send(result);
+            }
+            *preview = backend.get_all().into();
+        }
+    }
+    backend
+}
+
+/// Makes the backend's devices visible to readers.
+fn publish(index: &RwLock<Arc<DeviceIndex>>, backend: &dyn StorageBackend) {
+    let published = Arc::new(DeviceIndex::new(backend.get_all()));
+    *index.write() = published;
+}
+
+/// Runs `work` on the blocking pool, handing the backend back afterwards.
+async fn blocking<T: Send + 'static>(
+    mut backend: Box<dyn StorageBackend>,
+    work: impl FnOnce(&mut dyn StorageBackend) -> Result<T, StorageError> + Send + 'static,
+) -> (Box<dyn StorageBackend>, Result<T, StorageError>) {
+    tokio::task::spawn_blocking(move || {
+        let result = work(backend.as_mut());
+        (backend, result)
+    })
+    .await
+    .expect("storage backend panicked")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Device;
+    use crate::storage::SharedStorage;
+    use crate::storage::tests::create_test_device;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    /// Counts writes and can be told to fail or stall them.
+    #[derive(Debug, Default)]
+    struct RecordingBackend {
+        devices: DeviceTable,
+        writes: Arc<AtomicUsize>,
+        fail: Arc<AtomicBool>,
+        stall: Duration,
+    }
+
+    impl StorageBackend for RecordingBackend {
+        fn apply(&mut self, mutations: Vec<Mutation>) -> Result<(), StorageError> {
+            let changes = self.devices.apply(mutations)?;
+            std::thread::sleep(self.stall);
+            if self.fail.load(Ordering::SeqCst) {
+                self.devices.undo(changes);
+                return Err(std::io::Error::other("disk full").into());
+            }
+            self.writes.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn get_all(&self) -> Vec<Device> {
+            self.devices.to_vec()
+        }
+    }
+
+    #[tokio::test]
+    async fn rapid_changes_are_saved_in_one_write() {
+        let backend = RecordingBackend::default();
+        let writes = Arc::clone(&backend.writes);
+        let storage = SharedStorage::new(Box::new(backend), Duration::from_millis(50));
+
+        let adds: Vec<_> = (0..5)
+            .map(|i| {
+                let storage = storage.clone();
+                tokio::spawn(async move {
+                    storage
+                        .add(create_test_device(&format!("Device {i}")))
+                        .await
+                })
+            })
+            .collect();
+        for add in adds {
+            add.await.unwrap().unwrap();
+        }
+
+        assert_eq!(writes.load(Ordering::SeqCst), 1);
+        assert_eq!(storage.get_all().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn invalid_batch_is_rejected_without_blocking_others() {
+        let backend = RecordingBackend::default();
+        let writes = Arc::clone(&backend.writes);
+        let storage = SharedStorage::new(Box::new(backend), Duration::from_millis(50));
+
+        let (added, removed) = tokio::join!(
+            storage.add(create_test_device("Kept")),
+            storage.apply(vec![Mutation::Remove("missing".to_string())]),
+        );
+
+        added.unwrap();
+        assert!(matches!(removed, Err(StorageError::NotFound(_))));
+        assert_eq!(writes.load(Ordering::SeqCst), 1);
+        assert_eq!(storage.get_all().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn failed_write_is_reported_to_every_caller() {
+        let backend = RecordingBackend::default();
+        let fail = Arc::clone(&backend.fail);
+        let storage = SharedStorage::new(Box::new(backend), Duration::from_millis(50));
+        storage.add(create_test_device("Existing")).await.unwrap();
+        fail.store(true, Ordering::SeqCst);
+
+        let (first, second) = tokio::join!(
+            storage.add(create_test_device("A")),
+            storage.add(create_test_device("B")),
+        );
+
+        assert!(matches!(first, Err(StorageError::Io(_))));
+        assert!(matches!(second, Err(StorageError::Io(_))));
+        let names: Vec<String> = storage.get_all().iter().map(|d| d.name.clone()).collect();
+        assert_eq!(names, ["Existing"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reads_do_not_wait_for_writes() {
+        let backend = RecordingBackend {
+            stall: Duration::from_millis(300),
+            ..RecordingBackend::default()
+        };
+        let storage = SharedStorage::new(Box::new(backend), Duration::ZERO);
+
+        let writer = storage.clone();
+        let write = tokio::spawn(async move { writer.add(create_test_device("Slow")).await });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let started = Instant::now();
+        assert!(storage.get_all().is_empty());
+        assert!(started.elapsed() < Duration::from_millis(100));
+
+        write.await.unwrap().unwrap();
+        assert_eq!(storage.get_all().len(), 1);
+    }
+}
@@ -14,15 +14,22 @@ use axum::{
     routing::{get, post},
 };
 
+use axum_extra::extract::Query;
+
 use crate::{
+    api::{
+        devices::{DeviceQuery, ExportFormat, ExportQuery, ImportQuery},
+        wol::WakeQuery,
+    },
     auth::AuthenticatedUser,
-    devices::ArpError,
+    devices::{ArpError, Imported},
     error::ApiError,
-    storage::SharedStorage,
+    models::ValidationError,
+    storage::{SharedStorage, StorageError},
     web::{
         error::{WebError, WebResult, api_result, form_result},
-        forms::{ArpLookupForm, DeviceForm, ImportDevicesForm},
-        views::ToastKind,
+        forms::{ArpLookupForm, DeviceForm, GridFilter, ImportDevicesForm, ReorderForm},
+        views::{ImportState, ToastKind},
     },
 };
 
@@ -31,12 +38,18 @@ pub fn router() -> Router {
         .route("/", get(home))
         .route("/devices", get(devices_fragment).post(create_device))
         .route("/devices/new", get(new_device_modal))
+        .route("/devices/interface-row", get(interface_row))
         .route("/devices/{id}/edit", get(edit_device_modal))
         .route("/devices/{id}/update", post(update_device))
         .route("/devices/{id}/delete", post(delete_device))
+        .route("/devices/{id}/restore", post(restore_device))
+        .route("/devices/trash", get(trash_modal))
         .route("/devices/{id}/wake", post(wake_device))
+        .route("/devices/{id}/favorite", post(toggle_favorite))
+        .route("/devices/reorder", post(reorder_devices))
         .route("/devices/export", get(export_devices))
         .route("/devices/import", post(import_devices))
+        .route("/devices/import/preview", post(preview_import))
         .route("/arp-lookup", post(arp_lookup))
         .route("/transfer", get(transfer_modal))
 }
@@ -45,18 +58,25 @@ async fn home(
     Extension(storage): Extension<SharedStorage>,
     authenticated_user: Option<Extension<AuthenticatedUser>>,
 ) -> impl IntoResponse {
-    let devices = crate::devices::list_devices(&storage);
+    let devices = crate::devices::list_devices(&storage, &DeviceQuery::default());
     let username = authenticated_user.as_ref().map(|user| user.0.username());
     views::home_page(&devices, username)
 }
 
-async fn devices_fragment(Extension(storage): Extension<SharedStorage>) -> impl IntoResponse {
-    let devices = crate::devices::list_devices(&storage);
-    views::device_grid(&devices)
+async fn devices_fragment(
+    Extension(storage): Extension<SharedStorage>,
+    Query(query): Query<DeviceQuery>,
+) -> impl IntoResponse {
+    let devices = crate::devices::list_devices(&storage, &DeviceQuery::default());
+    views::device_grid(&devices, &query)
 }
 
 async fn new_device_modal() -> impl IntoResponse {
-    views::device_modal(None, None)
+    views::device_modal(None, None, &[])
+}
+
+async fn interface_row() -> impl IntoResponse {
+    views::interface_row(None, &[])
 }
 
 async fn edit_device_modal(
@@ -66,50 +86,117 @@ async fn edit_device_modal(
     let device = storage
         .get(&id)
         .ok_or_else(|| WebError::Form(format!("Device not found: {id}")))?;
-    Ok(views::device_modal(Some(&device), None))
+    Ok(views::device_modal(
+        Some(&device.id),
+        Some(&DeviceForm::from_device(&device)),
+        &[],
+    ))
 }
 
 async fn create_device(
     Extension(storage): Extension<SharedStorage>,
-    Form(form): Form<DeviceForm>,
+    axum_extra::extract::Form(form): axum_extra::extract::Form<DeviceForm>,
 ) -> Response {
-    let req = match form_result(form.into_create_request()) {
-        Ok(req) => req,
-        Err(err) => return device_form_error(None, &err.message()),
-    };
-
-    if let Err(err) = api_result(crate::devices::create_device(&storage, req)) {
-        return device_form_error(None, &err.message());
+    if let Err(err) =
+        api_result(crate::devices::create_device(&storage, form.to_create_request()).await)
+    {
+        return device_form_error(None, &form, &err);
     }
 
-    let devices = crate::devices::list_devices(&storage);
-    views::grid_with_toast(&devices, ToastKind::Success, "Device created").into_response()
+    let devices = crate::devices::list_devices(&storage, &DeviceQuery::default());
+    views::grid_with_toast(
+        &devices,
+        &DeviceQuery::default(),
+        ToastKind::Success,
+        "Device created",
+    )
+    .into_response()
 }
 
 async fn update_device(
     Extension(storage): Extension<SharedStorage>,
     Path(id): Path<String>,
-    Form(form): Form<DeviceForm>,
+    axum_extra::extract::Form(mut form): axum_extra::extract::Form<DeviceForm>,
 ) -> Response {
-    let existing = storage.get(&id);
-    let req = match form_result(form.into_update_request()) {
-        Ok(req) => req,
-        Err(err) => return device_form_error(existing.as_ref(), &err.message()),
-    };
-
-    if let Err(err) = api_result(crate::devices::update_device(&storage, &id, req)) {
-        return device_form_error(existing.as_ref(), &err.message());
+    let if_match = form.if_match();
+    match api_result(
+        crate::devices::patch_device(&storage, &id, form.to_patch_request(), if_match.as_deref())
+            .await,
+    ) {
+        Ok(_) => {}
+        Err(WebError::Api(ApiError::Storage(StorageError::Stale { current, .. }))) => {
+            // Keep the typed values but move to the current revision, so a
+            // second save deliberately overwrites the other change.
+            form.revision = Some(current);
+            let err = WebError::Form(
+                "Someone else changed this device while you were editing. \
+                 Saving again will overwrite their changes."
+                    .to_string(),
+            );
+            return device_form_error(Some(&id), &form, &err);
+        }
+        Err(err) => return device_form_error(Some(&id), &form, &err),
     }
 
-    let devices = crate::devices::list_devices(&storage);
-    views::grid_with_toast(&devices, ToastKind::Success, "Device updated").into_response()
+    let devices = crate::devices::list_devices(&storage, &DeviceQuery::default());
+    views::grid_with_toast(
+        &devices,
+        &DeviceQuery::default(),
+        ToastKind::Success,
+        "Device updated",
+    )
+    .into_response()
 }
 
 async fn delete_device(
     Extension(storage): Extension<SharedStorage>,
     Path(id): Path<String>,
+    axum_extra::extract::Form(filter): axum_extra::extract::Form<GridFilter>,
+) -> Response {
+    if let Err(err) = api_result(crate::devices::delete_device(&storage, &id).await) {
+        return (
+            err.status_code(),
+            views::toast_fragment(ToastKind::Error, &err.message()),
+        )
+            .into_response();
+    }
+
+    let devices = crate::devices::list_devices(&storage, &DeviceQuery::default());
+    views::grid_with_undo_toast(
+        &devices,
+        &filter.query(),
+        "Device removed",
+        &format!("/devices/{id}/restore"),
+    )
+    .into_response()
+}
+
+async fn restore_device(
+    Extension(storage): Extension<SharedStorage>,
+    Path(id): Path<String>,
+    axum_extra::extract::Form(filter): axum_extra::extract::Form<GridFilter>,
+) -> Response {
+    let query = filter.query();
+    let restored = api_result(crate::devices::restore_device(&storage, &id).await);
+    let devices = crate::devices::list_devices(&storage, &DeviceQuery::default());
+    match restored {
+        Ok(_) => views::grid_with_toast(&devices, &query, ToastKind::Success, "Device restored"),
+        Err(err) => views::grid_with_toast(&devices, &query, ToastKind::Error, &err.message()),
+    }
+    .into_response()
+}
+
+async fn trash_modal(Extension(storage): Extension<SharedStorage>) -> impl IntoResponse {
+    let devices = crate::devices::list_trash(&storage);
+    views::trash_modal(&devices, crate::config::get().storage.trash_retention_days)
+}
+
+async fn toggle_favorite(
+    Extension(storage): Extension<SharedStorage>,
+    Path(id): Path<String>,
+    axum_extra::extract::Form(filter): axum_extra::extract::Form<GridFilter>,
 ) -> Response {
-    if let Err(err) = api_result(crate::devices::delete_device(&storage, &id)) {
+    if let Err(err) = api_result(crate::devices::toggle_favorite(&storage, &id).await) {
         return (
             err.status_code(),
             views::toast_fragment(ToastKind::Error, &err.message()),
@@ -117,15 +204,39 @@ async fn delete_device(
             .into_response();
     }
 
-    let devices = crate::devices::list_devices(&storage);
-    views::grid_with_toast(&devices, ToastKind::Success, "Device removed").into_response()
+    let devices = crate::devices::list_devices(&storage, &DeviceQuery::default());
+    views::device_grid(&devices, &filter.query()).into_response()
+}
+
+async fn reorder_devices(
+    Extension(storage): Extension<SharedStorage>,
+    axum_extra::extract::Form(form): axum_extra::extract::Form<ReorderForm>,
+) -> Response {
+    let query = DeviceQuery {
+        tag: form.tag,
+        q: form.q,
+        ..DeviceQuery::default()
+    };
+    match api_result(crate::devices::reorder_devices(&storage, &form.id).await) {
+        Ok(devices) => views::device_grid(&devices, &query).into_response(),
+        Err(err) => {
+            let devices = crate::devices::list_devices(&storage, &DeviceQuery::default());
+            views::grid_with_toast(&devices, &query, ToastKind::Error, &err.message())
+                .into_response()
+        }
+    }
 }
 
 async fn wake_device(
     Extension(storage): Extension<SharedStorage>,
     Path(id): Path<String>,
+    Query(query): Query<WakeQuery>,
 ) -> Response {
-    match api_result(crate::devices::wake_device(&storage, &id)) {
+    match api_result(crate::devices::wake_device(
+        &storage,
+        &id,
+        query.interface.as_deref(),
+    )) {
         Ok(()) => views::toast_fragment(ToastKind::Success, "Wake signal sent").into_response(),
         Err(err) => (
             err.status_code(),
@@ -135,9 +246,11 @@ async fn wake_device(
     }
 }
 
-async fn arp_lookup(Form(form): Form<ArpLookupForm>) -> Response {
+async fn arp_lookup(
+    axum_extra::extract::Form(form): axum_extra::extract::Form<ArpLookupForm>,
+) -> Response {
     let ip = form.ip_address.trim();
-    let current_mac = form.mac_address.unwrap_or_default();
+    let current_mac = form.interface_mac.into_iter().next().unwrap_or_default();
     if ip.is_empty() {
         return views::mac_lookup_error(&current_mac, "Enter an IP address first").into_response();
     }
@@ -156,50 +269,96 @@ async fn arp_lookup(Form(form): Form<ArpLookupForm>) -> Response {
 }
 
 async fn transfer_modal() -> impl IntoResponse {
-    views::transfer_modal(None)
+    views::transfer_modal(&ImportState::default())
+}
+
+/// Runs the import submitted through the transfer modal.
+async fn run_import(
+    storage: &SharedStorage,
+    form: &ImportDevicesForm,
+    dry_run: bool,
+) -> WebResult<Imported> {
+    let query = ImportQuery {
+        format: form.format,
+        mode: form.mode,
+        dry_run,
+        id_conflict: form.id_conflict,
+    };
+    let content = form_result(form.content())?;
+    api_result(crate::devices::import_file(storage, content, &query).await)
+}
+
+/// Shows what the import would change, with a button to apply it.
+async fn preview_import(
+    Extension(storage): Extension<SharedStorage>,
+    Form(form): Form<ImportDevicesForm>,
+) -> Response {
+    match run_import(&storage, &form, true).await {
+        Ok(imported) => views::transfer_modal(&ImportState {
+            payload: &form.payload,
+            format: form.format,
+            mode: form.mode,
+            id_conflict: form.id_conflict,
+            preview: Some(&imported.report),
+            ..ImportState::default()
+        })
+        .into_response(),
+        Err(err) => transfer_error(&err, &form),
+    }
 }
 
 async fn import_devices(
     Extension(storage): Extension<SharedStorage>,
     Form(form): Form<ImportDevicesForm>,
 ) -> Response {
-    let req = match form_result(form.into_import_requests()) {
-        Ok(req) => req,
-        Err(err) => return transfer_error(&err.message()),
+    let report = match run_import(&storage, &form, false).await {
+        Ok(imported) => imported.report,
+        Err(err) => return transfer_error(&err, &form),
     };
 
-    let count = req.len();
-    if let Err(err) = api_result(crate::devices::import_devices(&storage, req)) {
-        return transfer_error(&err.message());
+    let mut message = format!(
+        "Imported {} device(s)",
+        report.created.len() + report.updated.len()
+    );
+    if !report.skipped.is_empty() {
+        message.push_str(&format!(", {} unchanged", report.skipped.len()));
     }
-
-    let devices = crate::devices::list_devices(&storage);
+    if !report.removed.is_empty() {
+        message.push_str(&format!(", {} moved to the trash", report.removed.len()));
+    }
+    let devices = crate::devices::list_devices(&storage, &DeviceQuery::default());
     views::grid_with_toast(
         &devices,
+        &DeviceQuery::default(),
         ToastKind::Success,
-        &format!("Imported {count} device(s)"),
+        &message,
     )
     .into_response()
 }
 
-async fn export_devices(Extension(storage): Extension<SharedStorage>) -> Response {
-    let exported = crate::devices::export_devices(&storage);
-    let body = match serde_json::to_string_pretty(&exported) {
+async fn export_devices(
+    Extension(storage): Extension<SharedStorage>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    let format = query.format.unwrap_or(ExportFormat::Json);
+    let body = crate::devices::export_file(&storage, format).map_err(|err| err.to_string());
+    let body = match body {
         Ok(body) => body,
         Err(err) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                views::error_page(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string()),
+                views::error_page(StatusCode::INTERNAL_SERVER_ERROR, &err),
             )
                 .into_response();
         }
     };
+    let (name, extension) = format.file_name();
     let date = time::OffsetDateTime::now_utc().date();
-    let filename = format!("jump-devices-{date}.json");
+    let filename = format!("jump-{name}-{date}.{extension}");
 
     (
         [
-            (CONTENT_TYPE, "application/json; charset=utf-8".to_string()),
+            (CONTENT_TYPE, format.content_type().to_string()),
             (
                 CONTENT_DISPOSITION,
                 format!("attachment; filename=\"{filename}\""),
@@ -210,20 +369,39 @@ async fn export_devices(Extension(storage): Extension<SharedStorage>) -> Respons
         .into_response()
 }
 
-fn device_form_error(device: Option<&crate::models::Device>, message: &str) -> Response {
+fn device_form_error(device_id: Option<&str>, form: &DeviceForm, err: &WebError) -> Response {
     (
         StatusCode::BAD_REQUEST,
         [("HX-Retarget", "#modal-root"), ("HX-Reswap", "innerHTML")],
-        views::device_modal(device, Some(message)),
+        views::device_modal(device_id, Some(form), &err.field_errors()),
     )
         .into_response()
 }
 
-fn transfer_error(message: &str) -> Response {
+/// Reopens the transfer modal with the error. Invalid entries are listed one
+/// per line, so every bad row of a large import can be fixed in one go.
+fn transfer_error(err: &WebError, form: &ImportDevicesForm) -> Response {
+    let problems = match err {
+        WebError::Api(ApiError::Validation(ValidationError::Fields(errors))) => errors.as_slice(),
+        _ => &[],
+    };
+    let message = if problems.is_empty() {
+        err.message()
+    } else {
+        "Nothing was imported; fix these entries and try again:".to_string()
+    };
     (
         StatusCode::BAD_REQUEST,
         [("HX-Retarget", "#modal-root"), ("HX-Reswap", "innerHTML")],
-        views::transfer_modal(Some(message)),
+        views::transfer_modal(&ImportState {
+            payload: &form.payload,
+            format: form.format,
+            mode: form.mode,
+            id_conflict: form.id_conflict,
+            error: Some(&message),
+            problems,
+            preview: None,
+        }),
     )
         .into_response()
 }
@@ -233,7 +411,10 @@ mod tests {
     use super::*;
     use axum::{
         body::{Body, to_bytes},
-        http::{Method, Request},
+        http::{
+            HeaderValue, Method, Request,
+            header::{LINK, WARNING},
+        },
     };
     use tempfile::TempDir;
     use tower::ServiceExt;
@@ -242,7 +423,8 @@ mod tests {
         let _ = crate::config::init();
         let dir = TempDir::new().unwrap();
         let path = dir.path().join("devices.json");
-        let storage = SharedStorage::load(path.to_str().unwrap()).unwrap();
+        let json = crate::storage::JsonStorage::open(path.to_str().unwrap(), 0).unwrap();
+        let storage = SharedStorage::new(Box::new(json), std::time::Duration::ZERO);
         (crate::app::build_app(storage.clone(), None), storage, dir)
     }
 
@@ -251,6 +433,11 @@ mod tests {
         String::from_utf8(bytes.to_vec()).unwrap()
     }
 
+    fn test_device(name: &str, mac: &str) -> crate::models::Device {
+        let interfaces = vec![crate::models::NetworkInterface::new(mac).unwrap()];
+        crate::models::Device::new(name.to_string(), interfaces, None, 9, None)
+    }
+
     fn form_request(uri: &str, body: &str) -> Request<Body> {
         Request::builder()
             .method(Method::POST)
@@ -284,7 +471,7 @@ mod tests {
         let response = app
             .oneshot(form_request(
                 "/devices",
-                "name=Gaming%20PC&mac_address=AA%3ABB%3ACC%3ADD%3AEE%3AFF&ip_address=192.168.1.10&port=9&description=Main",
+                "name=Gaming%20PC&interface_mac=AA%3ABB%3ACC%3ADD%3AEE%3AFF&ip_address=192.168.1.10&port=9&description=Main",
             ))
             .await
             .unwrap();
@@ -303,7 +490,7 @@ mod tests {
         let response = app
             .oneshot(form_request(
                 "/devices",
-                "name=Gaming%20PC&mac_address=bad&port=9",
+                "name=Gaming%20PC&interface_mac=bad&port=9",
             ))
             .await
             .unwrap();
@@ -322,21 +509,14 @@ mod tests {
     #[tokio::test]
     async fn update_device_validation_error_keeps_modal_root_closable() {
         let (app, storage, _dir) = app();
-        let device = crate::models::Device::new(
-            "Gaming PC".to_string(),
-            "AA:BB:CC:DD:EE:FF".to_string(),
-            None,
-            9,
-            None,
-        )
-        .unwrap();
+        let device = test_device("Gaming PC", "AA:BB:CC:DD:EE:FF");
         let id = device.id.clone();
-        storage.add(device).unwrap();
+        storage.add(device).await.unwrap();
 
         let response = app
             .oneshot(form_request(
                 &format!("/devices/{id}/update"),
-                "name=Gaming%20PC&mac_address=bad&port=9",
+                "name=Gaming%20PC&interface_mac=bad&port=9",
             ))
             .await
             .unwrap();
@@ -347,12 +527,218 @@ mod tests {
             "#modal-root"
         );
         assert_eq!(response.headers().get("HX-Reswap").unwrap(), "innerHTML");
-        assert_eq!(storage.get(&id).unwrap().mac_address, "AA:BB:CC:DD:EE:FF");
+        assert_eq!(
+            storage.get(&id).unwrap().interfaces[0]
+                .mac_address
+                .to_string(),
+            "AA:BB:CC:DD:EE:FF"
+        );
         let body = response_text(response).await;
         assert!(body.contains("Invalid MAC address format"));
         assert!(body.contains("Edit Device"));
     }
 
+    #[tokio::test]
+    async fn stale_revision_is_rejected_by_api_and_edit_form() {
+        let (app, storage, _dir) = app();
+        let device = test_device("Gaming PC", "AA:BB:CC:DD:EE:FF");
+        let id = device.id.clone();
+        storage.add(device).await.unwrap();
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/devices/{id}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get("ETag").unwrap(), "\"1\"");
+
+        let patch = |if_match: &str, name: &str| {
+            Request::builder()
+                .method(Method::PATCH)
+                .uri(format!("/api/devices/{id}"))
+                .header(CONTENT_TYPE, "application/json")
+                .header(
+                    "If-Match",
+                    HeaderValue::from_bytes(if_match.as_bytes()).unwrap(),
+                )
+                .body(Body::from(format!(r#"{{"name":"{name}"}}"#)))
+                .unwrap()
+        };
+        let response = app
+            .clone()
+            .oneshot(patch("\"1\"", "Renamed"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get("ETag").unwrap(), "\"2\"");
+
+        let response = app
+            .clone()
+            .oneshot(patch("\"1\"", "Clobbered"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+        assert_eq!(storage.get(&id).unwrap().name, "Renamed");
+
+        let response = app
+            .clone()
+            .oneshot(patch("\"2é\"", "Clobbered"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(response_text(response).await.contains("If-Match"));
+        assert_eq!(storage.get(&id).unwrap().name, "Renamed");
+
+        let response = app
+            .oneshot(form_request(
+                &format!("/devices/{id}/update"),
+                "name=Clobbered&interface_mac=AA:BB:CC:DD:EE:FF&port=9&revision=1",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response_text(response).await;
+        assert!(body.contains("Someone else changed this device"));
+        assert!(body.contains("name=\"revision\" value=\"2\""));
+        assert!(body.contains("value=\"Clobbered\""));
+        assert_eq!(storage.get(&id).unwrap().name, "Renamed");
+    }
+
+    #[tokio::test]
+    async fn patch_clears_null_fields_and_put_replaces_device() {
+        let (app, storage, _dir) = app();
+        let mut device = test_device("Gaming PC", "AA:BB:CC:DD:EE:FF")
+            .with_tags(vec!["lab".to_string()])
+            .with_metadata([("owner".to_string(), "infra".to_string())].into());
+        device.ip_address = Some("192.168.1.10".to_string());
+        device.description = Some("Main".to_string());
+        let id = device.id.clone();
+        storage.add(device).await.unwrap();
+
+        let request = |method: Method, body: &str| {
+            Request::builder()
+                .method(method)
+                .uri(format!("/api/devices/{id}"))
+                .header(CONTENT_TYPE, "application/merge-patch+json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(request(
+                Method::PATCH,
+                r#"{"ip_address":null,"metadata":{"owner":null,"rack":"A1"}}"#,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let patched = storage.get(&id).unwrap();
+        assert_eq!(patched.ip_address, None);
+        assert_eq!(patched.description.as_deref(), Some("Main"));
+        assert_eq!(patched.tags, vec!["lab"]);
+        assert_eq!(
+            patched.metadata,
+            [("rack".to_string(), "A1".to_string())].into()
+        );
+
+        let response = app
+            .clone()
+            .oneshot(request(Method::PATCH, r#"{"name":null}"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app
+            .oneshot(request(
+                Method::PUT,
+                r#"{"name":"Replaced","mac_address":"AA:BB:CC:DD:EE:FF"}"#,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let replaced = storage.get(&id).unwrap();
+        assert_eq!(replaced.name, "Replaced");
+        assert_eq!(replaced.description, None);
+        assert!(replaced.tags.is_empty());
+        assert!(replaced.metadata.is_empty());
+    }
+
+    #[tokio::test]
+    async fn create_device_rejects_duplicate_mac_in_other_notation() {
+        let (app, storage, _dir) = app();
+        let device = test_device("Gaming PC", "AA:BB:CC:DD:EE:FF");
+        storage.add(device).await.unwrap();
+
+        let response = app
+            .oneshot(form_request(
+                "/devices",
+                "name=Other&interface_mac=aabb.ccdd.eeff&port=9",
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(storage.get_all().len(), 1);
+        let body = response_text(response).await;
+        assert!(body.contains("already used by Gaming PC"));
+    }
+
+    #[tokio::test]
+    async fn create_device_reports_every_invalid_field() {
+        let (app, storage, _dir) = app();
+        let response = app
+            .clone()
+            .oneshot(form_request(
+                "/devices",
+                "name=+&interface_mac=bad&ip_address=not+a+host&port=70000&description=Kept",
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(storage.get_all().is_empty());
+        let body = response_text(response).await;
+        assert!(body.contains("Name is required"));
+        assert!(body.contains("Invalid MAC address format"));
+        assert!(body.contains("Port must be between 1 and 65535"));
+        assert_eq!(body.matches("aria-invalid=\"true\"").count(), 3);
+        assert!(body.contains("value=\"Kept\""));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("/api/devices")
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(Body::from(
+                        r#"{"name":"","interfaces":[{"mac_address":"AA:BB:CC:DD:EE:FF"},{"mac_address":"AA:BB:CC:DD:EE:01","broadcast":"10.0"}],"ip_address":"-nas","port":0}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = serde_json::from_str(&response_text(response).await).unwrap();
+        let fields: Vec<&str> = body["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|error| error["field"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            fields,
+            ["interfaces[1].broadcast", "name", "ip_address", "port"]
+        );
+    }
+
     #[tokio::test]
     async fn import_valid_json_updates_grid() {
         let (app, storage, _dir) = app();
@@ -373,21 +759,811 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn export_devices_returns_download_json() {
-        let (app, _storage, _dir) = app();
+    async fn import_preview_saves_nothing_until_confirmed() {
+        let (app, storage, _dir) = app();
+        let existing = test_device("Server", "AA:BB:CC:DD:EE:01");
+        let id = existing.id.clone();
+        storage.add(existing).await.unwrap();
+        let payload = r#"[{"name":"Renamed","mac_address":"aa-bb-cc-dd-ee-01"},{"name":"New","mac_address":"AA:BB:CC:DD:EE:02"}]"#;
+        let body = format!(
+            "mode=upsert&payload={}",
+            payload
+                .replace('"', "%22")
+                .replace(',', "%2C")
+                .replace(':', "%3A")
+                .replace('[', "%5B")
+                .replace(']', "%5D")
+                .replace('{', "%7B")
+                .replace('}', "%7D")
+        );
+
+        let response = app
+            .clone()
+            .oneshot(form_request("/devices/import/preview", &body))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let preview = response_text(response).await;
+        assert!(preview.contains("1 to create, 1 to update, 0 unchanged, 0 conflicting"));
+        assert!(preview.contains("Confirm import"));
+        assert!(preview.contains("<option value=\"upsert\" selected>"));
+        assert_eq!(storage.get_all().len(), 1);
+        assert_eq!(storage.get_all()[0].name, "Server");
+
+        let response = app
+            .oneshot(form_request("/devices/import", &body))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(
+            response_text(response)
+                .await
+                .contains("Imported 2 device(s)")
+        );
+        let devices = storage.get_all();
+        assert_eq!(devices.len(), 2);
+        assert_eq!(storage.get(&id).unwrap().name, "Renamed");
+    }
+
+    #[tokio::test]
+    async fn api_import_modes_and_dry_run() {
+        let (app, storage, _dir) = app();
+        storage
+            .add(test_device("Server", "AA:BB:CC:DD:EE:01"))
+            .await
+            .unwrap();
+        let import = |query: &str| {
+            Request::builder()
+                .method(Method::POST)
+                .uri(format!("/api/devices/import?{query}"))
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    r#"[{"name":"Server","mac_address":"AA:BB:CC:DD:EE:01"}]"#,
+                ))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(import("dry_run=true")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let report: serde_json::Value =
+            serde_json::from_str(&response_text(response).await).unwrap();
+        assert_eq!(report["dry_run"], true);
+        assert_eq!(report["conflicts"][0]["entry"], "[0]");
+        let response = app.clone().oneshot(import("mode=append")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app
+            .clone()
+            .oneshot(import("mode=upsert&dry_run=true"))
+            .await
+            .unwrap();
+        let report: serde_json::Value =
+            serde_json::from_str(&response_text(response).await).unwrap();
+        assert_eq!(report["skipped"].as_array().unwrap().len(), 1);
+
+        let response = app
+            .clone()
+            .oneshot(import("mode=replace&dry_run=true"))
+            .await
+            .unwrap();
+        let report: serde_json::Value =
+            serde_json::from_str(&response_text(response).await).unwrap();
+        assert_eq!(report["removed"].as_array().unwrap().len(), 1);
+        assert_eq!(report["created"].as_array().unwrap().len(), 1);
+        assert!(crate::devices::list_trash(&storage).is_empty());
+
+        let response = app.oneshot(import("mode=replace")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(storage.get_all().len(), 1);
+        assert_eq!(crate::devices::list_trash(&storage).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn upsert_updates_a_device_once() {
+        let (app, storage, _dir) = app();
+        let mut device = test_device("Server", "AA:BB:CC:DD:EE:01");
+        device
+            .interfaces
+            .push(crate::models::NetworkInterface::new("AA:BB:CC:DD:EE:02").unwrap());
+        storage.add(device).await.unwrap();
+        let import = |query: &str| {
+            Request::builder()
+                .method(Method::POST)
+                .uri(format!("/api/devices/import?{query}"))
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    r#"[{"name":"A","mac_address":"AA:BB:CC:DD:EE:01"},{"name":"B","mac_address":"AA:BB:CC:DD:EE:02"}]"#,
+                ))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(import("mode=upsert&dry_run=true"))
+            .await
+            .unwrap();
+        let report: serde_json::Value =
+            serde_json::from_str(&response_text(response).await).unwrap();
+        assert_eq!(report["updated"].as_array().unwrap().len(), 1);
+        assert_eq!(report["conflicts"][0]["entry"], "[1]");
+        assert_eq!(
+            report["conflicts"][0]["reason"],
+            "Matches the same device as [0]"
+        );
+
+        let response = app.oneshot(import("mode=upsert")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(storage.get_all()[0].name, "Server");
+    }
+
+    #[tokio::test]
+    async fn backup_round_trip_keeps_ids() {
+        let (app, storage, _dir) = app();
+        let device = test_device("Server", "AA:BB:CC:DD:EE:01");
+        storage.add(device.clone()).await.unwrap();
         let response = app
+            .clone()
             .oneshot(
                 Request::builder()
-                    .uri("/devices/export")
+                    .uri("/api/devices/export?format=backup")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
-
         assert_eq!(response.status(), StatusCode::OK);
-        assert_eq!(
-            response.headers().get(CONTENT_TYPE).unwrap(),
+        let backup = response_text(response).await;
+        let import = |query: &str| {
+            Request::builder()
+                .method(Method::POST)
+                .uri(format!("/api/devices/import?{query}"))
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(backup.clone()))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(import("mode=replace")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let restored = storage.get_all();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].id, device.id);
+        assert_eq!(restored[0].created_at, device.created_at);
+
+        crate::devices::delete_device(&storage, &device.id)
+            .await
+            .unwrap();
+        let response = app.clone().oneshot(import("dry_run=true")).await.unwrap();
+        let report: serde_json::Value =
+            serde_json::from_str(&response_text(response).await).unwrap();
+        assert_eq!(report["conflicts"][0]["entry"], "[0]");
+        let response = app.clone().oneshot(import("")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(response_text(response).await.contains("[0].id"));
+
+        let response = app.oneshot(import("id_conflict=remap")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let remapped = storage.get_all();
+        assert_eq!(remapped.len(), 1);
+        assert_ne!(remapped[0].id, device.id);
+    }
+
+    #[tokio::test]
+    async fn import_detects_other_tools_and_lists_lost_fields() {
+        let (app, storage, _dir) = app();
+        let wolweb = r#"{"devices":[{"name":"Desktop","mac":"AA:BB:CC:DD:EE:01","ip":"192.168.1.255:9","icon":"pc"}]}"#;
+        let body = format!("payload={wolweb}");
+        let response = app
+            .clone()
+            .oneshot(form_request("/devices/import/preview", &body))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response_text(response).await;
+        assert!(body.contains("Read as wolweb devices.json"));
+        assert!(body.contains("<code>[0].icon</code>"));
+        assert!(storage.get_all().is_empty());
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("/api/devices/import?dry_run=true")
+                    .header(CONTENT_TYPE, "text/plain")
+                    .body(Body::from("aa:bb:cc:dd:ee:02 192.168.1.20 extra\n"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let report: serde_json::Value =
+            serde_json::from_str(&response_text(response).await).unwrap();
+        assert_eq!(report["format"], "ethers");
+        assert_eq!(report["created"][0]["name"], "192.168.1.20");
+        assert_eq!(report["warnings"][0]["field"], "line 1");
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("/api/devices/import")
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(Body::from(wolweb))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let warning = response.headers().get(WARNING).unwrap().to_str().unwrap();
+        assert!(warning.starts_with("299 - \"[0].icon: "));
+        assert_eq!(storage.get_all().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn import_csv_lists_every_bad_row() {
+        let (app, storage, _dir) = app();
+        let csv = "name,mac_address,port\nGood,AA:BB:CC:DD:EE:01,9\n,AA:BB:CC:DD:EE:02,9\nBad,AA:BB:CC:DD:EE:03,wake\n";
+        let body = format!("payload={}", csv.replace(',', "%2C").replace('\n', "%0A"));
+        let response = app
+            .clone()
+            .oneshot(form_request("/devices/import", &body))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response_text(response).await;
+        assert!(body.contains("<code>line 3.name</code>"));
+        assert!(body.contains("<code>line 4.port</code>"));
+        assert!(storage.get_all().is_empty());
+
+        let csv = "name,mac_address,location\nGood,AA:BB:CC:DD:EE:01,Office\n";
+        let body = format!("payload={}", csv.replace(',', "%2C").replace('\n', "%0A"));
+        let response = app
+            .oneshot(form_request("/devices/import", &body))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(
+            response_text(response)
+                .await
+                .contains("Imported 1 device(s)")
+        );
+        assert_eq!(storage.get_all()[0].metadata["location"], "Office");
+    }
+
+    #[tokio::test]
+    async fn api_negotiates_csv_for_export_and_import() {
+        let (app, storage, _dir) = app();
+        let device = test_device("Server", "AA:BB:CC:DD:EE:01")
+            .with_metadata([("rack".to_string(), "4".to_string())].into());
+        storage.add(device).await.unwrap();
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/api/devices/export")
+                    .header("accept", "text/csv")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE).unwrap(),
+            "text/csv; charset=utf-8"
+        );
+        let exported = response_text(response).await;
+        assert_eq!(
+            exported,
+            "name,mac_address,ip_address,port,description,tags,favorite,rack\n\
+             Server,AA:BB:CC:DD:EE:01,,9,,,false,4\n"
+        );
+
+        let import = |body: String| {
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/devices/import")
+                .header(CONTENT_TYPE, "text/csv")
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let response = app
+            .clone()
+            .oneshot(import(exported.replace("Server", "Copy")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = serde_json::from_str(&response_text(response).await).unwrap();
+        assert_eq!(body["errors"][0]["field"], "line 2.mac_address");
+
+        let response = app
+            .oneshot(import(
+                exported.replace("AA:BB:CC:DD:EE:01", "AA:BB:CC:DD:EE:02"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(storage.get_all().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn tag_filter_applies_to_fragment_and_api() {
+        let (app, storage, _dir) = app();
+        for (name, mac, tags) in [
+            ("Lab Box", "AA:BB:CC:DD:EE:01", vec!["lab".to_string()]),
+            (
+                "GPU Box",
+                "AA:BB:CC:DD:EE:03",
+                vec!["lab".to_string(), "gpu".to_string()],
+            ),
+            ("Office PC", "AA:BB:CC:DD:EE:02", vec!["office".to_string()]),
+        ] {
+            let device = test_device(name, mac).with_tags(tags);
+            storage.add(device).await.unwrap();
+        }
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/devices?tag=lab")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response_text(response).await;
+        assert!(body.contains("Lab Box"));
+        assert!(!body.contains("Office PC"));
+        assert!(body.contains("class=\"tag-filter\""));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/devices?tag=lab&tag=GPU")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let devices: Vec<crate::models::Device> =
+            serde_json::from_str(&response_text(response).await).unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].name, "GPU Box");
+    }
+
+    #[tokio::test]
+    async fn api_searches_sorts_and_pages_devices() {
+        let (app, storage, _dir) = app();
+        for (name, mac, ip) in [
+            ("Office PC", "AA:BB:CC:DD:EE:01", Some("192.168.1.20")),
+            ("nas", "AA:BB:CC:DD:EE:02", Some("192.168.1.3")),
+            ("Lab Box", "AA:BB:CC:DD:EE:03", None),
+            ("Media", "AA:BB:CC:DD:EE:04", Some("media.lan")),
+        ] {
+            let mut device = test_device(name, mac);
+            device.ip_address = ip.map(ToString::to_string);
+            storage.add(device).await.unwrap();
+        }
+        let list = |uri: &str| {
+            let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                let link = response
+                    .headers()
+                    .get(LINK)
+                    .map(|link| link.to_str().unwrap().to_string());
+                let body = response_text(response).await;
+                let names = serde_json::from_str::<Vec<crate::models::Device>>(&body)
+                    .map(|devices| devices.into_iter().map(|device| device.name).collect())
+                    .unwrap_or_else(|_| vec![body]);
+                (names, link)
+            }
+        };
+
+        let (names, link) = list("/api/devices?q=eeee:02").await;
+        assert!(names.is_empty());
+        assert_eq!(link, None);
+        let (names, _) = list("/api/devices?q=ddee02").await;
+        assert_eq!(names, ["nas"]);
+        let (names, _) = list("/api/devices?q=PC%20office").await;
+        assert_eq!(names, ["Office PC"]);
+
+        let (names, _) = list("/api/devices?sort=name").await;
+        assert_eq!(names, ["Lab Box", "Media", "nas", "Office PC"]);
+        let (names, _) = list("/api/devices?sort=ip&order=desc").await;
+        assert_eq!(names, ["Media", "Office PC", "nas", "Lab Box"]);
+
+        let (names, link) = list("/api/devices?sort=ip&limit=2").await;
+        assert_eq!(names, ["nas", "Office PC"]);
+        let link = link.unwrap();
+        let next = link
+            .split_once('<')
+            .and_then(|(_, rest)| rest.split_once('>'))
+            .map(|(next, _)| next.to_string())
+            .unwrap();
+        assert!(link.ends_with("</api/devices?sort=ip&limit=2>; rel=\"first\""));
+        let (names, link) = list(&next).await;
+        assert_eq!(names, ["Media", "Lab Box"]);
+        assert_eq!(
+            link.as_deref(),
+            Some("</api/devices?sort=ip&limit=2>; rel=\"first\"")
+        );
+
+        let (names, link) = list("/api/devices?sort=name&limit=2").await;
+        assert_eq!(names, ["Lab Box", "Media"]);
+        let media = storage
+            .get_all()
+            .iter()
+            .find(|device| device.name == "Media")
+            .cloned()
+            .unwrap();
+        crate::devices::delete_device(&storage, &media.id)
+            .await
+            .unwrap();
+        let next = link
+            .unwrap()
+            .split_once('<')
+            .and_then(|(_, rest)| rest.split_once('>'))
+            .map(|(next, _)| next.to_string())
+            .unwrap();
+        let (names, _) = list(&next).await;
+        assert_eq!(names, ["nas", "Office PC"]);
+        let cursor = next.split_once("cursor=").unwrap().1;
+        let (names, _) = list(&format!("/api/devices?sort=ip&cursor={cursor}")).await;
+        assert!(names[0].contains("cursor"));
+        let (names, _) = list("/api/devices?cursor=missing").await;
+        assert!(names[0].contains("cursor"));
+        let (names, _) = list("/api/devices?limit=0").await;
+        assert!(names[0].contains("limit"));
+    }
+
+    #[tokio::test]
+    async fn search_box_filters_the_grid() {
+        let (app, storage, _dir) = app();
+        for (name, mac) in [
+            ("Office PC", "AA:BB:CC:DD:EE:01"),
+            ("NAS", "AA:BB:CC:DD:EE:02"),
+        ] {
+            storage.add(test_device(name, mac)).await.unwrap();
+        }
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = response_text(response).await;
+        assert!(body.contains("hx-trigger=\"keyup changed delay:200ms, search\""));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/devices?q=nas")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = response_text(response).await;
+        assert!(body.contains("NAS"));
+        assert!(!body.contains("Office PC"));
+        assert!(body.contains("name=\"q\" value=\"nas\""));
+    }
+
+    #[tokio::test]
+    async fn create_device_with_extra_interface_rows() {
+        let (app, storage, _dir) = app();
+
+        let response = app
+            .clone()
+            .oneshot(form_request(
+                "/devices",
+                "name=Dual+NIC&port=9\
+                 &interface_mac=AA:BB:CC:DD:EE:01&interface_label=onboard&interface_broadcast=\
+                 &interface_mac=aa-bb-cc-dd-ee-02&interface_label=10GbE&interface_broadcast=10.0.0.255\
+                 &interface_mac=&interface_label=&interface_broadcast=",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let device = &storage.get_all()[0];
+        assert_eq!(device.interfaces.len(), 2);
+        assert_eq!(device.interfaces[0].label.as_deref(), Some("onboard"));
+        assert_eq!(device.interfaces[0].broadcast, None);
+        assert_eq!(
+            device.interfaces[1].mac_address.to_string(),
+            "AA:BB:CC:DD:EE:02"
+        );
+        assert_eq!(
+            device.interfaces[1].broadcast,
+            Some(std::net::Ipv4Addr::new(10, 0, 0, 255))
+        );
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri(format!(
+                        "/api/devices/{}/wake?interface=AA:BB:CC:DD:EE:FF",
+                        device.id
+                    ))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn metadata_from_form_is_stored_and_searchable() {
+        let (app, storage, _dir) = app();
+
+        let response = app
+            .clone()
+            .oneshot(form_request(
+                "/devices",
+                "name=Rack+Server&interface_mac=AA:BB:CC:DD:EE:01&port=9\
+                 &metadata_key=location&metadata_value=Rack+4\
+                 &metadata_key=owner&metadata_value=",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let device = &storage.get_all()[0];
+        assert_eq!(
+            device.metadata,
+            [("location".to_string(), "Rack 4".to_string())].into()
+        );
+
+        for (uri, expected) in [
+            ("/api/devices?meta=location:rack%204", 1),
+            ("/api/devices?meta=LOCATION", 1),
+            ("/api/devices?meta=owner", 0),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let devices: Vec<crate::models::Device> =
+                serde_json::from_str(&response_text(response).await).unwrap();
+            assert_eq!(devices.len(), expected, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn reorder_persists_order_from_repeated_ids() {
+        let (app, storage, _dir) = app();
+        let mut ids = Vec::new();
+        for (name, mac) in [
+            ("First", "AA:BB:CC:DD:EE:01"),
+            ("Second", "AA:BB:CC:DD:EE:02"),
+        ] {
+            let device = test_device(name, mac);
+            ids.push(device.id.clone());
+            storage.add(device).await.unwrap();
+        }
+
+        let response = app
+            .oneshot(form_request(
+                "/devices/reorder",
+                &format!("id={}&id={}", ids[1], ids[0]),
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let names: Vec<String> = storage.get_all().iter().map(|d| d.name.clone()).collect();
+        assert_eq!(names, ["Second", "First"]);
+        let body = response_text(response).await;
+        assert!(body.find("Second").unwrap() < body.find("First").unwrap());
+    }
+
+    #[tokio::test]
+    async fn toggle_favorite_pins_device() {
+        let (app, storage, _dir) = app();
+        let device = test_device("Pinned", "AA:BB:CC:DD:EE:01");
+        let id = device.id.clone();
+        storage.add(device).await.unwrap();
+
+        let response = app
+            .oneshot(form_request(&format!("/devices/{id}/favorite"), ""))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(storage.get(&id).unwrap().favorite);
+        let body = response_text(response).await;
+        assert!(body.contains("device-grid__grid--pinned"));
+    }
+
+    #[tokio::test]
+    async fn card_actions_keep_the_active_filter() {
+        let (app, storage, _dir) = app();
+        let mut ids = Vec::new();
+        for (name, mac) in [
+            ("NAS", "AA:BB:CC:DD:EE:01"),
+            ("Desk", "AA:BB:CC:DD:EE:02"),
+            ("Printer", "AA:BB:CC:DD:EE:03"),
+        ] {
+            let device = test_device(name, mac);
+            ids.push(device.id.clone());
+            storage.add(device).await.unwrap();
+        }
+        let card = |index: usize| format!("id=\"device-{}\"", ids[index]);
+
+        let response = app
+            .clone()
+            .oneshot(form_request(
+                &format!("/devices/{}/favorite", ids[0]),
+                "q=nas",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response_text(response).await;
+        assert!(body.contains(&card(0)));
+        assert!(!body.contains(&card(2)));
+
+        let response = app
+            .clone()
+            .oneshot(form_request(
+                &format!("/devices/{}/delete", ids[1]),
+                "q=nas",
+            ))
+            .await
+            .unwrap();
+        let body = response_text(response).await;
+        assert!(body.contains(&card(0)));
+        assert!(!body.contains(&card(2)));
+
+        let response = app
+            .oneshot(form_request(
+                &format!("/devices/{}/restore", ids[1]),
+                "q=desk",
+            ))
+            .await
+            .unwrap();
+        let body = response_text(response).await;
+        assert!(body.contains("Device restored"));
+        assert!(body.contains(&card(1)));
+        assert!(!body.contains(&card(0)));
+    }
+
+    #[tokio::test]
+    async fn delete_moves_device_to_trash_and_undo_restores_it() {
+        let (app, storage, _dir) = app();
+        let device = test_device("Desk", "AA:BB:CC:DD:EE:01");
+        let id = device.id.clone();
+        storage.add(device).await.unwrap();
+
+        let response = app
+            .clone()
+            .oneshot(form_request(&format!("/devices/{id}/delete"), ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response_text(response).await;
+        assert!(body.contains(&format!("hx-post=\"/devices/{id}/restore\"")));
+        assert!(storage.get(&id).is_none());
+        assert!(storage.get_all().is_empty());
+
+        let trash = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/api/devices/trash")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let trash: Vec<crate::models::Device> =
+            serde_json::from_str(&response_text(trash).await).unwrap();
+        assert_eq!(trash.len(), 1);
+        assert!(trash[0].deleted_at.is_some());
+
+        let response = app
+            .clone()
+            .oneshot(form_request(&format!("/devices/{id}/restore"), ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response_text(response).await.contains("Device restored"));
+        let restored = storage.get(&id).unwrap();
+        assert!(restored.deleted_at.is_none());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri(format!("/api/devices/{id}/restore"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn restore_is_refused_once_mac_is_taken() {
+        let (app, storage, _dir) = app();
+        let device = test_device("Old", "AA:BB:CC:DD:EE:02");
+        let id = device.id.clone();
+        storage.add(device).await.unwrap();
+        crate::devices::delete_device(&storage, &id).await.unwrap();
+        storage
+            .add(test_device("New", "aa-bb-cc-dd-ee-02"))
+            .await
+            .unwrap();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri(format!("/api/devices/{id}/restore"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(crate::devices::list_trash(&storage).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn purge_removes_only_expired_devices() {
+        let (_app, storage, _dir) = app();
+        let device = test_device("Gone", "AA:BB:CC:DD:EE:03");
+        let id = device.id.clone();
+        storage.add(device).await.unwrap();
+        crate::devices::delete_device(&storage, &id).await.unwrap();
+
+        let kept = crate::devices::purge_trash(&storage, time::Duration::days(30))
+            .await
+            .unwrap();
+        assert_eq!(kept, 0);
+        assert_eq!(crate::devices::list_trash(&storage).len(), 1);
+
+        let purged = crate::devices::purge_trash(&storage, time::Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(purged, 1);
+        assert!(crate::devices::list_trash(&storage).is_empty());
+    }
+
+    #[tokio::test]
+    async fn export_devices_returns_download_json() {
+        let (app, _storage, _dir) = app();
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/devices/export")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE).unwrap(),
             "application/json; charset=utf-8"
         );
         assert!(
@@ -401,6 +1577,54 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn exports_configuration_for_other_tools() {
+        let (app, storage, _dir) = app();
+        storage
+            .add(test_device("Gaming PC", "AA:BB:CC:DD:EE:01").with_tags(vec!["lab".to_string()]))
+            .await
+            .unwrap();
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let response = app
+            .clone()
+            .oneshot(get("/api/devices/export?format=ethers"))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE).unwrap(),
+            "text/plain; charset=utf-8"
+        );
+        assert!(
+            response_text(response)
+                .await
+                .ends_with("\nAA:BB:CC:DD:EE:01 gaming-pc\n")
+        );
+
+        let response = app
+            .clone()
+            .oneshot(get("/api/devices/export?format=home_assistant"))
+            .await
+            .unwrap();
+        assert!(
+            response_text(response)
+                .await
+                .contains("  - platform: wake_on_lan\n    name: \"Gaming PC\"\n")
+        );
+
+        let response = app
+            .oneshot(get("/devices/export?format=ansible_ini"))
+            .await
+            .unwrap();
+        let disposition = response.headers().get(CONTENT_DISPOSITION).unwrap();
+        assert!(disposition.to_str().unwrap().ends_with(".ini\""));
+        assert!(
+            response_text(response)
+                .await
+                .ends_with("\n[lab]\ngaming-pc\n")
+        );
+    }
+
     #[tokio::test]
     async fn preserved_api_and_swagger_routes_resolve() {
         let (app, _storage, _dir) = app();
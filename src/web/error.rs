@@ -6,6 +6,7 @@ use thiserror::Error;
 
 use crate::api::ApiResult;
 use crate::error::ApiError;
+use crate::models::FieldError;
 
 #[derive(Debug, Error)]
 pub enum WebError {
@@ -27,6 +28,15 @@ impl WebError {
     pub fn message(&self) -> String {
         self.to_string()
     }
+
+    /// Validation errors per field; any other error becomes a single entry
+    /// without a field name.
+    pub fn field_errors(&self) -> Vec<FieldError> {
+        match self {
+            Self::Api(ApiError::Validation(err)) => err.field_errors(),
+            _ => vec![FieldError::new("", self.message())],
+        }
+    }
 }
 
 impl IntoResponse for WebError {
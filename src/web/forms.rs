@@ -1,88 +1,214 @@
 use serde::Deserialize;
+use serde::de::IntoDeserializer;
+use std::collections::BTreeMap;
 
-use crate::api::devices::{CreateDeviceRequest, ImportRequest, UpdateDeviceRequest};
+use crate::api::devices::{
+    CreateDeviceRequest, DeviceQuery, IdConflict, ImportFormat, ImportMode, InterfaceRequest,
+    PatchDeviceRequest,
+};
+use crate::models::Device;
 
-#[derive(Debug, Deserialize)]
+/// The device modal's fields as typed, so a rejected submission can be shown
+/// again with its values intact.
+#[derive(Debug, Default, Deserialize)]
 pub struct DeviceForm {
     pub name: String,
-    pub mac_address: String,
+    /// One entry per interface row, in the order the rows appear.
+    #[serde(default)]
+    pub interface_mac: Vec<String>,
+    #[serde(default)]
+    pub interface_label: Vec<String>,
+    #[serde(default)]
+    pub interface_broadcast: Vec<String>,
     #[serde(default, deserialize_with = "empty_string_as_none")]
     pub ip_address: Option<String>,
-    #[serde(default, deserialize_with = "empty_string_as_none_u16")]
-    pub port: Option<u16>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub port: Option<String>,
     #[serde(default, deserialize_with = "empty_string_as_none")]
     pub description: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub tags: Option<String>,
+    /// Metadata inputs come as parallel `metadata_key`/`metadata_value` lists.
+    #[serde(default)]
+    pub metadata_key: Vec<String>,
+    #[serde(default)]
+    pub metadata_value: Vec<String>,
+    /// Revision the edit form was opened at; updates fail once it is outdated.
+    #[serde(default)]
+    pub revision: Option<u64>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ImportDevicesForm {
     pub payload: String,
+    /// Empty to tell the format from the payload.
+    #[serde(default, deserialize_with = "empty_option_as_none")]
+    pub format: Option<ImportFormat>,
+    #[serde(default)]
+    pub mode: ImportMode,
+    #[serde(default)]
+    pub id_conflict: IdConflict,
+}
+
+/// Sent by the drag-and-drop grid; `id` repeats once per card in display order
+/// and `tag` and `q` carry the active filter so the grid re-renders unchanged.
+#[derive(Debug, Deserialize)]
+pub struct ReorderForm {
+    #[serde(default)]
+    pub id: Vec<String>,
+    #[serde(default)]
+    pub tag: Vec<String>,
+    #[serde(default)]
+    pub q: Option<String>,
+}
+
+/// The grid's active tag filter and search, sent along by the card and
+/// trash buttons that re-render the grid.
+#[derive(Debug, Default, Deserialize)]
+pub struct GridFilter {
+    #[serde(default)]
+    pub tag: Vec<String>,
+    #[serde(default)]
+    pub q: Option<String>,
+}
+
+impl GridFilter {
+    pub fn query(self) -> DeviceQuery {
+        DeviceQuery {
+            tag: self.tag,
+            q: self.q,
+            ..DeviceQuery::default()
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ArpLookupForm {
     pub ip_address: String,
-    pub mac_address: Option<String>,
+    #[serde(default)]
+    pub interface_mac: Vec<String>,
 }
 
 impl DeviceForm {
-    pub fn validate(&self) -> Result<(), String> {
-        if self.name.trim().is_empty() {
-            return Err("Device name is required".to_string());
+    pub fn from_device(device: &Device) -> Self {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        Self {
+            name: device.name.clone(),
+            interface_mac: device
+                .interfaces
+                .iter()
+                .map(|interface| interface.mac_address.to_string())
+                .collect(),
+            interface_label: device
+                .interfaces
+                .iter()
+                .map(|interface| optional(interface.label.clone()))
+                .collect(),
+            interface_broadcast: device
+                .interfaces
+                .iter()
+                .map(|interface| optional(interface.broadcast.map(|ip| ip.to_string())))
+                .collect(),
+            ip_address: device.ip_address.clone(),
+            port: Some(device.port.to_string()),
+            description: device.description.clone(),
+            tags: Some(device.tags.join(", ")),
+            metadata_key: device.metadata.keys().cloned().collect(),
+            metadata_value: device.metadata.values().cloned().collect(),
+            revision: Some(device.revision),
         }
+    }
 
-        if self.mac_address.trim().is_empty() {
-            return Err("MAC address is required".to_string());
-        }
+    /// Rows with an empty MAC are left-over blank inputs and are skipped, so
+    /// `interfaces[i]` in a validation error refers to the i-th returned row.
+    pub fn interfaces(&self) -> Vec<InterfaceRequest> {
+        self.interface_mac
+            .iter()
+            .enumerate()
+            .filter(|(_, mac)| !mac.trim().is_empty())
+            .map(|(index, mac)| InterfaceRequest {
+                mac_address: mac.trim().to_string(),
+                label: self.interface_label.get(index).cloned(),
+                broadcast: self.interface_broadcast.get(index).cloned(),
+            })
+            .collect()
+    }
 
-        Ok(())
+    pub fn metadata(&self) -> BTreeMap<String, String> {
+        self.metadata_key
+            .iter()
+            .cloned()
+            .zip(self.metadata_value.iter().cloned())
+            .collect()
     }
 
-    pub fn into_create_request(self) -> Result<CreateDeviceRequest, String> {
-        self.validate()?;
-        Ok(CreateDeviceRequest {
+    /// A port that is not a number in range becomes 0, which device
+    /// validation reports alongside every other invalid field.
+    fn port(&self) -> Option<u16> {
+        self.port
+            .as_deref()
+            .map(|port| port.trim().parse().unwrap_or(0))
+    }
+
+    pub fn to_create_request(&self) -> CreateDeviceRequest {
+        CreateDeviceRequest {
             name: self.name.trim().to_string(),
-            mac_address: self.mac_address.trim().to_string(),
-            ip_address: self
-                .ip_address
-                .map(|value| value.trim().to_string())
-                .filter(|value| !value.is_empty()),
-            port: self.port,
-            description: self
-                .description
-                .map(|value| value.trim().to_string())
-                .filter(|value| !value.is_empty()),
-        })
+            mac_address: None,
+            interfaces: Some(self.interfaces()),
+            ip_address: self.ip_address.clone(),
+            port: self.port(),
+            description: self.description.clone(),
+            tags: Some(split_tags(self.tags.as_deref())),
+            metadata: Some(self.metadata()),
+            favorite: None,
+        }
     }
 
-    pub fn into_update_request(self) -> Result<UpdateDeviceRequest, String> {
-        self.validate()?;
-        Ok(UpdateDeviceRequest {
-            name: Some(self.name.trim().to_string()),
-            mac_address: Some(self.mac_address.trim().to_string()),
-            ip_address: self
-                .ip_address
-                .map(|value| value.trim().to_string())
-                .filter(|value| !value.is_empty()),
-            port: self
-                .port
-                .or_else(|| Some(crate::config::get().wol.default_port)),
-            description: self
-                .description
-                .map(|value| value.trim().to_string())
-                .filter(|value| !value.is_empty()),
-        })
+    /// `If-Match` value guarding an update, when the form carries a revision.
+    pub fn if_match(&self) -> Option<String> {
+        self.revision.map(crate::models::revision_etag)
     }
+
+    /// The edit form shows every field, so each one is set; blank optional
+    /// inputs clear their field and blank metadata values remove the key.
+    pub fn to_patch_request(&self) -> PatchDeviceRequest {
+        PatchDeviceRequest {
+            name: Some(Some(self.name.trim().to_string())),
+            mac_address: None,
+            interfaces: Some(Some(self.interfaces())),
+            ip_address: Some(self.ip_address.clone()),
+            port: Some(self.port()),
+            description: Some(self.description.clone()),
+            tags: Some(Some(split_tags(self.tags.as_deref()))),
+            metadata: Some(Some(
+                self.metadata()
+                    .into_iter()
+                    .map(|(key, value)| (key, Some(value).filter(|value| !value.trim().is_empty())))
+                    .collect(),
+            )),
+            favorite: None,
+        }
+    }
+}
+
+fn split_tags(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(ToString::to_string)
+        .collect()
 }
 
 impl ImportDevicesForm {
-    pub fn into_import_requests(self) -> Result<Vec<ImportRequest>, String> {
-        let payload = self.payload.trim();
-        if payload.is_empty() {
-            return Err("Paste or drop a JSON device list first".to_string());
+    pub fn content(&self) -> Result<&str, String> {
+        let content = self.payload.trim();
+        if content.is_empty() {
+            Err("Paste or drop a device file first".to_string())
+        } else {
+            Ok(content)
         }
-
-        serde_json::from_str(payload).map_err(|err| format!("Invalid JSON: {err}"))
     }
 }
 
@@ -101,20 +227,17 @@ where
     }))
 }
 
-fn empty_string_as_none_u16<'de, D>(deserializer: D) -> Result<Option<u16>, D::Error>
+/// Reads the value of a select whose empty option stands for none.
+fn empty_option_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
 where
     D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
 {
-    let value = Option::<String>::deserialize(deserializer)?;
-    value
-        .and_then(|value| {
-            let trimmed = value.trim();
-            if trimmed.is_empty() {
-                None
-            } else {
-                Some(trimmed.parse::<u16>())
-            }
-        })
-        .transpose()
-        .map_err(serde::de::Error::custom)
+    match Option::<String>::deserialize(deserializer)?
+        .as_deref()
+        .map(str::trim)
+    {
+        None | Some("") => Ok(None),
+        Some(value) => T::deserialize(value.into_deserializer()).map(Some),
+    }
 }
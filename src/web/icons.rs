@@ -12,6 +12,7 @@ pub enum Icon {
     Plus,
     Power,
     Search,
+    Star,
     Trash2,
     Upload,
     X,
@@ -30,6 +31,7 @@ impl Icon {
             Self::Plus => "Add",
             Self::Power => "Wake",
             Self::Search => "Lookup",
+            Self::Star => "Favorite",
             Self::Trash2 => "Remove",
             Self::Upload => "Upload",
             Self::X => "Close",
@@ -123,6 +125,9 @@ fn paths(icon: Icon) -> Markup {
             path d="m21 21-4.34-4.34" {}
             circle cx="11" cy="11" r="8" {}
         },
+        Icon::Star => html! {
+            path d="M11.525 2.295a.53.53 0 0 1 .95 0l2.31 4.679a2.123 2.123 0 0 0 1.595 1.16l5.166.756a.53.53 0 0 1 .294.904l-3.736 3.638a2.123 2.123 0 0 0-.611 1.878l.882 5.14a.53.53 0 0 1-.771.56l-4.618-2.428a2.122 2.122 0 0 0-1.973 0L6.396 21.01a.53.53 0 0 1-.77-.56l.881-5.139a2.122 2.122 0 0 0-.611-1.879L2.16 9.795a.53.53 0 0 1 .294-.906l5.165-.755a2.122 2.122 0 0 0 1.597-1.16z" {}
+        },
         Icon::Trash2 => html! {
             path d="M3 6h18" {}
             path d="M19 6v14c0 1-1 2-2 2H7c-1 0-2-1-2-2V6" {}
@@ -2,10 +2,13 @@ mod devices;
 mod feedback;
 mod layout;
 mod transfer;
+mod trash;
 
 pub use devices::{
-    device_grid, device_modal, mac_lookup_controls, mac_lookup_error, mac_lookup_error_with_hint,
+    device_grid, device_modal, interface_row, mac_lookup_controls, mac_lookup_error,
+    mac_lookup_error_with_hint,
 };
-pub use feedback::{ToastKind, grid_with_toast, toast_fragment};
+pub use feedback::{ToastKind, grid_with_toast, grid_with_undo_toast, toast_fragment};
 pub use layout::{error_page, home_page};
-pub use transfer::transfer_modal;
+pub use transfer::{ImportState, transfer_modal};
+pub use trash::trash_modal;
@@ -1,11 +1,28 @@
 use maud::{Markup, html};
+use time::OffsetDateTime;
 
-use crate::models::Device;
+use std::collections::BTreeMap;
+
+use crate::api::devices::{DeviceQuery, InterfaceRequest};
+use crate::config::MetadataField;
+use crate::models::{Device, FieldError};
+use crate::web::forms::DeviceForm;
 use crate::web::icons::{Icon, icon, icon_with_label};
 
-use super::feedback::field_error;
+use super::feedback::{field_error, form_error};
+
+/// The inputs holding the grid's active filter, for requests that re-render
+/// the grid to send along.
+pub(super) const GRID_FILTER: &str = "#device-search, .tag-filter input[name='tag']";
+
+/// Renders the whole inventory, showing only the devices that match `query`.
+pub fn device_grid(devices: &[Device], query: &DeviceQuery) -> Markup {
+    let tags = crate::devices::all_tags(devices);
+    let visible: Vec<&Device> = devices
+        .iter()
+        .filter(|device| crate::devices::matches_query(device, query))
+        .collect();
 
-pub fn device_grid(devices: &[Device]) -> Markup {
     html! {
         section id="device-grid" class="device-grid__section" {
             div class="device-grid__header" {
@@ -21,19 +38,134 @@ pub fn device_grid(devices: &[Device]) -> Markup {
                 }
             }
 
+            @if !tags.is_empty() {
+                (tag_filter_bar(&tags, &query.tag))
+            }
+
             @if devices.is_empty() {
                 (empty_state())
+            } @else if visible.is_empty() {
+                div class="device-grid__no-match" {
+                    "No devices match the search or the selected tags."
+                }
             } @else {
-                div class="device-grid__grid" {
-                    @for device in devices {
+                (sortable_grid(&visible, query))
+            }
+        }
+    }
+}
+
+/// Favorites are pinned above the rest. Dropping a card posts the ids of all
+/// cards in their new order (see `app.js`), together with the active filter.
+fn sortable_grid(devices: &[&Device], query: &DeviceQuery) -> Markup {
+    let (favorites, others): (Vec<&Device>, Vec<&Device>) =
+        devices.iter().partition(|device| device.favorite);
+
+    html! {
+        div
+            class="device-grid__sortable"
+            hx-post="/devices/reorder"
+            hx-trigger="jump-reorder"
+            hx-include="this"
+            hx-target="#device-grid"
+            hx-swap="outerHTML"
+            hx-disinherit="*" {
+            @for tag in &query.tag {
+                input type="hidden" name="tag" value=(tag);
+            }
+            @if let Some(q) = &query.q {
+                input type="hidden" name="q" value=(q);
+            }
+            @if !favorites.is_empty() {
+                h3 class="device-grid__subtitle" { "Favorites" }
+                div class="device-grid__grid device-grid__grid--pinned" data-sortable {
+                    @for device in &favorites {
                         (device_card(device))
                     }
                 }
+                @if !others.is_empty() {
+                    h3 class="device-grid__subtitle" { "All Devices" }
+                }
+            }
+            @if !others.is_empty() {
+                div class="device-grid__grid" data-sortable {
+                    @for device in &others {
+                        (device_card(device))
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn tag_filter_bar(tags: &[String], active: &[String]) -> Markup {
+    html! {
+        nav class="tag-filter" aria-label="Filter by tag" {
+            // Read by the search box, which keeps the tag filter.
+            @for tag in active {
+                input type="hidden" name="tag" value=(tag);
+            }
+            button
+                class={ "tag-chip" @if active.is_empty() { " tag-chip--active" } }
+                type="button"
+                hx-get="/devices"
+                hx-target="#device-grid"
+                hx-swap="outerHTML"
+                hx-include="#device-search"
+                aria-pressed=(active.is_empty()) {
+                "All"
+            }
+            @for tag in tags {
+                @let is_active = active.iter().any(|own| own.eq_ignore_ascii_case(tag));
+                button
+                    class={ "tag-chip" @if is_active { " tag-chip--active" } }
+                    type="button"
+                    hx-get={ "/devices" (toggled_tag_query(active, tag)) }
+                    hx-target="#device-grid"
+                    hx-swap="outerHTML"
+                    hx-include="#device-search"
+                    aria-pressed=(is_active) {
+                    (tag)
+                }
             }
         }
     }
 }
 
+/// Query string for the filter that results from toggling `tag`.
+fn toggled_tag_query(active: &[String], tag: &str) -> String {
+    let mut tags: Vec<&str> = active
+        .iter()
+        .map(String::as_str)
+        .filter(|own| !own.eq_ignore_ascii_case(tag))
+        .collect();
+    if tags.len() == active.len() {
+        tags.push(tag);
+    }
+
+    if tags.is_empty() {
+        return String::new();
+    }
+    let params: Vec<String> = tags
+        .iter()
+        .map(|tag| format!("tag={}", encode_query_value(tag)))
+        .collect();
+    format!("?{}", params.join("&"))
+}
+
+fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+                char::from(byte).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect()
+}
+
 fn empty_state() -> Markup {
     html! {
         div class="device-grid__empty-grid" {
@@ -50,23 +182,79 @@ fn empty_state() -> Markup {
 
 fn device_card(device: &Device) -> Markup {
     let short_id: String = device.id.chars().take(8).collect();
+    let neighbor_watcher = crate::config::get().network.neighbor_watcher;
+    let observed_ip = device
+        .ip_address
+        .is_none()
+        .then(|| crate::devices::observed_ip(device))
+        .flatten();
     html! {
-        article class="device-card" id={ "device-" (device.id) } {
+        article class="device-card" id={ "device-" (device.id) } draggable="true" {
+            input type="hidden" name="id" value=(device.id);
             div class="device-card__header" {
                 h3 class="device-card__name" { (device.name) }
-                div class="device-card__id" { (short_id) }
+                div class="device-card__badges" {
+                    button
+                        class={ "device-card__favorite" @if device.favorite { " device-card__favorite--active" } }
+                        type="button"
+                        hx-post={ "/devices/" (device.id) "/favorite" }
+                        hx-include=(GRID_FILTER)
+                        hx-target="#device-grid"
+                        hx-swap="outerHTML"
+                        aria-pressed=(device.favorite)
+                        aria-label=(if device.favorite { "Unpin from favorites" } else { "Pin to favorites" })
+                        title=(if device.favorite { "Unpin from favorites" } else { "Pin to favorites" }) {
+                        (icon(Icon::Star))
+                    }
+                    div class="device-card__id" { (short_id) }
+                }
+            }
+
+            @if !device.tags.is_empty() {
+                ul class="device-card__tags" aria-label="Tags" {
+                    @for tag in &device.tags {
+                        li class="tag-chip tag-chip--static" { (tag) }
+                    }
+                }
             }
 
             div class="device-card__info" {
-                div class="device-card__info-row" {
-                    span class="device-card__label" { "MAC" }
-                    span class="device-card__value device-card__value--mac" { (device.mac_address) }
+                @for interface in &device.interfaces {
+                    div class="device-card__info-row" {
+                        span class="device-card__label" { "MAC" }
+                        span class="device-card__value device-card__value--mac" {
+                            (interface.mac_address)
+                            @if let Some(label) = &interface.label {
+                                " " span class="form-hint" { "(" (label) ")" }
+                            }
+                        }
+                        @if device.interfaces.len() > 1 {
+                            @let target = interface.label.as_deref().map_or_else(|| interface.mac_address.to_string(), ToString::to_string);
+                            button
+                                class="device-card__wake-one"
+                                type="button"
+                                hx-post={ "/devices/" (device.id) "/wake?interface=" (interface.mac_address) }
+                                hx-target="#toast-root"
+                                hx-swap="beforeend"
+                                aria-label={ "Wake " (target) " only" }
+                                title={ "Wake " (target) " only" } {
+                                (icon(Icon::Power))
+                            }
+                        }
+                    }
                 }
                 @if let Some(ip_address) = &device.ip_address {
                     div class="device-card__info-row" {
                         span class="device-card__label" { "IP" }
                         span class="device-card__value" { (ip_address) }
                     }
+                } @else if let Some(ip_address) = observed_ip {
+                    div class="device-card__info-row" {
+                        span class="device-card__label" { "IP" }
+                        span class="device-card__value" title="Observed on the network" {
+                            (ip_address) " " span class="form-hint" { "(observed)" }
+                        }
+                    }
                 }
                 div class="device-card__info-row" {
                     span class="device-card__label" { "Port" }
@@ -78,6 +266,20 @@ fn device_card(device: &Device) -> Markup {
                         span class="device-card__value" { (description) }
                     }
                 }
+                @for (key, value) in &device.metadata {
+                    div class="device-card__info-row" {
+                        span class="device-card__label" title=(key) { (metadata_label(key)) }
+                        span class="device-card__value" { (value) }
+                    }
+                }
+                @if neighbor_watcher {
+                    div class="device-card__info-row" {
+                        span class="device-card__label" { "Seen" }
+                        span class="device-card__value" {
+                            (format_last_seen(crate::devices::last_seen(device), OffsetDateTime::now_utc()))
+                        }
+                    }
+                }
             }
 
             div class="device-card__actions" {
@@ -105,6 +307,7 @@ fn device_card(device: &Device) -> Markup {
                     class="btn btn-danger device-card__icon-action"
                     type="button"
                     hx-post={ "/devices/" (device.id) "/delete" }
+                    hx-include=(GRID_FILTER)
                     hx-target="#device-grid"
                     hx-swap="outerHTML"
                     hx-confirm={ "Remove " (device.name) "?" }
@@ -118,25 +321,70 @@ fn device_card(device: &Device) -> Markup {
     }
 }
 
-pub fn device_modal(device: Option<&Device>, error: Option<&str>) -> Markup {
-    let is_edit = device.is_some();
+pub(super) fn format_last_seen(last_seen: Option<OffsetDateTime>, now: OffsetDateTime) -> String {
+    let Some(last_seen) = last_seen else {
+        return "Not yet".to_string();
+    };
+
+    let elapsed = now - last_seen;
+    if elapsed.whole_minutes() < 1 {
+        "Just now".to_string()
+    } else if elapsed.whole_hours() < 1 {
+        format!("{}m ago", elapsed.whole_minutes())
+    } else if elapsed.whole_days() < 1 {
+        format!("{}h ago", elapsed.whole_hours())
+    } else {
+        format!("{}d ago", elapsed.whole_days())
+    }
+}
+
+/// The add/edit dialog. `values` refills the inputs after a rejected
+/// submission and `errors` marks the offending fields; errors without a
+/// matching input are listed at the top.
+pub fn device_modal(
+    device_id: Option<&str>,
+    values: Option<&DeviceForm>,
+    errors: &[FieldError],
+) -> Markup {
+    let is_edit = device_id.is_some();
     let title = if is_edit { "Edit Device" } else { "Add Device" };
-    let action = device.map_or_else(
+    let action = device_id.map_or_else(
         || "/devices".to_string(),
-        |device| format!("/devices/{}/update", device.id),
+        |id| format!("/devices/{id}/update"),
     );
-    let name = device.map_or("", |device| device.name.as_str());
-    let mac = device.map_or("", |device| device.mac_address.as_str());
-    let ip = device
-        .and_then(|device| device.ip_address.as_deref())
+    let new_device = DeviceForm {
+        port: Some(crate::config::get().wol.default_port.to_string()),
+        ..DeviceForm::default()
+    };
+    let values = values.unwrap_or(&new_device);
+    let interfaces = values.interfaces();
+    let primary = interfaces.first();
+    let mac = primary.map_or("", |interface| interface.mac_address.as_str());
+    let primary_label = primary
+        .and_then(|interface| interface.label.as_deref())
         .unwrap_or("");
-    let port = device.map_or_else(
-        || crate::config::get().wol.default_port.to_string(),
-        |device| device.port.to_string(),
-    );
-    let description = device
-        .and_then(|device| device.description.as_deref())
+    let primary_broadcast = primary
+        .and_then(|interface| interface.broadcast.as_deref())
         .unwrap_or("");
+    let primary_errors = interface_errors(errors, 0);
+    let mac_error = error_for(errors, "mac_address")
+        .or_else(|| error_for(errors, "interfaces"))
+        .or_else(|| error_for(&primary_errors, "mac_address"));
+    let label_error = error_for(&primary_errors, "label");
+    let broadcast_error = error_for(&primary_errors, "broadcast");
+    let metadata = values.metadata();
+    let metadata_inputs = metadata_inputs(&metadata);
+    let banner: Vec<String> = errors
+        .iter()
+        .filter(|error| !is_inline_field(&error.field, interfaces.len(), &metadata_inputs))
+        .map(|error| {
+            if error.field.is_empty() {
+                error.message.clone()
+            } else {
+                format!("{}: {}", error.field, error.message)
+            }
+        })
+        .collect();
 
     html! {
         dialog
@@ -157,15 +405,19 @@ pub fn device_modal(device: Option<&Device>, error: Option<&str>) -> Markup {
                     hx-post=(action)
                     hx-target="#device-grid"
                     hx-swap="outerHTML" {
+                    @if let Some(revision) = values.revision {
+                        input type="hidden" name="revision" value=(revision);
+                    }
                     div class="modal__body" {
-                        @if let Some(error) = error {
-                            (field_error(error))
+                        @if !banner.is_empty() {
+                            (form_error(&banner.join("; ")))
                         }
                         div class="form-group" {
                             label class="form-label" for="device-name" { "Device Name" }
-                            input id="device-name" class="form-input" name="name" placeholder="e.g., Gaming PC" value=(name) required autofocus;
+                            input id="device-name" class="form-input" name="name" placeholder="e.g., Gaming PC" value=(values.name) aria-invalid=[invalid(errors, "name")] required autofocus;
+                            @if let Some(error) = error_for(errors, "name") { (field_error(error)) }
                         }
-                        div class="form-group" {
+                        div class=(group_class("form-group", mac_error)) {
                             label class="form-label" for="mac-address" {
                                 "MAC Address"
                                 span class="form-hint" { "AA:BB:CC:DD:EE:FF" }
@@ -173,24 +425,82 @@ pub fn device_modal(device: Option<&Device>, error: Option<&str>) -> Markup {
                             div id="mac-lookup-result" class="mac-input-wrapper" {
                                 (mac_lookup_controls(mac))
                             }
+                            @if let Some(error) = mac_error { (field_error(error)) }
+                        }
+                        div class="form-row form-row--even" {
+                            div class="form-group form-group--flush" {
+                                label class="form-label" for="interface-label" {
+                                    "Interface Label " span class="form-hint" { "(optional)" }
+                                }
+                                input id="interface-label" class="form-input" name="interface_label" placeholder="onboard" value=(primary_label) aria-invalid=[label_error.map(|_| "true")];
+                                @if let Some(error) = label_error { (field_error(error)) }
+                            }
+                            div class="form-group form-group--flush" {
+                                label class="form-label" for="interface-broadcast" {
+                                    "Broadcast " span class="form-hint" { "(optional)" }
+                                }
+                                input id="interface-broadcast" class="form-input" name="interface_broadcast" placeholder="255.255.255.255" value=(primary_broadcast) aria-invalid=[broadcast_error.map(|_| "true")];
+                                @if let Some(error) = broadcast_error { (field_error(error)) }
+                            }
+                        }
+                        div id="extra-interfaces" class="interface-list" {
+                            @for (index, interface) in interfaces.iter().enumerate().skip(1) {
+                                (interface_row(Some(interface), &interface_errors(errors, index)))
+                            }
+                        }
+                        button
+                            class="interface-list__add"
+                            type="button"
+                            hx-get="/devices/interface-row"
+                            hx-target="#extra-interfaces"
+                            hx-swap="beforeend" {
+                            (icon(Icon::Plus))
+                            "Add interface"
                         }
                         div class="form-row" {
                             div class="form-group form-group--flush" {
                                 label class="form-label" for="ip-address" {
                                     "IP Address " span class="form-hint" { "(optional)" }
                                 }
-                                input id="ip-address" class="form-input" name="ip_address" placeholder="192.168.1.100" value=(ip);
+                                input id="ip-address" class="form-input" name="ip_address" placeholder="192.168.1.100 or nas.lan" value=(values.ip_address.as_deref().unwrap_or("")) aria-invalid=[invalid(errors, "ip_address")];
+                                @if let Some(error) = error_for(errors, "ip_address") { (field_error(error)) }
                             }
                             div class="form-group form-group--flush" {
                                 label class="form-label" for="device-port" { "Port" }
-                                input id="device-port" class="form-input" type="number" min="1" max="65535" name="port" placeholder="9" value=(port);
+                                input id="device-port" class="form-input" type="number" min="1" max="65535" name="port" placeholder="9" value=(values.port.as_deref().unwrap_or("")) aria-invalid=[invalid(errors, "port")];
+                                @if let Some(error) = error_for(errors, "port") { (field_error(error)) }
                             }
                         }
                         div class="form-group form-group--spaced" {
                             label class="form-label" for="description" {
                                 "Description " span class="form-hint" { "(optional)" }
                             }
-                            input id="description" class="form-input" name="description" placeholder="Notes about this device..." value=(description);
+                            input id="description" class="form-input" name="description" placeholder="Notes about this device..." value=(values.description.as_deref().unwrap_or("")) aria-invalid=[invalid(errors, "description")];
+                            @if let Some(error) = error_for(errors, "description") { (field_error(error)) }
+                        }
+                        div class="form-group form-group--spaced" {
+                            label class="form-label" for="device-tags" {
+                                "Tags " span class="form-hint" { "(comma separated)" }
+                            }
+                            input id="device-tags" class="form-input" name="tags" placeholder="lab, gpu" value=(values.tags.as_deref().unwrap_or("")) aria-invalid=[invalid(errors, "tags")];
+                            @if let Some(error) = error_for(errors, "tags") { (field_error(error)) }
+                        }
+                        @for (index, input) in metadata_inputs.iter().enumerate() {
+                            @let field = format!("metadata.{}", input.key);
+                            div class="form-group form-group--spaced" {
+                                label class="form-label" for={ "device-metadata-" (index) } {
+                                    (input.label) " " span class="form-hint" { "(optional)" }
+                                }
+                                input type="hidden" name="metadata_key" value=(input.key);
+                                input
+                                    id={ "device-metadata-" (index) }
+                                    class="form-input"
+                                    name="metadata_value"
+                                    placeholder=(input.placeholder)
+                                    value=(input.value)
+                                    aria-invalid=[invalid(errors, &field)];
+                                @if let Some(error) = error_for(errors, &field) { (field_error(error)) }
+                            }
                         }
                     }
                     div class="modal__footer" {
@@ -209,13 +519,140 @@ pub fn device_modal(device: Option<&Device>, error: Option<&str>) -> Markup {
     }
 }
 
+/// Inputs for an additional network interface in the device modal. `errors`
+/// carry the row's own field names (`mac_address`, `label`, `broadcast`).
+pub fn interface_row(interface: Option<&InterfaceRequest>, errors: &[FieldError]) -> Markup {
+    let mac = interface.map_or("", |interface| interface.mac_address.as_str());
+    let label = interface
+        .and_then(|interface| interface.label.as_deref())
+        .unwrap_or("");
+    let broadcast = interface
+        .and_then(|interface| interface.broadcast.as_deref())
+        .unwrap_or("");
+
+    html! {
+        div class="interface-row" {
+            input class="form-input" name="interface_mac" placeholder="AA:BB:CC:DD:EE:FF" aria-label="Interface MAC address" value=(mac) aria-invalid=[invalid(errors, "mac_address")];
+            input class="form-input" name="interface_label" placeholder="Label" aria-label="Interface label" value=(label) aria-invalid=[invalid(errors, "label")];
+            input class="form-input" name="interface_broadcast" placeholder="Broadcast" aria-label="Interface broadcast address" value=(broadcast) aria-invalid=[invalid(errors, "broadcast")];
+            button
+                class="interface-row__remove"
+                type="button"
+                onclick="this.closest('.interface-row').remove()"
+                aria-label="Remove interface"
+                title="Remove interface" {
+                (icon(Icon::X))
+            }
+            @for error in errors {
+                (field_error(&error.message))
+            }
+        }
+    }
+}
+
+fn error_for<'a>(errors: &'a [FieldError], field: &str) -> Option<&'a str> {
+    errors
+        .iter()
+        .find(|error| error.field == field)
+        .map(|error| error.message.as_str())
+}
+
+fn invalid(errors: &[FieldError], field: &str) -> Option<&'static str> {
+    error_for(errors, field).map(|_| "true")
+}
+
+fn group_class(base: &str, error: Option<&str>) -> String {
+    match error {
+        Some(_) => format!("{base} form-group--invalid"),
+        None => base.to_string(),
+    }
+}
+
+/// Splits `interfaces[2].broadcast` into `(2, "broadcast")`.
+fn interface_field(field: &str) -> Option<(usize, &str)> {
+    let (index, name) = field.strip_prefix("interfaces[")?.split_once("].")?;
+    Some((index.parse().ok()?, name))
+}
+
+/// The errors of one interface row, with the `interfaces[i].` prefix removed.
+fn interface_errors(errors: &[FieldError], index: usize) -> Vec<FieldError> {
+    errors
+        .iter()
+        .filter_map(|error| {
+            let (own, name) = interface_field(&error.field)?;
+            (own == index).then(|| FieldError::new(name, error.message.clone()))
+        })
+        .collect()
+}
+
+/// Whether the modal shows `field` next to its input rather than in the banner.
+fn is_inline_field(field: &str, interface_count: usize, metadata: &[MetadataInput<'_>]) -> bool {
+    match field {
+        "name" | "mac_address" | "interfaces" | "ip_address" | "port" | "description" | "tags" => {
+            true
+        }
+        _ => {
+            interface_field(field).is_some_and(|(index, _)| index < interface_count.max(1))
+                || field
+                    .strip_prefix("metadata.")
+                    .is_some_and(|key| metadata.iter().any(|input| input.key == key))
+        }
+    }
+}
+
+struct MetadataInput<'a> {
+    key: &'a str,
+    label: &'a str,
+    placeholder: &'a str,
+    value: &'a str,
+}
+
+/// Inputs for every configured metadata field, followed by any other keys the
+/// device already carries so that saving the form keeps them.
+fn metadata_inputs(metadata: &BTreeMap<String, String>) -> Vec<MetadataInput<'_>> {
+    let fields = &crate::config::get().metadata.fields;
+    let value_of = |key: &str| metadata.get(key).map_or("", String::as_str);
+
+    let mut inputs: Vec<MetadataInput<'_>> = fields
+        .iter()
+        .map(|field| MetadataInput {
+            key: &field.key,
+            label: field.label(),
+            placeholder: field.placeholder.as_deref().unwrap_or(""),
+            value: value_of(&field.key),
+        })
+        .collect();
+
+    inputs.extend(
+        metadata
+            .iter()
+            .filter(|(key, _)| !fields.iter().any(|field| field.key == **key))
+            .map(|(key, value)| MetadataInput {
+                key,
+                label: key,
+                placeholder: "",
+                value,
+            }),
+    );
+    inputs
+}
+
+fn metadata_label(key: &str) -> &str {
+    crate::config::get()
+        .metadata
+        .fields
+        .iter()
+        .find(|field| field.key == key)
+        .map_or(key, MetadataField::label)
+}
+
 pub fn mac_lookup_controls(mac: &str) -> Markup {
     html! {
         div class="mac-input-control" {
             input
                 id="mac-address"
                 class="form-input form-input--with-action"
-                name="mac_address"
+                name="interface_mac"
                 placeholder="AA:BB:CC:DD:EE:FF"
                 value=(mac)
                 required;
@@ -259,3 +696,35 @@ pub fn mac_lookup_error_with_hint(mac: &str, error: &str, hint: Option<&str>) ->
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggling_tags_builds_filter_query() {
+        let active = vec!["lab".to_string()];
+        assert_eq!(toggled_tag_query(&active, "gpu"), "?tag=lab&tag=gpu");
+        assert_eq!(toggled_tag_query(&active, "LAB"), "");
+        assert_eq!(toggled_tag_query(&[], "rack 4/b"), "?tag=rack%204%2Fb");
+    }
+
+    #[test]
+    fn last_seen_is_relative_to_now() {
+        let now = OffsetDateTime::now_utc();
+        assert_eq!(format_last_seen(None, now), "Not yet");
+        assert_eq!(format_last_seen(Some(now), now), "Just now");
+        assert_eq!(
+            format_last_seen(Some(now - time::Duration::minutes(5)), now),
+            "5m ago"
+        );
+        assert_eq!(
+            format_last_seen(Some(now - time::Duration::hours(3)), now),
+            "3h ago"
+        );
+        assert_eq!(
+            format_last_seen(Some(now - time::Duration::days(2)), now),
+            "2d ago"
+        );
+    }
+}
@@ -1,9 +1,10 @@
 use maud::{Markup, html};
 
+use crate::api::devices::DeviceQuery;
 use crate::models::Device;
 use crate::web::icons::{Icon, icon};
 
-use super::devices::device_grid;
+use super::devices::{GRID_FILTER, device_grid};
 
 #[derive(Debug, Clone, Copy)]
 pub enum ToastKind {
@@ -27,12 +28,18 @@ impl ToastKind {
     }
 }
 
-pub fn field_error(message: &str) -> Markup {
+pub fn form_error(message: &str) -> Markup {
     html! {
         div class="error-message error-message--block" role="alert" { (message) }
     }
 }
 
+pub fn field_error(message: &str) -> Markup {
+    html! {
+        span class="error-message" role="alert" { (message) }
+    }
+}
+
 fn toast(kind: ToastKind, message: &str) -> Markup {
     html! {
         div
@@ -43,6 +50,29 @@ fn toast(kind: ToastKind, message: &str) -> Markup {
     }
 }
 
+/// A success toast with an "Undo" button posting to `undo_url`. It stays up
+/// longer than other toasts so there is time to use it.
+fn undo_toast(message: &str, undo_url: &str) -> Markup {
+    html! {
+        div
+            class={ (ToastKind::Success.class_name()) " toast__show" }
+            data-dismiss-after="8000" {
+            span class="toast__icon" { (icon(ToastKind::Success.icon())) }
+            span class="toast__message" { (message) }
+            button
+                class="toast__action"
+                type="button"
+                hx-post=(undo_url)
+                hx-include=(GRID_FILTER)
+                hx-target="#device-grid"
+                hx-swap="outerHTML"
+                hx-on::after-request="this.closest('.toast__toast').remove()" {
+                "UNDO"
+            }
+        }
+    }
+}
+
 fn toast_oob(kind: ToastKind, message: &str) -> Markup {
     html! {
         div id="toast-root" hx-swap-oob="beforeend" {
@@ -57,14 +87,35 @@ pub fn clear_modal_oob() -> Markup {
     }
 }
 
-pub fn grid_with_toast(devices: &[Device], kind: ToastKind, message: &str) -> Markup {
+pub fn grid_with_toast(
+    devices: &[Device],
+    query: &DeviceQuery,
+    kind: ToastKind,
+    message: &str,
+) -> Markup {
     html! {
-        (device_grid(devices))
+        (device_grid(devices, query))
         (toast_oob(kind, message))
         (clear_modal_oob())
     }
 }
 
+/// Like [`grid_with_toast`], with a toast that can undo the change.
+pub fn grid_with_undo_toast(
+    devices: &[Device],
+    query: &DeviceQuery,
+    message: &str,
+    undo_url: &str,
+) -> Markup {
+    html! {
+        (device_grid(devices, query))
+        div id="toast-root" hx-swap-oob="beforeend" {
+            (undo_toast(message, undo_url))
+        }
+        (clear_modal_oob())
+    }
+}
+
 pub fn toast_fragment(kind: ToastKind, message: &str) -> Markup {
     toast(kind, message)
 }
@@ -1,6 +1,7 @@
 use axum::http::StatusCode;
 use maud::{DOCTYPE, Markup, html};
 
+use crate::api::devices::DeviceQuery;
 use crate::models::Device;
 use crate::web::icons::{Icon, icon};
 
@@ -41,8 +42,8 @@ pub fn home_page(devices: &[Device], username: Option<&str>) -> Markup {
     layout(
         "Jumpers",
         html! {
-            (header(username))
-            (device_grid(devices))
+            (header(username, true))
+            (device_grid(devices, &DeviceQuery::default()))
         },
     )
 }
@@ -51,7 +52,7 @@ pub fn error_page(status: StatusCode, message: &str) -> Markup {
     layout(
         "Jumpers Error",
         html! {
-            (header(None))
+            (header(None, false))
             section class="empty-grid" {
                 div class="empty-banner" {
                     h2 class="empty-title" { "Request Failed" }
@@ -62,7 +63,8 @@ pub fn error_page(status: StatusCode, message: &str) -> Markup {
     )
 }
 
-fn header(username: Option<&str>) -> Markup {
+/// The search box filters the device grid, so it is only shown above one.
+fn header(username: Option<&str>, search: bool) -> Markup {
     html! {
         header class="app-header" {
             div class="app-header__brand" {
@@ -72,6 +74,21 @@ fn header(username: Option<&str>) -> Markup {
                 p class="app-header__subtitle" { "Wake-on-LAN Control" }
             }
             div class="app-header__right" {
+                @if search {
+                    input
+                        id="device-search"
+                        class="app-header__search"
+                        type="search"
+                        name="q"
+                        placeholder="Search name, IP or MAC"
+                        aria-label="Search devices"
+                        autocomplete="off"
+                        hx-get="/devices"
+                        hx-trigger="keyup changed delay:200ms, search"
+                        hx-target="#device-grid"
+                        hx-swap="outerHTML"
+                        hx-include=".tag-filter input[name='tag']";
+                }
                 button
                     class="app-header__data-btn"
                     type="button"
@@ -81,6 +98,15 @@ fn header(username: Option<&str>) -> Markup {
                     (icon(Icon::Database))
                     "Import / Export"
                 }
+                button
+                    class="app-header__data-btn"
+                    type="button"
+                    hx-get="/devices/trash"
+                    hx-target="#modal-root"
+                    hx-swap="innerHTML" {
+                    (icon(Icon::Trash2))
+                    "Trash"
+                }
                 @if let Some(username) = username {
                     form method="post" action="/logout" {
                         button
@@ -123,7 +149,7 @@ mod tests {
 
     #[test]
     fn authenticated_header_shows_username_and_logout_icon() {
-        let markup = header(Some("alice")).into_string();
+        let markup = header(Some("alice"), true).into_string();
 
         assert!(markup.contains("alice"));
         assert!(markup.contains("aria-label=\"Sign out alice\""));
@@ -1,10 +1,65 @@
 use maud::{Markup, html};
 
+use crate::api::devices::{IdConflict, ImportChange, ImportFormat, ImportMode, ImportReport};
+use crate::models::FieldError;
 use crate::web::icons::{Icon, icon, icon_with_label};
 
-use super::feedback::field_error;
+use super::feedback::form_error;
 
-pub fn transfer_modal(error: Option<&str>) -> Markup {
+/// What the import tab shows. The default is an empty form; after a preview
+/// or a failed import the modal reopens on the import tab with the submitted
+/// data still filled in.
+#[derive(Debug, Default)]
+pub struct ImportState<'a> {
+    pub payload: &'a str,
+    /// `None` to tell the format from the payload.
+    pub format: Option<ImportFormat>,
+    pub mode: ImportMode,
+    pub id_conflict: IdConflict,
+    pub error: Option<&'a str>,
+    /// One entry per invalid field, e.g. `line 3.port`.
+    pub problems: &'a [FieldError],
+    pub preview: Option<&'a ImportReport>,
+}
+
+impl ImportState<'_> {
+    const fn is_active(&self) -> bool {
+        self.error.is_some() || self.preview.is_some()
+    }
+}
+
+/// `format` values and labels of the exports meant for other tools.
+const CONFIG_EXPORTS: [(&str, &str); 4] = [
+    ("ethers", "/ETC/ETHERS"),
+    ("home_assistant", "HOME ASSISTANT"),
+    ("ansible_ini", "ANSIBLE INI"),
+    ("ansible_yaml", "ANSIBLE YAML"),
+];
+
+const IMPORT_FORMATS: [(ImportFormat, &str); 7] = [
+    (ImportFormat::Json, "JSON export"),
+    (ImportFormat::Csv, "CSV export"),
+    (ImportFormat::Backup, "Backup"),
+    (ImportFormat::Upsnap, "UpSnap"),
+    (ImportFormat::Wolweb, "wolweb devices.json"),
+    (ImportFormat::Android, "Android Wake On Lan backup"),
+    (ImportFormat::Ethers, "/etc/ethers"),
+];
+
+const IMPORT_MODES: [(ImportMode, &str); 3] = [
+    (ImportMode::Append, "Add as new devices"),
+    (
+        ImportMode::Upsert,
+        "Update devices with matching MAC addresses",
+    ),
+    (
+        ImportMode::Replace,
+        "Replace all devices (current ones go to the trash)",
+    ),
+];
+
+pub fn transfer_modal(import: &ImportState) -> Markup {
+    let importing = import.is_active();
     html! {
         dialog
             class="modal modal__large"
@@ -26,10 +81,10 @@ pub fn transfer_modal(error: Option<&str>) -> Markup {
                             id="transfer-export-tab"
                             type="button"
                             role="tab"
-                            class="transfer__tab transfer__tab--active"
-                            aria-selected="true"
+                            class={ "transfer__tab" @if !importing { " transfer__tab--active" } }
+                            aria-selected=(!importing)
                             aria-controls="transfer-export-panel"
-                            tabindex="0"
+                            tabindex=(if importing { "-1" } else { "0" })
                             onclick="jumpShowTransferTab('export')"
                             onkeydown="jumpHandleTransferTabKeydown(event)" {
                             "EXPORT"
@@ -38,10 +93,10 @@ pub fn transfer_modal(error: Option<&str>) -> Markup {
                             id="transfer-import-tab"
                             type="button"
                             role="tab"
-                            class="transfer__tab"
-                            aria-selected="false"
+                            class={ "transfer__tab" @if importing { " transfer__tab--active" } }
+                            aria-selected=(importing)
                             aria-controls="transfer-import-panel"
-                            tabindex="-1"
+                            tabindex=(if importing { "0" } else { "-1" })
                             onclick="jumpShowTransferTab('import')"
                             onkeydown="jumpHandleTransferTabKeydown(event)" {
                             "IMPORT"
@@ -53,14 +108,36 @@ pub fn transfer_modal(error: Option<&str>) -> Markup {
                             id="transfer-export-panel"
                             class="transfer__export-section"
                             role="tabpanel"
-                            aria-labelledby="transfer-export-tab" {
+                            aria-labelledby="transfer-export-tab"
+                            hidden[importing] {
                             div class="transfer__export-icon" { (icon(Icon::Download)) }
                             p class="transfer__description" {
-                                "Export all registered devices to a JSON file. The file contains device names, MAC addresses, IP addresses, ports, and descriptions."
+                                "Export all registered devices to a JSON or CSV file. The file contains device names, MAC addresses, IP addresses, ports, descriptions, tags, and metadata. A backup also keeps device ids and timestamps, so links and scripts keep working after it is imported."
                             }
-                            a class="transfer__action-btn" href="/devices/export" download {
-                                (icon(Icon::Download))
-                                "DOWNLOAD JSON"
+                            div class="transfer__export-actions" {
+                                a class="transfer__action-btn" href="/devices/export" download {
+                                    (icon(Icon::Download))
+                                    "DOWNLOAD JSON"
+                                }
+                                a class="transfer__action-btn" href="/devices/export?format=csv" download {
+                                    (icon(Icon::Download))
+                                    "DOWNLOAD CSV"
+                                }
+                                a class="transfer__action-btn" href="/devices/export?format=backup" download {
+                                    (icon(Icon::Download))
+                                    "DOWNLOAD BACKUP"
+                                }
+                            }
+                            p class="transfer__description" {
+                                "Or generate configuration for other tools: /etc/ethers for etherwake, Home Assistant wake_on_lan switches, or an Ansible inventory with a group per tag."
+                            }
+                            div class="transfer__export-actions" {
+                                @for (format, label) in CONFIG_EXPORTS {
+                                    a class="transfer__action-btn" href={ "/devices/export?format=" (format) } download {
+                                        (icon(Icon::Download))
+                                        (label)
+                                    }
+                                }
                             }
                         }
 
@@ -69,12 +146,22 @@ pub fn transfer_modal(error: Option<&str>) -> Markup {
                             class="transfer__import-section"
                             role="tabpanel"
                             aria-labelledby="transfer-import-tab"
-                            hidden
-                            hx-post="/devices/import"
-                            hx-target="#device-grid"
-                            hx-swap="outerHTML" {
-                            @if let Some(error) = error {
-                                (field_error(error))
+                            hidden[!importing]
+                            hx-post="/devices/import/preview"
+                            hx-target="#modal-root"
+                            hx-swap="innerHTML" {
+                            @if let Some(error) = import.error {
+                                (form_error(error))
+                            }
+                            @if !import.problems.is_empty() {
+                                ul class="transfer__problems" role="alert" {
+                                    @for problem in import.problems {
+                                        li {
+                                            code { (problem.field) }
+                                            " " (problem.message)
+                                        }
+                                    }
+                                }
                             }
                             div class="transfer__import-options" {
                                 div
@@ -87,14 +174,13 @@ pub fn transfer_modal(error: Option<&str>) -> Markup {
                                         id="json-file"
                                         class="transfer__file-input"
                                         type="file"
-                                        accept=".json"
                                         onchange="jumpLoadImportFile(event.target.files[0])";
                                     button
                                         class="transfer__upload-btn"
                                         type="button"
                                         onclick="document.getElementById('json-file').click()" {
                                         (icon(Icon::Upload))
-                                        "Upload JSON File"
+                                        "Upload Device File"
                                     }
                                     div class="transfer__drop-text" {
                                         span id="transfer-file-name" { "or drag and drop file here" }
@@ -105,19 +191,61 @@ pub fn transfer_modal(error: Option<&str>) -> Markup {
 
                                 div class="transfer__import-option" {
                                     label class="form-label" for="import-payload" {
-                                        "JSON device data"
+                                        "Device data"
                                     }
                                     textarea
                                         class="transfer__json-input"
                                         name="payload"
                                         id="import-payload"
                                         rows="6"
-                                        placeholder="[{\"name\":\"Device\",\"mac_address\":\"aa:bb:cc:dd:ee:ff\",\"ip_address\":\"192.168.1.100\",\"port\":9}]" {}
+                                        oninput="jumpClearImportPreview()"
+                                        placeholder="[{\"name\":\"Device\",\"mac_address\":\"aa:bb:cc:dd:ee:ff\",\"ip_address\":\"192.168.1.100\",\"port\":9}]" {
+                                        (import.payload)
+                                    }
+                                    label class="form-label" for="import-format" { "Format" }
+                                    select
+                                        class="form-input"
+                                        name="format"
+                                        id="import-format"
+                                        onchange="jumpClearImportPreview()" {
+                                        option value="" selected[import.format.is_none()] { "Detect automatically" }
+                                        @for (format, label) in IMPORT_FORMATS {
+                                            option value=(format_value(format)) selected[Some(format) == import.format] { (label) }
+                                        }
+                                    }
+                                    label class="form-label" for="import-mode" { "Mode" }
+                                    select
+                                        class="form-input"
+                                        name="mode"
+                                        id="import-mode"
+                                        onchange="jumpClearImportPreview()" {
+                                        @for (mode, label) in IMPORT_MODES {
+                                            option value=(mode_value(mode)) selected[mode == import.mode] { (label) }
+                                        }
+                                    }
+                                    label class="form-label" for="import-id-conflict" {
+                                        "Backup ids already in use"
+                                    }
+                                    select
+                                        class="form-input"
+                                        name="id_conflict"
+                                        id="import-id-conflict"
+                                        onchange="jumpClearImportPreview()" {
+                                        option value="reject" selected[import.id_conflict == IdConflict::Reject] {
+                                            "Report as a conflict"
+                                        }
+                                        option value="remap" selected[import.id_conflict == IdConflict::Remap] {
+                                            "Import under a new id"
+                                        }
+                                    }
+                                    @if let Some(report) = import.preview {
+                                        (import_preview(report))
+                                    }
                                     button
                                         class="transfer__action-btn"
                                         type="submit" {
-                                        (icon(Icon::Upload))
-                                        "Import devices"
+                                        (icon(Icon::Search))
+                                        "Preview import"
                                     }
                                 }
                             }
@@ -126,10 +254,117 @@ pub fn transfer_modal(error: Option<&str>) -> Markup {
                     div class="transfer__footer" {
                         div class="transfer__format-hint" {
                             "Expected JSON format:"
-                            code { "[{\"name\":\"Name\",\"mac_address\":\"aa:bb:cc:dd:ee:ff\",\"ip_address\":\"1.2.3.4\",\"port\":9,\"description\":\"...\"}]" }
+                            code { "[{\"name\":\"Name\",\"mac_address\":\"aa:bb:cc:dd:ee:ff\",\"ip_address\":\"1.2.3.4\",\"port\":9,\"description\":\"...\",\"tags\":[\"lab\"]}]" }
+                            "CSV needs a header row; extra columns become metadata and several MACs or tags are separated by ';':"
+                            code { "name,mac_address,ip_address,port,description,tags,favorite,location\nName,aa:bb:cc:dd:ee:ff,1.2.3.4,9,...,lab;gpu,false,Office" }
+                            "Files from UpSnap, wolweb, the Android Wake On Lan app and /etc/ethers are recognised too."
                         }
                     }
                 }
         }
     }
 }
+
+const fn format_value(format: ImportFormat) -> &'static str {
+    match format {
+        ImportFormat::Json => "json",
+        ImportFormat::Csv => "csv",
+        ImportFormat::Backup => "backup",
+        ImportFormat::Upsnap => "upsnap",
+        ImportFormat::Wolweb => "wolweb",
+        ImportFormat::Android => "android",
+        ImportFormat::Ethers => "ethers",
+    }
+}
+
+const fn mode_value(mode: ImportMode) -> &'static str {
+    match mode {
+        ImportMode::Append => "append",
+        ImportMode::Upsert => "upsert",
+        ImportMode::Replace => "replace",
+    }
+}
+
+/// What a dry run found, with the button that applies the import. The
+/// button is left out while there are conflicts, as the import would fail.
+fn import_preview(report: &ImportReport) -> Markup {
+    let sections = [
+        ("Create", &report.created),
+        ("Update", &report.updated),
+        ("Unchanged", &report.skipped),
+        ("Conflict", &report.conflicts),
+        ("Move to trash", &report.removed),
+    ];
+    html! {
+        div id="import-preview" class="transfer__preview" {
+            p class="transfer__preview-summary" role="status" {
+                @if let Some(label) = format_label(report.format) {
+                    "Read as " (label) ": "
+                }
+                (report.created.len()) " to create, "
+                (report.updated.len()) " to update, "
+                (report.skipped.len()) " unchanged, "
+                (report.conflicts.len()) " conflicting, "
+                (report.removed.len()) " to move to the trash"
+            }
+            ul class="transfer__preview-list" {
+                @for (action, changes) in sections {
+                    @for change in changes {
+                        (preview_item(action, change))
+                    }
+                }
+            }
+            @if !report.warnings.is_empty() {
+                p class="transfer__preview-summary" {
+                    (report.warnings.len()) " field(s) will not be imported:"
+                }
+                ul class="transfer__problems" {
+                    @for warning in &report.warnings {
+                        li {
+                            code { (warning.field) }
+                            " " (warning.message)
+                        }
+                    }
+                }
+            }
+            @if report.conflicts.is_empty() {
+                button
+                    class="transfer__action-btn"
+                    type="button"
+                    hx-post="/devices/import"
+                    hx-target="#device-grid"
+                    hx-swap="outerHTML" {
+                    (icon(Icon::Upload))
+                    "Confirm import"
+                }
+            } @else {
+                p class="transfer__preview-summary" {
+                    "Resolve the conflicts or pick another mode to import."
+                }
+            }
+        }
+    }
+}
+
+fn format_label(format: Option<ImportFormat>) -> Option<&'static str> {
+    IMPORT_FORMATS
+        .iter()
+        .find(|(known, _)| Some(*known) == format)
+        .map(|(_, label)| *label)
+}
+
+fn preview_item(action: &str, change: &ImportChange) -> Markup {
+    let conflict = action == "Conflict";
+    html! {
+        li class={ "transfer__preview-item" @if conflict { " transfer__preview-item--conflict" } } {
+            span class="transfer__preview-action" { (action) }
+            @if let Some(entry) = &change.entry {
+                code { (entry) }
+            }
+            " " (change.name)
+            @if let Some(reason) = &change.reason {
+                " — " (reason)
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use maud::{Markup, html};
+use time::OffsetDateTime;
+
+use crate::models::Device;
+use crate::web::icons::{Icon, icon_with_label};
+
+use super::devices::{GRID_FILTER, format_last_seen};
+
+/// Lists the devices in the trash, each with a button that restores it.
+pub fn trash_modal(devices: &[Device], retention_days: u32) -> Markup {
+    let now = OffsetDateTime::now_utc();
+
+    html! {
+        dialog
+            class="modal modal__medium"
+            aria-labelledby="trash-modal-title"
+            onclick="jumpCloseModalOnBackdrop(event)" {
+                div class="modal__header" {
+                    h2 id="trash-modal-title" class="modal__title" { "TRASH" }
+                    button
+                        class="modal__close-btn"
+                        type="button"
+                        onclick="jumpCloseModal()"
+                        aria-label="Close" {
+                        (icon_with_label(Icon::X))
+                    }
+                }
+                div class="modal__body" {
+                    p class="trash__note" {
+                        "Removed devices are deleted for good "
+                        (retention_days) " day(s) after they were removed."
+                    }
+                    @if devices.is_empty() {
+                        p class="trash__empty" { "The trash is empty." }
+                    } @else {
+                        ul class="trash__list" {
+                            @for device in devices {
+                                li class="trash__item" {
+                                    div {
+                                        div class="trash__name" { (device.name) }
+                                        div class="trash__meta" {
+                                            "Removed " (format_last_seen(device.deleted_at, now).to_lowercase())
+                                        }
+                                    }
+                                    button
+                                        class="trash__restore-btn"
+                                        type="button"
+                                        hx-post={ "/devices/" (device.id) "/restore" }
+                                        hx-include=(GRID_FILTER)
+                                        hx-target="#device-grid"
+                                        hx-swap="outerHTML"
+                                        aria-label=(format!("Restore {}", device.name)) {
+                                        "RESTORE"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+        }
+    }
+}
@@ -21,7 +21,7 @@ document.body.addEventListener("htmx:afterSwap", () => {
     setTimeout(() => {
       toast.classList.remove("toast__show");
       setTimeout(() => toast.remove(), 450);
-    }, 3800);
+    }, Number(toast.dataset.dismissAfter) || 3800);
   });
 });
 
@@ -124,8 +124,9 @@ function jumpImportDrop(event) {
 
 async function jumpLoadImportFile(file) {
   if (!file) return;
-  if (!file.name.endsWith(".json")) {
-    alert("Only JSON files are supported");
+  // The format is told apart by the server; only binary files are refused.
+  if (file.type && !/^text\/|json/i.test(file.type)) {
+    alert("Only text files such as JSON, CSV or /etc/ethers are supported");
     return;
   }
 
@@ -133,4 +134,63 @@ async function jumpLoadImportFile(file) {
   const fileName = document.getElementById("transfer-file-name");
   if (payload) payload.value = await file.text();
   if (fileName) fileName.textContent = file.name;
+  jumpClearImportPreview();
 }
+
+// A preview only describes the data it was made from, so editing the data or
+// the mode takes it away along with its confirm button.
+function jumpClearImportPreview() {
+  document.getElementById("import-preview")?.remove();
+}
+
+let draggedCard = null;
+let dragStartOrder = "";
+
+function jumpCardOrder(container) {
+  return [...container.querySelectorAll('.device-card input[name="id"]')]
+    .map((input) => input.value)
+    .join(",");
+}
+
+document.addEventListener("dragstart", (event) => {
+  const card = event.target instanceof Element
+    ? event.target.closest('.device-card[draggable="true"]')
+    : null;
+  const container = card?.closest(".device-grid__sortable");
+  if (!card || !container) return;
+
+  draggedCard = card;
+  dragStartOrder = jumpCardOrder(container);
+  card.classList.add("device-card--dragging");
+  event.dataTransfer.effectAllowed = "move";
+  event.dataTransfer.setData("text/plain", card.id);
+});
+
+document.addEventListener("dragover", (event) => {
+  if (!draggedCard || !(event.target instanceof Element)) return;
+  // Cards only move within their own section so favorites stay pinned.
+  const list = event.target.closest("[data-sortable]");
+  if (list !== draggedCard.parentElement) return;
+  event.preventDefault();
+
+  const target = event.target.closest(".device-card");
+  if (!target || target === draggedCard) return;
+  const bounds = target.getBoundingClientRect();
+  const after = event.clientX > bounds.left + bounds.width / 2;
+  list.insertBefore(draggedCard, after ? target.nextSibling : target);
+});
+
+document.addEventListener("drop", (event) => {
+  if (draggedCard) event.preventDefault();
+});
+
+document.addEventListener("dragend", () => {
+  if (!draggedCard) return;
+  const container = draggedCard.closest(".device-grid__sortable");
+  draggedCard.classList.remove("device-card--dragging");
+  draggedCard = null;
+
+  if (container && jumpCardOrder(container) !== dragStartOrder) {
+    htmx.trigger(container, "jump-reorder");
+  }
+});